@@ -0,0 +1,42 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use edi::buffer::Buffer;
+
+const LINE: &str = "fn main() { println!(\"hello world\"); }\n";
+
+fn buffer_with_lines(lines: usize) -> Buffer {
+    let mut buffer = Buffer::from_reader(LINE.repeat(lines).as_bytes()).unwrap();
+
+    for _ in 0..lines / 2 {
+        buffer.move_down();
+    }
+    buffer.move_end_of_line();
+    buffer
+}
+
+fn typing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("typing");
+
+    for lines in [1_000, 10_000, 100_000] {
+        let mut buffer = buffer_with_lines(lines);
+
+        group.bench_with_input(BenchmarkId::new("insert_char", lines), &lines, |b, _| {
+            b.iter(|| {
+                buffer.insert("x");
+                buffer.delete();
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("new_line", lines), &lines, |b, _| {
+            b.iter(|| {
+                buffer.new_line();
+                buffer.delete();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, typing);
+criterion_main!(benches);
@@ -0,0 +1,2 @@
+" the colors edi starts with
+hi clear
@@ -0,0 +1,33 @@
+" warm colors on a dark grey background
+hi clear
+hi Normal       guifg=#ffffff guibg=#333333
+hi MsgArea      guifg=#ffffff
+hi Cursor       guibg=#f0e68ccc
+hi StatusLine   guifg=#333333 guibg=#c2bfa5
+hi StatusLineNC guifg=#7f7f8c guibg=#c2bfa5
+hi VertSplit    guibg=#c2bfa5
+hi LineNr       guifg=#ffff60
+hi CursorLineNr guifg=#ffff60
+hi ErrorMsg     guifg=#ff4040
+hi MoreMsg      guifg=#2e8b57
+hi TabLine      guifg=#333333 guibg=#7f7f8c
+hi TabLineSel   guifg=#333333 guibg=#c2bfa5
+hi TabLineFill  guibg=#4d4d4d
+hi Pmenu        guifg=#ffffff guibg=#4d4d4d
+hi WildMenu     guifg=#000000 guibg=#f0e68c
+hi Visual       guifg=#f0e68c guibg=#6b8e23
+hi Search       guifg=#708090 guibg=#f0e68c
+hi IncSearch    guifg=#708090 guibg=#cd853f
+hi CursorLine   guibg=#4d4d4d
+hi ColorColumn  guibg=#6b2020
+hi NonText      guifg=#add8e6
+hi Comment      guifg=#87ceeb
+hi Constant     guifg=#ffa0a0
+hi String       guifg=#ffa0a0
+hi Identifier   guifg=#98fb98
+hi Statement    guifg=#f0e68c
+hi Type         guifg=#bdb76b
+hi PreProc      guifg=#cd5c5c
+hi Special      guifg=#ffdead
+hi Title        guifg=#cd5c5c
+hi Todo         guifg=#ff4500 guibg=#eeee00
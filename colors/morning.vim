@@ -0,0 +1,33 @@
+" dark text on a light grey background
+hi clear
+hi Normal       guifg=#000000 guibg=#e5e5e5
+hi MsgArea      guifg=#000000
+hi Cursor       guibg=#0000ff80
+hi StatusLine   guifg=#ffffff guibg=#0000ff
+hi StatusLineNC guifg=#000000 guibg=#b3b3b3
+hi VertSplit    guibg=#b3b3b3
+hi LineNr       guifg=#a52a2a
+hi CursorLineNr guifg=#a52a2a
+hi ErrorMsg     guifg=#ffffff guibg=#ff0000
+hi MoreMsg      guifg=#2e8b57
+hi TabLine      guifg=#000000 guibg=#b3b3b3
+hi TabLineSel   guifg=#ffffff guibg=#0000ff
+hi TabLineFill  guibg=#cccccc
+hi Pmenu        guifg=#000000 guibg=#cccccc
+hi WildMenu     guifg=#000000 guibg=#ffff00
+hi Visual       guibg=#cccccc
+hi Search       guibg=#ffff00
+hi IncSearch    guibg=#ff8000
+hi CursorLine   guibg=#e5e5e5
+hi ColorColumn  guibg=#ffd0d0
+hi NonText      guifg=#0000ff
+hi Comment      guifg=#0000ff
+hi Constant     guifg=#ff00ff
+hi String       guifg=#ff00ff
+hi Identifier   guifg=#008b8b
+hi Statement    guifg=#a52a2a
+hi Type         guifg=#2e8b57
+hi PreProc      guifg=#a020f0
+hi Special      guifg=#6a5acd
+hi Title        guifg=#a020f0
+hi Todo         guifg=#0000ff guibg=#ffff00
@@ -0,0 +1,87 @@
+use crate::errors::EdiError;
+
+#[derive(Debug, PartialEq)]
+pub enum StartPos {
+    Line(usize),
+    LastLine,
+    Search(String),
+}
+
+// edi [-R] [+N | +/pattern] [file | -]...
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub files: Vec<String>,
+    pub readonly: bool,
+    pub start: Option<StartPos>,
+}
+
+pub const STDIN: &str = "-";
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, EdiError> {
+        let mut result = Args {
+            files: Vec::new(),
+            readonly: false,
+            start: None,
+        };
+        let mut only_files = false;
+
+        for arg in args {
+            if only_files || arg == STDIN {
+                result.files.push(arg);
+            } else if arg == "--" {
+                only_files = true;
+            } else if arg == "-R" {
+                result.readonly = true;
+            } else if let Some(pos) = arg.strip_prefix('+') {
+                result.start = Some(if pos.is_empty() {
+                    StartPos::LastLine
+                } else if let Some(pattern) = pos.strip_prefix('/') {
+                    StartPos::Search(pattern.to_string())
+                } else {
+                    let line = pos
+                        .parse::<usize>()
+                        .map_err(|_| EdiError::InvalidArgument(arg.clone()))?;
+                    StartPos::Line(line)
+                });
+            } else if arg.starts_with('-') {
+                return Err(EdiError::InvalidArgument(arg));
+            } else {
+                result.files.push(arg);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, StartPos};
+
+    fn parse(args: &[&str]) -> Option<Args> {
+        Args::parse(args.iter().map(|arg| arg.to_string())).ok()
+    }
+
+    #[test]
+    fn files_and_options() {
+        assert_eq!(
+            parse(&["-R", "foo", "+12", "-", "--", "-bar"]),
+            Some(Args {
+                files: vec!["foo".into(), "-".into(), "-bar".into()],
+                readonly: true,
+                start: Some(StartPos::Line(12)),
+            })
+        );
+
+        let args = parse(&["+/fn main", "src/main.rs"]).unwrap();
+        assert_eq!(args.start, Some(StartPos::Search("fn main".into())));
+        assert_eq!(parse(&["+"]).unwrap().start, Some(StartPos::LastLine));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(parse(&["-x"]), None);
+        assert_eq!(parse(&["+1a"]), None);
+    }
+}
@@ -0,0 +1,1604 @@
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::ops::Range;
+
+use crate::errors::EdiError;
+use crate::render::V2;
+use crate::syntax::{self, Span, Syntax};
+use crate::text::Text;
+#[cfg(feature = "tree-sitter")]
+use crate::tree::{self, SyntaxTree};
+use crate::undo::{Change, History};
+
+pub struct Buffer {
+    id: usize,
+    path: Option<String>,
+    text: Text,
+    lines: Vec<Line>,
+    longest_line: usize,
+    // counts the changes to the text
+    generation: usize,
+    cursor: Pos,
+    history: History,
+    readonly: bool,
+    syntax: Syntax,
+    #[cfg(feature = "tree-sitter")]
+    tree: Option<SyntaxTree>,
+}
+
+// layout of lines longer than `width` columns in several rows with 'wrap'
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wrap {
+    pub width: usize,
+    // rows break before a word instead of at any column, as 'linebreak'
+    pub linebreak: bool,
+    // columns of 'showbreak' in front of the continued rows
+    pub prefix: usize,
+}
+
+// structural text objects given by the syntax tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextObject {
+    Function,
+    Class,
+    Argument,
+}
+
+pub struct LineIter<'a> {
+    buffer: &'a Buffer,
+    idx: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for LineIter<'a> {
+    type Item = WordIter<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx < self.end {
+            let idx = self.idx;
+            self.idx += 1;
+            Some(WordIter {
+                buffer: self.buffer,
+                line: &self.buffer.lines[idx],
+                start: self.buffer.text.line_to_byte(idx),
+                idx: 0,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+pub struct WordIter<'a> {
+    buffer: &'a Buffer,
+    line: &'a Line,
+    start: usize,
+    idx: usize,
+}
+
+impl<'a> Iterator for WordIter<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx < self.line.tokens.len() {
+            let token = &self.line.tokens[self.idx];
+            let start = self.start + token.idx();
+            let end = start + token.len();
+            self.idx += 1;
+            Some(self.buffer.text.slice(start..end))
+        } else {
+            None
+        }
+    }
+}
+
+// token offsets are relative to the start of the line so that an edit
+// never has to touch the lines following it
+struct Line {
+    tokens: Vec<Token>,
+}
+
+impl Line {
+    fn tokenize(text: &Text, line: usize) -> Line {
+        let tokens = Tokenizer::new(text, text.line_to_byte(line))
+            .take_while(|token| !matches!(token, Token::Newline { .. }))
+            .collect();
+
+        Line { tokens }
+    }
+
+    fn len(&self) -> usize {
+        self.tokens
+            .last()
+            .map(|token| token.idx() + token.len())
+            .unwrap_or(0)
+    }
+
+    fn current_word(&self, col: usize) -> Option<&Token> {
+        self.tokens.iter().find(|token| match token {
+            Token::Word {
+                idx: token_start,
+                len,
+            } => token_start + len > col,
+            _ => false,
+        })
+    }
+
+    fn next_word(&self, col: usize) -> Option<&Token> {
+        self.tokens.iter().find(|token| match token {
+            Token::Word {
+                idx: token_start,
+                len: _,
+            } => *token_start > col,
+            _ => false,
+        })
+    }
+
+    fn prev_word(&self, col: usize) -> Option<&Token> {
+        self.tokens.iter().rev().find(|token| match token {
+            Token::Word {
+                idx: token_start,
+                len,
+            } => token_start + len <= col,
+            _ => false,
+        })
+    }
+}
+
+enum Token {
+    Word { idx: usize, len: usize },
+    Space { idx: usize, len: usize },
+    Newline { idx: usize },
+}
+
+impl Token {
+    fn len(&self) -> usize {
+        match self {
+            Token::Word { idx: _, len } => *len,
+            Token::Space { idx: _, len } => *len,
+            Token::Newline { .. } => 1,
+        }
+    }
+
+    fn idx(&self) -> usize {
+        match self {
+            Token::Word { idx, len: _ } => *idx,
+            Token::Space { idx, len: _ } => *idx,
+            Token::Newline { idx } => *idx,
+        }
+    }
+
+    fn start_pos(&self, line_start: usize, line_idx: usize) -> Pos {
+        let col = self.idx();
+        Pos {
+            idx: line_start + col,
+            line: line_idx,
+            col,
+        }
+    }
+
+    fn end_pos(&self, line_start: usize, line_idx: usize) -> Pos {
+        let col = self.idx() + self.len() - 1;
+        Pos {
+            idx: line_start + col,
+            line: line_idx,
+            col,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Pos {
+    idx: usize,
+    line: usize,
+    col: usize,
+}
+
+impl Pos {
+    fn new_line(&mut self) {
+        self.idx += 1;
+        self.col = 0;
+        self.line += 1;
+    }
+
+    fn line_start(&self) -> usize {
+        self.idx - self.col
+    }
+}
+
+// (file type, file extensions)
+const FILETYPES: [(&str, &[&str]); 8] = [
+    ("rust", &["rs"]),
+    ("toml", &["toml"]),
+    ("markdown", &["md", "markdown"]),
+    ("json", &["json"]),
+    ("glsl", &["glsl", "vert", "frag"]),
+    ("sh", &["sh", "bash"]),
+    ("c", &["c", "h"]),
+    ("python", &["py"]),
+];
+
+// (file type, interpreters of a #! line)
+const INTERPRETERS: [(&str, &[&str]); 2] = [
+    ("sh", &["sh", "bash", "dash", "ksh", "zsh"]),
+    ("python", &["python", "python3"]),
+];
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer {
+            id: 0,
+            path: None,
+            text: Text::new(),
+            lines: vec![Line { tokens: Vec::new() }],
+            longest_line: 0,
+            generation: 0,
+            cursor: Pos {
+                idx: 0,
+                line: 0,
+                col: 0,
+            },
+            history: History::new(),
+            readonly: false,
+            syntax: Syntax::new(None),
+            #[cfg(feature = "tree-sitter")]
+            tree: None,
+        }
+    }
+
+    // a file that does not exist yet is created on the first write
+    pub fn from_file(path: &str) -> Result<Buffer, EdiError> {
+        let mut buffer = match std::fs::File::open(path) {
+            Ok(file) => Self::from_reader(std::io::BufReader::new(file))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::new(),
+            Err(err) => return Err(err.into()),
+        };
+        buffer.set_path(path);
+        Ok(buffer)
+    }
+
+    // returns the number of bytes written
+    pub fn write(&mut self) -> Result<usize, EdiError> {
+        let path = self.path.clone().ok_or(EdiError::NoFileName)?;
+        let written = self.write_file(&path)?;
+
+        self.history.mark_saved();
+        Ok(written)
+    }
+
+    pub fn write_file(&self, path: &str) -> Result<usize, EdiError> {
+        let file = std::fs::File::create(path)?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        self.text.write_to(&mut writer)?;
+        std::io::Write::flush(&mut writer)?;
+
+        Ok(self.text.len())
+    }
+
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Buffer, EdiError> {
+        let mut buffer = Self::new();
+
+        buffer.text = Text::from_reader(reader)?;
+        buffer.tokenize();
+        buffer.detect_syntax();
+
+        Ok(buffer)
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn name(&self) -> &str {
+        self.path().unwrap_or("[No Name]")
+    }
+
+    // file type from the extension of the file name or the interpreter
+    // of a #! line
+    pub fn filetype(&self) -> Option<&'static str> {
+        let ext = self
+            .path()
+            .and_then(|path| path.rsplit_once('.'))
+            .map(|(_, ext)| ext);
+        let by_ext = FILETYPES
+            .iter()
+            .find(|(_, exts)| ext.is_some_and(|ext| exts.contains(&ext)));
+
+        let first_line = self.text.line(0);
+        let mut words = first_line
+            .strip_prefix("#!")
+            .unwrap_or("")
+            .split_whitespace();
+        let mut interpreter = words.next().and_then(|path| path.rsplit('/').next());
+        if interpreter == Some("env") {
+            interpreter = words.find(|word| !word.starts_with('-'));
+        }
+        let by_interpreter = || {
+            INTERPRETERS
+                .iter()
+                .find(|(_, names)| interpreter.is_some_and(|name| names.contains(&name)))
+        };
+
+        by_ext
+            .or_else(by_interpreter)
+            .map(|(filetype, _)| *filetype)
+    }
+
+    // highlighted parts of a line
+    pub fn spans(&self, line: usize) -> &[Span] {
+        self.syntax.spans(line)
+    }
+
+    // highlights all lines again if the file type changed
+    fn detect_syntax(&mut self) {
+        #[cfg(feature = "tree-sitter")]
+        if self.detect_tree() {
+            return;
+        }
+        let language = self.filetype().and_then(syntax::language);
+        if language.map(|language| language.name)
+            != self.syntax.language().map(|language| language.name)
+        {
+            self.syntax = Syntax::new(language);
+            let text = &self.text;
+            self.syntax
+                .highlight((0..text.line_count()).map(|line| text.line(line)));
+        }
+    }
+
+    // parses the text again if the file type changed, returning whether
+    // there is a grammar for it
+    #[cfg(feature = "tree-sitter")]
+    fn detect_tree(&mut self) -> bool {
+        let grammar = self.filetype().and_then(tree::grammar);
+        if grammar.map(|grammar| grammar.filetype) != self.tree.as_ref().map(SyntaxTree::filetype) {
+            self.tree = grammar.map(|grammar| SyntaxTree::new(grammar, &self.text));
+            self.syntax = Syntax::new(None);
+            if let Some(tree) = &self.tree {
+                let count = self.text.line_count();
+                self.syntax.splice(0, 0, count);
+                for line in 0..count {
+                    self.syntax.set_spans(line, tree.spans(&self.text, line));
+                }
+            }
+        }
+        self.tree.is_some()
+    }
+
+    // highlights the lines changed by an edit using the syntax tree, if
+    // there is one
+    #[cfg(feature = "tree-sitter")]
+    fn edit_tree(
+        &mut self,
+        edit: &tree_sitter::InputEdit,
+        first: usize,
+        removed: usize,
+        added: usize,
+    ) -> bool {
+        let Some(tree) = self.tree.as_mut() else {
+            return false;
+        };
+        let mut lines = tree.edit(&self.text, edit);
+        lines.extend(first..first + added);
+
+        self.syntax.splice(first, removed, added);
+        for line in lines {
+            self.syntax.set_spans(line, tree.spans(&self.text, line));
+        }
+        true
+    }
+
+    // outline of the syntax tree as shown by :InspectTree
+    pub fn inspect_tree(&self) -> Option<String> {
+        #[cfg(feature = "tree-sitter")]
+        return self.tree.as_ref().map(SyntaxTree::inspect);
+        #[cfg(not(feature = "tree-sitter"))]
+        None
+    }
+
+    // line ending style as in vim's 'fileformat', given by the first line
+    pub fn fileformat(&self) -> &'static str {
+        let len = self.text.line_len(0);
+        if len > 0 && self.text.slice(len - 1..len) == "\r" {
+            "dos"
+        } else {
+            "unix"
+        }
+    }
+
+    pub fn set_path(&mut self, path: &str) {
+        self.path = Some(path.to_string());
+        self.detect_syntax();
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified()
+    }
+
+    pub fn cursor(&self) -> V2 {
+        V2 {
+            x: self.cursor.col as f32,
+            y: self.cursor.line as f32,
+        }
+    }
+
+    pub fn cursor_pos(&self) -> (usize, usize) {
+        (self.cursor.line, self.cursor.col)
+    }
+
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    pub fn iter(&self) -> LineIter<'_> {
+        self.iter_lines(0..self.lines.len())
+    }
+
+    pub fn iter_lines(&self, lines: Range<usize>) -> LineIter<'_> {
+        let end = lines.end.min(self.lines.len());
+        LineIter {
+            buffer: self,
+            idx: lines.start.min(end),
+            end,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    // text of a line without its line break
+    pub fn line_text(&self, line: usize) -> Cow<'_, str> {
+        self.text.line(line)
+    }
+
+    // byte length of a line without its line break
+    pub fn line_len(&self, line: usize) -> usize {
+        self.lines.get(line).map_or(0, Line::len)
+    }
+
+    pub fn longest_line(&self) -> usize {
+        self.longest_line
+    }
+
+    // number of changes to the text so far
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn move_left(&mut self) {
+        if self.cursor.col > 0 {
+            self.move_to_byte(self.text.prev_char(self.cursor.idx));
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.cursor.col < self.line().len() {
+            self.move_to_byte(self.text.next_char(self.cursor.idx));
+        }
+    }
+
+    pub fn next_word_end(&mut self) {
+        let line = self.line();
+        let col = self.cursor.col;
+        let line_next_word = line
+            .current_word(col)
+            .filter(|token| token.idx() + token.len() > col + 1)
+            .or_else(|| line.next_word(col))
+            .map(|token| token.end_pos(self.cursor.line_start(), self.cursor.line));
+
+        if let Some(next) = line_next_word.or_else(|| {
+            self.next_line().and_then(|line| {
+                let line_idx = self.cursor.line + 1;
+                line.tokens
+                    .first()
+                    .map(|token| token.end_pos(self.text.line_to_byte(line_idx), line_idx))
+            })
+        }) {
+            // the last character of a word may take up several bytes
+            self.move_to_byte(next.idx);
+        } else {
+            self.move_down();
+        }
+    }
+
+    pub fn next_word(&mut self) {
+        let line = self.line();
+        let line_next_word = line
+            .next_word(self.cursor.col)
+            .map(|token| token.start_pos(self.cursor.line_start(), self.cursor.line));
+
+        if let Some(next) = line_next_word.or_else(|| {
+            self.next_line().and_then(|line| {
+                let line_idx = self.cursor.line + 1;
+                line.tokens
+                    .first()
+                    .map(|token| token.start_pos(self.text.line_to_byte(line_idx), line_idx))
+            })
+        }) {
+            self.cursor = next;
+        } else {
+            self.move_down();
+        }
+    }
+
+    pub fn prev_word(&mut self) {
+        let line = self.line();
+        let col = self.cursor.col;
+        let line_prev_word = line
+            .current_word(col)
+            .filter(|token| token.idx() < col)
+            .or_else(|| line.prev_word(col))
+            .map(|token| token.start_pos(self.cursor.line_start(), self.cursor.line));
+
+        if let Some(next) = line_prev_word.or_else(|| {
+            self.prev_line().and_then(|line| {
+                let line_idx = self.cursor.line - 1;
+                line.tokens
+                    .last()
+                    .map(|token| token.start_pos(self.text.line_to_byte(line_idx), line_idx))
+            })
+        }) {
+            self.cursor = next;
+        } else {
+            self.move_up();
+        }
+    }
+
+    pub fn open_line_above(&mut self) {
+        let line_start = self.cursor.line_start();
+
+        self.edit(line_start..line_start, "\n");
+
+        self.cursor.col = 0;
+        self.cursor.idx = line_start;
+    }
+
+    pub fn open_line_below(&mut self) {
+        let line_end = self.cursor.line_start() + self.line().len();
+
+        self.edit(line_end..line_end, "\n");
+
+        self.cursor.line += 1;
+        self.cursor.col = 0;
+        self.cursor.idx = line_end + 1;
+    }
+
+    // text as new lines below the cursor line, moving to the first of them
+    pub fn insert_below(&mut self, text: &str) {
+        let line_end = self.cursor.line_start() + self.line().len();
+        let text = text.strip_suffix('\n').unwrap_or(text);
+
+        self.edit(line_end..line_end, &format!("\n{text}"));
+
+        self.cursor.line += 1;
+        self.cursor.col = 0;
+        self.cursor.idx = line_end + 1;
+    }
+
+    fn line(&self) -> &Line {
+        &self.lines[self.cursor.line]
+    }
+
+    fn next_line(&self) -> Option<&Line> {
+        if self.lines.len() > self.cursor.line + 1 {
+            Some(&self.lines[self.cursor.line + 1])
+        } else {
+            None
+        }
+    }
+
+    fn prev_line(&self) -> Option<&Line> {
+        if self.cursor.line > 0 {
+            Some(&self.lines[self.cursor.line - 1])
+        } else {
+            None
+        }
+    }
+
+    pub fn move_start_of_line(&mut self) {
+        self.cursor.idx = self.cursor.line_start();
+        self.cursor.col = 0;
+    }
+
+    pub fn move_end_of_line(&mut self) {
+        let line_start = self.cursor.line_start();
+
+        self.cursor.col = self.line().len();
+        self.cursor.idx = line_start + self.cursor.col;
+    }
+
+    pub fn move_down(&mut self) {
+        if self.cursor.line + 1 < self.lines.len() {
+            self.cursor.line += 1;
+            self.move_to_column(self.cursor.col);
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.cursor.line > 0 {
+            self.cursor.line -= 1;
+            self.move_to_column(self.cursor.col);
+        }
+    }
+
+    // moves the cursor to a byte column of its line, onto the start of
+    // the character it falls into
+    fn move_to_column(&mut self, col: usize) {
+        let line_start = self.text.line_to_byte(self.cursor.line);
+        let idx = self
+            .text
+            .char_start(line_start + col.min(self.line().len()));
+
+        self.cursor.col = idx - line_start;
+        self.cursor.idx = idx;
+    }
+
+    pub fn goto_line(&mut self, line: usize) {
+        self.set_cursor_pos(line, 0);
+    }
+
+    pub fn set_cursor_pos(&mut self, line: usize, col: usize) {
+        self.cursor.line = line.min(self.lines.len() - 1);
+        self.move_to_column(col);
+    }
+
+    // byte offset of the next occurrence of `pattern` at or after `from`,
+    // wrapping around at the end of the buffer
+    pub fn find(&self, pattern: &str, from: usize) -> Option<usize> {
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let from_line = self.text.byte_to_line(from);
+        let line_count = self.lines.len();
+
+        (0..=line_count).find_map(|offset| {
+            let line = (from_line + offset) % line_count;
+            let start = self.text.line_to_byte(line);
+            let end = start + self.lines[line].len();
+            let skip = if offset == 0 {
+                (from - start).min(end - start)
+            } else {
+                0
+            };
+
+            let idx = start + skip + self.text.slice(start + skip..end).find(pattern)?;
+            // the first line is searched again up to `from` after wrapping
+            (offset < line_count || idx < from).then_some(idx)
+        })
+    }
+
+    // start of the last match of the pattern in front of `before`,
+    // wrapping around to the end of the text
+    pub fn rfind(&self, pattern: &str, before: usize) -> Option<usize> {
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let before_line = self.text.byte_to_line(before);
+        let line_count = self.lines.len();
+
+        (0..=line_count).find_map(|offset| {
+            let line = (before_line + line_count - offset % line_count) % line_count;
+            let start = self.text.line_to_byte(line);
+            let end = start + self.lines[line].len();
+
+            // the first line is searched again from `before` after wrapping
+            self.text
+                .slice(start..end)
+                .match_indices(pattern)
+                .map(|(idx, _)| start + idx)
+                .filter(|idx| match offset {
+                    0 => *idx < before,
+                    _ if offset == line_count => *idx >= before,
+                    _ => true,
+                })
+                .last()
+        })
+    }
+
+    // byte offsets of the rows a line takes up, each row holding up to
+    // `width` characters
+    pub fn rows(&self, line: usize, wrap: &Wrap) -> Vec<usize> {
+        let Some(tokens) = self.lines.get(line).map(|line| &line.tokens) else {
+            return vec![0];
+        };
+        let mut rows = vec![0];
+        // a line has at least as many bytes as characters
+        if self.line_len(line) <= wrap.width {
+            return rows;
+        }
+
+        let text = self.text.line(line);
+        let chars = text.char_indices().map(|(idx, _)| idx).collect::<Vec<_>>();
+        let mut start = 0;
+
+        loop {
+            let width = match rows.len() {
+                1 => wrap.width,
+                _ => wrap.width.saturating_sub(wrap.prefix),
+            }
+            .max(1);
+            if chars.len() - start <= width {
+                return rows;
+            }
+
+            let mut end = start + width;
+            if wrap.linebreak {
+                let (first, last) = (chars[start], chars[end]);
+                let word = tokens.iter().rev().find(|token| {
+                    matches!(token, Token::Word { .. })
+                        && token.idx() > first
+                        && token.idx() <= last
+                });
+                if let Some(word) = word {
+                    end = chars.partition_point(|idx| *idx < word.idx());
+                }
+            }
+            rows.push(chars[end]);
+            start = end;
+        }
+    }
+
+    // moves the cursor by a row of the wrapped lines, like gj and gk, keeping
+    // its character column within the row
+    pub fn move_row(&mut self, down: bool, wrap: &Wrap) {
+        let line = self.cursor.line;
+        let line_start = self.text.line_to_byte(line);
+        let col = self.text.char_start(self.cursor.idx) - line_start;
+        let prefix = |row: usize| if row > 0 { wrap.prefix } else { 0 };
+        let rows = self.rows(line, wrap);
+        let row = rows.partition_point(|start| *start <= col) - 1;
+        let row_col = self.text.byte_to_char(line_start + col)
+            - self.text.byte_to_char(line_start + rows[row])
+            + prefix(row);
+
+        let (line, rows, row) = if down && row + 1 < rows.len() {
+            (line, rows, row + 1)
+        } else if down && line + 1 < self.lines.len() {
+            (line + 1, self.rows(line + 1, wrap), 0)
+        } else if !down && row > 0 {
+            (line, rows, row - 1)
+        } else if !down && line > 0 {
+            let rows = self.rows(line - 1, wrap);
+            let last = rows.len() - 1;
+            (line - 1, rows, last)
+        } else {
+            return;
+        };
+
+        let text = self.text.line(line);
+        let start = rows[row];
+        let end = rows.get(row + 1).copied().unwrap_or(text.len());
+        let mut chars = text[start..end].char_indices();
+        // past the end of a row the cursor stays on its last character
+        let col = match chars.nth(row_col.saturating_sub(prefix(row))) {
+            Some((idx, _)) => start + idx,
+            None if row + 1 < rows.len() => {
+                start
+                    + text[start..end]
+                        .char_indices()
+                        .last()
+                        .map_or(0, |(idx, _)| idx)
+            }
+            None => end,
+        };
+        self.cursor.line = line;
+        self.move_to_column(col);
+    }
+
+    // (column, character, whether it ends the line) of the spaces and tabs
+    // of a line
+    pub fn blanks(&self, line: usize) -> Vec<(usize, char, bool)> {
+        let Some(tokens) = self.lines.get(line).map(|line| &line.tokens) else {
+            return Vec::new();
+        };
+        let start = self.text.line_to_byte(line);
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| matches!(token, Token::Space { .. }))
+            .flat_map(|(idx, token)| {
+                let trailing = idx + 1 == tokens.len();
+                let text = self
+                    .text
+                    .slice(start + token.idx()..start + token.idx() + token.len());
+                text.bytes()
+                    .enumerate()
+                    .map(|(offset, byte)| (token.idx() + offset, byte as char, trailing))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // (line, column range) of every match of `pattern` in the given lines
+    pub fn matches(&self, pattern: &str, lines: Range<usize>) -> Vec<(usize, Range<usize>)> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        let end = lines.end.min(self.lines.len());
+        (lines.start.min(end)..end)
+            .flat_map(|line| {
+                self.text
+                    .line(line)
+                    .match_indices(pattern)
+                    .map(|(col, _)| (line, col..col + pattern.len()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // moves the cursor to the next match of `pattern` after it
+    pub fn search(&mut self, pattern: &str) -> bool {
+        let from = self.text.next_char(self.cursor.idx);
+        match self.find(pattern, from) {
+            Some(idx) => {
+                self.move_to_byte(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn search_backward(&mut self, pattern: &str) -> bool {
+        match self.rfind(pattern, self.cursor.idx) {
+            Some(idx) => {
+                self.move_to_byte(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn move_to_byte(&mut self, idx: usize) {
+        (self.cursor.line, self.cursor.col) = self.text.byte_to_pos(idx.min(self.text.len()));
+        self.move_to_column(self.cursor.col);
+    }
+
+    pub fn new_line(&mut self) {
+        self.edit(self.cursor.idx..self.cursor.idx, "\n");
+        self.cursor.new_line();
+    }
+
+    pub fn insert(&mut self, input: &str) {
+        self.edit(self.cursor.idx..self.cursor.idx, input);
+        self.cursor.idx += input.len();
+        (self.cursor.line, self.cursor.col) = self.text.byte_to_pos(self.cursor.idx);
+    }
+
+    pub fn delete_char(&mut self) {
+        if self.cursor.idx < self.text.len() && self.cursor.col < self.line().len() {
+            self.edit(self.cursor.idx..self.text.next_char(self.cursor.idx), "");
+        }
+    }
+
+    pub fn delete_line(&mut self) {
+        let line = self.cursor.line;
+        let start_idx = self.cursor.line_start();
+        let end_idx = start_idx + self.line().len();
+
+        if line + 1 < self.lines.len() {
+            self.edit(start_idx..end_idx + 1, "");
+        } else {
+            self.edit(start_idx.max(1) - 1..end_idx, "");
+            self.cursor.line = line.max(1) - 1;
+        }
+
+        self.move_to_column(self.cursor.col);
+    }
+
+    pub fn delete_word(&mut self) {
+        let line = self.line();
+        let col = self.cursor.col;
+        let end = line
+            .next_word(col)
+            .map(|token| token.idx())
+            .unwrap_or(line.len());
+
+        if end > col {
+            self.edit(self.cursor.idx..self.cursor.idx + end - col, "");
+        }
+    }
+
+    // deletes the text object under the cursor, like daf or dia in vim
+    pub fn delete_object(&mut self, object: TextObject, around: bool) {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(range) = self
+            .tree
+            .as_ref()
+            .and_then(|tree| tree.object(&self.text, object, around, self.cursor.idx))
+        {
+            self.edit(range.clone(), "");
+            self.move_to_byte(range.start);
+        }
+        #[cfg(not(feature = "tree-sitter"))]
+        let _ = (object, around);
+    }
+
+    // moves to the start of the next or previous function
+    pub fn next_function(&mut self, forward: bool) {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(idx) = self
+            .tree
+            .as_ref()
+            .and_then(|tree| tree.function(self.cursor.idx, forward))
+        {
+            self.move_to_byte(idx);
+        }
+        #[cfg(not(feature = "tree-sitter"))]
+        let _ = forward;
+    }
+
+    // :s/pattern/replacement/[g] on the current line
+    pub fn substitute(&mut self, args: &str) {
+        let mut chars = args.chars();
+        let Some(delimiter) = chars.next() else {
+            return;
+        };
+
+        let mut parts = chars.as_str().splitn(3, delimiter);
+        let pattern = parts.next().unwrap_or("");
+        let replacement = parts.next().unwrap_or("");
+        let global = parts.next().is_some_and(|flags| flags.contains('g'));
+
+        if pattern.is_empty() {
+            return;
+        }
+
+        let line_start = self.cursor.line_start();
+        let line_end = line_start + self.line().len();
+        let line = self.text.slice(line_start..line_end);
+
+        if line.contains(pattern) {
+            let replaced = if global {
+                line.replace(pattern, replacement)
+            } else {
+                line.replacen(pattern, replacement, 1)
+            };
+
+            self.edit(line_start..line_end, &replaced);
+            self.move_start_of_line();
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor.idx > 0 && self.cursor.idx <= self.text.len() {
+            let end = self.cursor.idx;
+            self.cursor.idx = self.text.prev_char(end);
+            self.edit(self.cursor.idx..end, "");
+
+            // update cursor position
+            (self.cursor.line, self.cursor.col) = self.text.byte_to_pos(self.cursor.idx);
+        }
+    }
+
+    // close the current undo step
+    pub fn commit(&mut self) {
+        self.history.commit();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(changes) = self.history.undo() else {
+            return false;
+        };
+
+        for change in changes.iter().rev() {
+            self.apply(
+                change.idx..change.idx + change.inserted.len(),
+                &change.removed,
+            );
+        }
+        if let Some(first) = changes.first() {
+            self.move_to_byte(first.idx);
+        }
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(changes) = self.history.redo() else {
+            return false;
+        };
+
+        for change in &changes {
+            self.apply(
+                change.idx..change.idx + change.removed.len(),
+                &change.inserted,
+            );
+        }
+        if let Some(first) = changes.first() {
+            self.move_to_byte(first.idx);
+        }
+        true
+    }
+
+    fn edit(&mut self, range: Range<usize>, text: &str) {
+        debug_assert!(
+            self.text.char_start(range.start) == range.start
+                && self.text.char_start(range.end) == range.end,
+            "edit of {range:?} does not start and end on characters"
+        );
+        if range.is_empty() && text.is_empty() {
+            return;
+        }
+        self.history.record(Change {
+            idx: range.start,
+            removed: self.text.slice(range.clone()).into_owned(),
+            inserted: text.to_string(),
+        });
+        self.apply(range, text);
+    }
+
+    // replace the given byte range with `text` and re-tokenize the
+    // affected lines only
+    fn apply(&mut self, range: Range<usize>, text: &str) {
+        let first_line = self.text.byte_to_line(range.start);
+        let last_line = self.text.byte_to_line(range.end);
+        #[cfg(feature = "tree-sitter")]
+        let edit = tree::input_edit(&self.text, range.clone(), text);
+
+        if !range.is_empty() {
+            self.text.remove(range.clone());
+        }
+        if !text.is_empty() {
+            self.text.insert(range.start, text);
+        }
+
+        let new_last_line = self.text.byte_to_line(range.start + text.len());
+        let lines = (first_line..=new_last_line)
+            .map(|line| Line::tokenize(&self.text, line))
+            .collect::<Vec<_>>();
+
+        let removed_longest = self.lines[first_line..=last_line]
+            .iter()
+            .map(Line::len)
+            .max()
+            .unwrap_or(0);
+        let added_longest = lines.iter().map(Line::len).max().unwrap_or(0);
+
+        self.lines.splice(first_line..=last_line, lines);
+        self.generation += 1;
+
+        let removed = last_line - first_line + 1;
+        let added = new_last_line - first_line + 1;
+        #[cfg(feature = "tree-sitter")]
+        let highlighted = self.edit_tree(&edit, first_line, removed, added);
+        #[cfg(not(feature = "tree-sitter"))]
+        let highlighted = false;
+        if !highlighted {
+            let text = &self.text;
+            self.syntax.edit(first_line, removed, added, |line| {
+                (line < text.line_count()).then(|| text.line(line))
+            });
+        }
+        if first_line == 0 {
+            self.detect_syntax();
+        }
+
+        if added_longest >= self.longest_line {
+            self.longest_line = added_longest;
+        } else if removed_longest == self.longest_line {
+            self.update_longest_line();
+        }
+    }
+
+    fn tokenize(&mut self) {
+        self.lines = (0..self.text.line_count())
+            .map(|line| Line::tokenize(&self.text, line))
+            .collect();
+        self.generation += 1;
+        self.update_longest_line();
+    }
+
+    fn update_longest_line(&mut self) {
+        self.longest_line = self.lines.iter().map(Line::len).max().unwrap_or(0);
+    }
+}
+
+struct Tokenizer<'a> {
+    idx: usize,
+    bytes: Peekable<ropey::iter::Bytes<'a>>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(text: &'a Text, start: usize) -> Tokenizer<'a> {
+        Tokenizer {
+            idx: 0,
+            bytes: text.bytes_at(start).peekable(),
+        }
+    }
+
+    fn take_word(&mut self) -> Option<Token> {
+        let start = self.idx;
+        while let Some(&current) = self.bytes.peek() {
+            if matches!(current, b' ' | b'\t' | b'\n') {
+                break;
+            }
+            self.bytes.next();
+            self.idx += 1;
+        }
+        Some(Token::Word {
+            idx: start,
+            len: self.idx - start,
+        })
+    }
+
+    fn take_space(&mut self) -> Option<Token> {
+        let start = self.idx;
+        let mut len = 0usize;
+        // TODO: handle other whitespace than spaces and tabs
+        while self
+            .bytes
+            .next_if(|byte| matches!(byte, b' ' | b'\t'))
+            .is_some()
+        {
+            self.idx += 1;
+            len += 1;
+        }
+        Some(Token::Space { idx: start, len })
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        match self.bytes.peek() {
+            None => None,
+            Some(b' ' | b'\t') => self.take_space(),
+            Some(10) => {
+                let idx = self.idx;
+                self.bytes.next();
+                self.idx += 1;
+                Some(Token::Newline { idx })
+            }
+            Some(_) => self.take_word(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tree-sitter")]
+    use super::TextObject;
+    use super::{Buffer, Pos, Wrap};
+    use crate::highlight::Group;
+
+    #[test]
+    fn tokenize_one_word() {
+        let mut e = Buffer::new();
+        e.insert("test");
+
+        assert_eq!(join(&e), vec!["test"])
+    }
+
+    #[test]
+    fn tokenize_multiple_words() {
+        let mut e = Buffer::new();
+        e.insert("foo bar ham");
+
+        assert_eq!(join(&e), vec!["foo bar ham"])
+    }
+
+    #[test]
+    fn tokenize_multiple_spaces() {
+        let mut e = Buffer::new();
+        e.insert(" ham   eggs ");
+
+        assert_eq!(join(&e), vec![" ham   eggs "])
+    }
+
+    #[test]
+    fn tokenize_multiple_lines() {
+        let mut e = Buffer::new();
+        e.insert("foo\nbar");
+
+        assert_eq!(join(&e), vec!["foo", "bar"])
+    }
+
+    #[test]
+    fn tokenize_empty_lines() {
+        let mut e = Buffer::new();
+        e.insert("\n\n\n");
+
+        assert_eq!(join(&e), vec!["", "", "", ""])
+    }
+
+    #[test]
+    fn tokenize_empty() {
+        let mut e = Buffer::new();
+        e.insert("");
+
+        assert_eq!(join(&e), vec![""])
+    }
+
+    #[test]
+    fn tokenize_delete_single_line() {
+        let mut e = Buffer::new();
+        e.insert("fooo");
+        e.delete();
+
+        assert_eq!(join(&e), vec!["foo"])
+    }
+
+    #[test]
+    fn tokenize_delete_multiline() {
+        let mut e = Buffer::new();
+        e.insert("f");
+        e.new_line();
+        e.delete();
+        e.insert("oobar");
+
+        assert_eq!(join(&e), vec!["foobar"])
+    }
+
+    #[test]
+    fn cursor_pos() {
+        let mut e = Buffer::new();
+        e.insert("foo");
+
+        assert_eq!(join(&e), vec!["foo"]);
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 3,
+                col: 3,
+                line: 0
+            }
+        );
+
+        e.new_line();
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 4,
+                col: 0,
+                line: 1
+            }
+        );
+    }
+
+    #[test]
+    fn cursor_newline() {
+        let mut e = Buffer::new();
+        e.insert("foo");
+        e.new_line();
+        e.insert("bar");
+
+        assert_eq!(join(&e), vec!["foo", "bar"]);
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 7,
+                col: 3,
+                line: 1
+            }
+        );
+
+        e.move_start_of_line();
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 4,
+                col: 0,
+                line: 1
+            }
+        );
+
+        e.move_end_of_line();
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 7,
+                col: 3,
+                line: 1
+            }
+        );
+    }
+
+    #[test]
+    fn delete_lines() {
+        let mut e = Buffer::new();
+        e.insert("foo\nbar\nham");
+        e.move_up();
+        e.delete_line();
+
+        assert_eq!(join(&e), vec!["foo", "ham"]);
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 7,
+                col: 3,
+                line: 1
+            }
+        );
+
+        e.delete_line();
+        assert_eq!(join(&e), vec!["foo"]);
+        assert_eq!(
+            e.cursor,
+            Pos {
+                idx: 3,
+                col: 3,
+                line: 0
+            }
+        );
+
+        e.delete_line();
+        assert_eq!(join(&e), vec![""]);
+    }
+
+    #[test]
+    fn tokenize_incremental() {
+        let mut e = Buffer::new();
+        e.insert("foo bar\nham\n\neggs");
+        e.move_up();
+        e.move_up();
+        e.move_end_of_line();
+        e.insert(" spam\nx");
+        e.move_down();
+        e.delete_line();
+        e.move_start_of_line();
+        e.delete();
+        e.open_line_below();
+        e.insert("baz");
+
+        let expected = Buffer::from_reader(e.text.to_string().as_bytes()).unwrap();
+
+        assert_eq!(e.lines.len(), e.text.line_count());
+        assert_eq!(join(&e), join(&expected));
+        assert_eq!(join(&e), vec!["foo bar", "ham spam", "xeggs", "baz"]);
+    }
+
+    #[test]
+    fn longest_line() {
+        let mut e = Buffer::new();
+        e.insert("foo\nlonger line\nbar");
+        assert_eq!(e.longest_line(), 11);
+
+        e.move_up();
+        e.delete_line();
+        assert_eq!(e.longest_line(), 3);
+
+        e.insert("ham");
+        assert_eq!(e.longest_line(), 6);
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut e = Buffer::from_reader("foo\nbar".as_bytes()).unwrap();
+        e.move_down();
+        e.delete_line();
+        e.commit();
+        e.insert("ham ");
+        e.insert("eggs");
+        e.commit();
+
+        assert_eq!(e.text(), "ham eggsfoo");
+        assert!(e.is_modified());
+
+        assert!(e.undo());
+        assert_eq!(e.text(), "foo");
+        assert_eq!(e.cursor_pos(), (0, 0));
+
+        assert!(e.undo());
+        assert_eq!(join(&e), vec!["foo", "bar"]);
+        assert!(!e.undo());
+        assert!(!e.is_modified());
+
+        assert!(e.redo());
+        assert!(e.redo());
+        assert_eq!(join(&e), vec!["ham eggsfoo"]);
+        assert!(!e.redo());
+    }
+
+    #[test]
+    fn find_wraps_around() {
+        let e = Buffer::from_reader("foo bar\nbaz foo\nbar".as_bytes()).unwrap();
+
+        assert_eq!(e.find("foo", 0), Some(0));
+        assert_eq!(e.find("foo", 1), Some(12));
+        assert_eq!(e.find("foo", 13), Some(0));
+        assert_eq!(e.find("bar", 9), Some(16));
+        assert_eq!(e.find("eggs", 0), None);
+
+        assert_eq!(e.rfind("foo", 12), Some(0));
+        assert_eq!(e.rfind("foo", 13), Some(12));
+        assert_eq!(e.rfind("foo", 0), Some(12));
+        assert_eq!(e.rfind("bar", 4), Some(16));
+        assert_eq!(e.rfind("eggs", 10), None);
+    }
+
+    #[test]
+    fn search_from_multibyte_char() {
+        let mut e = Buffer::from_reader("äb\nx ä".as_bytes()).unwrap();
+
+        assert!(e.search("b"));
+        assert_eq!(e.cursor_pos(), (0, 2));
+        e.set_cursor_pos(0, 0);
+        assert!(e.search("x"));
+        assert_eq!(e.cursor_pos(), (1, 0));
+        e.set_cursor_pos(0, 0);
+        assert!(e.search("ä"));
+        assert_eq!(e.cursor_pos(), (1, 2));
+        assert!(e.search("ä"));
+        assert_eq!(e.cursor_pos(), (0, 0));
+    }
+
+    #[test]
+    fn tabs_and_blanks() {
+        let mut e = Buffer::new();
+        e.insert("a\tb  \n\t");
+
+        assert_eq!(join(&e), vec!["a\tb  ", "\t"]);
+        e.goto_line(0);
+        e.next_word();
+        assert_eq!(e.cursor_pos(), (0, 2));
+        assert_eq!(
+            e.blanks(0),
+            vec![(1, '\t', false), (3, ' ', true), (4, ' ', true)]
+        );
+        assert_eq!(e.blanks(1), vec![(0, '\t', true)]);
+        assert_eq!(e.blanks(2), vec![]);
+    }
+
+    #[test]
+    fn wrapped_rows() {
+        let mut e = Buffer::new();
+        e.insert("foo bar bazinga\nab");
+        let wrap = |width, linebreak, prefix| Wrap {
+            width,
+            linebreak,
+            prefix,
+        };
+
+        assert_eq!(e.rows(0, &wrap(20, false, 0)), vec![0]);
+        assert_eq!(e.rows(0, &wrap(6, false, 0)), vec![0, 6, 12]);
+        assert_eq!(e.rows(0, &wrap(6, true, 0)), vec![0, 4, 8, 14]);
+        assert_eq!(e.rows(0, &wrap(6, false, 2)), vec![0, 6, 10, 14]);
+        assert_eq!(e.rows(1, &wrap(1, false, 5)), vec![0, 1]);
+        // rows hold characters, not bytes
+        let mut u = Buffer::new();
+        u.insert("aäb 日本語の文");
+        assert_eq!(u.rows(0, &wrap(2, false, 0)), vec![0, 3, 5, 11, 17]);
+        assert_eq!(u.rows(0, &wrap(6, true, 0)), vec![0, 5]);
+        u.set_cursor_pos(0, 8);
+        u.move_row(true, &wrap(2, false, 0));
+        assert_eq!(u.cursor_pos(), (0, 14));
+        u.move_row(false, &wrap(2, false, 0));
+        u.move_row(false, &wrap(2, false, 0));
+        assert_eq!(u.cursor_pos(), (0, 4));
+
+        // the column within the row is kept while moving by rows
+        let wrap = wrap(6, true, 2);
+        e.set_cursor_pos(0, 1);
+        e.move_row(true, &wrap);
+        assert_eq!(e.cursor_pos(), (0, 4));
+        e.move_row(true, &wrap);
+        assert_eq!(e.cursor_pos(), (0, 8));
+        e.move_row(true, &wrap);
+        assert_eq!(e.cursor_pos(), (0, 12));
+        e.move_row(true, &wrap);
+        assert_eq!(e.cursor_pos(), (1, 2));
+        e.move_row(false, &wrap);
+        assert_eq!(e.cursor_pos(), (0, 12));
+        e.set_cursor_pos(0, 7);
+        e.move_row(false, &wrap);
+        assert_eq!(e.cursor_pos(), (0, 3));
+        e.move_row(false, &wrap);
+        assert_eq!(e.cursor_pos(), (0, 3));
+    }
+
+    #[test]
+    fn matches_in_lines() {
+        let mut e = Buffer::new();
+        e.insert("foo bar foo\nbar\nfoofoo");
+
+        assert_eq!(
+            e.matches("foo", 0..3),
+            vec![(0, 0..3), (0, 8..11), (2, 0..3), (2, 3..6)]
+        );
+        assert_eq!(e.matches("foo", 1..2), vec![]);
+        assert_eq!(e.matches("bar", 1..10), vec![(1, 0..3)]);
+        assert_eq!(e.matches("", 0..3), vec![]);
+    }
+
+    #[test]
+    fn iter_line_range() {
+        let mut e = Buffer::new();
+        e.insert("foo\nbar\nham\neggs");
+
+        let lines = e
+            .iter_lines(1..3)
+            .map(|line| line.collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["bar", "ham"]);
+        assert_eq!(e.iter_lines(3..10).count(), 1);
+        assert_eq!(e.iter_lines(10..20).count(), 0);
+    }
+
+    fn join(e: &Buffer) -> Vec<String> {
+        e.iter()
+            .map(|line| line.map(|s| s.to_string()).collect::<Vec<_>>().join(""))
+            .collect()
+    }
+
+    // the lexer highlights these file types without a syntax tree only
+    #[cfg(not(feature = "tree-sitter"))]
+    #[test]
+    fn filetype_syntax() {
+        let mut b = Buffer::from_reader("#!/usr/bin/env -S bash\necho $home".as_bytes()).unwrap();
+        assert_eq!(b.filetype(), Some("sh"));
+        assert_eq!(b.spans(1)[0].group, Group::Identifier);
+
+        b.set_path("build.rs");
+        assert_eq!(b.filetype(), Some("rust"));
+        assert!(b.spans(1).is_empty());
+
+        // edits highlight the changed and following lines again
+        b.move_start_of_line();
+        b.insert("/*");
+        assert_eq!(b.spans(1)[0].group, Group::Comment);
+        b.undo();
+        assert!(b.spans(1).is_empty());
+
+        b.set_path("notes");
+        b.move_start_of_line();
+        b.delete_char();
+        assert_eq!(b.filetype(), None);
+        assert!(b.spans(0).is_empty());
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    #[test]
+    fn syntax_tree() {
+        let mut b = Buffer::from_reader(
+            "fn f(a: u8, b: u8) {}
+
+fn g() {}
+"
+            .as_bytes(),
+        )
+        .unwrap();
+        assert!(b.spans(0).is_empty());
+        b.set_path("lib.rs");
+        assert_eq!(b.spans(0)[0].group, Group::Keyword);
+
+        b.insert("/*");
+        assert_eq!(b.spans(2)[0].group, Group::Comment);
+        b.undo();
+        assert_eq!(b.spans(2)[0].group, Group::Keyword);
+
+        b.next_function(true);
+        assert_eq!(b.cursor_pos(), (2, 0));
+        b.next_function(false);
+        assert_eq!(b.cursor_pos(), (0, 0));
+
+        b.move_to_byte(5);
+        b.delete_object(TextObject::Argument, true);
+        assert_eq!(b.text(), "fn f(b: u8) {}\n\nfn g() {}\n");
+        b.delete_object(TextObject::Function, true);
+        assert_eq!(b.text(), "\nfn g() {}\n");
+        assert!(b
+            .inspect_tree()
+            .unwrap()
+            .starts_with("(source_file) ; [1, 0] - [2, 0]"));
+    }
+}
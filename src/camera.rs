@@ -1,55 +1,191 @@
-use crate::render::V2;
+use crate::render::{DELTA_TIME, V2};
 
 const CAMERA_BASE_SPEED: f32 = 5.0;
 
+// largest scale of the text, when the longest line fits into the window
+pub const MAX_SCALE: f32 = 0.3;
+
+const MIN_SCALE: f32 = 0.05;
+
+// scale change per millisecond while zooming
+const SCALE_SPEED: f32 = 0.01 / DELTA_TIME;
+
+// where zz, zt and zb put the cursor line in the view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Center,
+    Top,
+    Bottom,
+}
+
 pub struct Camera {
     pub pos: V2,
     pub scale: f32,
+    pub speed: f32,
+    // fixed scale instead of fitting the longest line into the window
+    pub zoom: Option<f32>,
+    // distance of the cursor to the edges of the view
+    pub margin: V2,
+    // whether the view follows the cursor horizontally, wrapped lines
+    // fit into the window
+    pub scroll_x: bool,
+    // whether the camera moves smoothly or jumps to its target
+    pub animate: bool,
 
     velocity: V2,
     scale_velocity: f32,
 
     target: V2,
     target_scale: f32,
+    align: Option<Align>,
 }
 
 impl Camera {
     pub fn new() -> Camera {
         Camera {
             pos: V2::default(),
-            scale: 0.3,
+            scale: MAX_SCALE,
+            speed: CAMERA_BASE_SPEED,
+            zoom: None,
+            margin: V2::default(),
+            scroll_x: true,
+            animate: true,
             velocity: V2::default(),
             scale_velocity: 0.0,
             target: V2::default(),
-            target_scale: 0.3,
+            target_scale: MAX_SCALE,
+            align: None,
         }
     }
 
-    pub fn target(&mut self, target: V2, max_line_length: f32, win_width: f32) {
-        self.target = target;
-        self.target_scale = (win_width / 1.0 / (max_line_length.max(1.0) * 0.55))
-            .max(0.05)
-            .min(0.3);
-    }
+    // the view only moves to keep the cursor `margin` away from its edges
+    // and never beyond the top left corner `origin` of the text
+    pub fn target(&mut self, cursor: V2, origin: V2, max_line_length: f32, resolution: V2) {
+        self.target_scale = self.zoom.unwrap_or_else(|| {
+            (resolution.x / 1.0 / (max_line_length.max(1.0) * 0.55)).clamp(MIN_SCALE, MAX_SCALE)
+        });
 
-    pub fn update(&mut self, delta: f32) {
-        if self.target == self.pos {
-            return;
+        let half = resolution / (2.0 * self.target_scale);
+        let slack = V2 {
+            x: (half.x - self.margin.x).max(0.0),
+            y: (half.y - self.margin.y).max(0.0),
+        };
+        let mut target = self.target;
+        match self.align.take() {
+            Some(Align::Center) => target.y = cursor.y,
+            Some(Align::Top) => target.y = cursor.y - slack.y,
+            Some(Align::Bottom) => target.y = cursor.y + slack.y,
+            None => (),
         }
+        if !self.scroll_x {
+            target.x = origin.x + half.x;
+        }
+
+        self.target = V2 {
+            x: target
+                .x
+                .clamp(cursor.x - slack.x, cursor.x + slack.x)
+                .max(origin.x + half.x),
+            y: target
+                .y
+                .clamp(cursor.y - slack.y, cursor.y + slack.y)
+                .min(origin.y - half.y),
+        };
+        if !self.animate {
+            self.pos = self.target;
+            self.scale = self.target_scale;
+        }
+    }
+
+    // positions the view at the cursor with the next target
+    pub fn align(&mut self, align: Align) {
+        self.align = Some(align);
+    }
 
+    // world space rectangle (bottom left, top right) visible on screen
+    pub fn view(&self, resolution: V2) -> (V2, V2) {
+        let half = resolution / (2.0 * self.scale);
+        (self.pos - half, self.pos + half)
+    }
+
+    pub fn update(&mut self, delta: f32) {
         let target = self.target - self.pos;
         let dist_sq = target.x * target.x + target.y * target.y;
-        let dir = target / dist_sq.sqrt();
+        let step = delta * self.speed;
 
-        if dist_sq < 5000.0 {
+        if dist_sq < 5000.0 || dist_sq <= step * step {
             self.pos = self.target;
         } else {
-            self.velocity = dir * delta * CAMERA_BASE_SPEED;
+            self.velocity = target / dist_sq.sqrt() * step;
             self.pos = self.pos + self.velocity;
         }
 
         let scale_dir = self.target_scale - self.scale;
-        self.scale_velocity = scale_dir.abs().max(0.0).min(0.01) * scale_dir.signum();
-        self.scale = self.scale + self.scale_velocity;
+        self.scale_velocity = scale_dir.abs().min(SCALE_SPEED * delta) * scale_dir.signum();
+        self.scale += self.scale_velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Align, Camera, MAX_SCALE};
+    use crate::render::{DELTA_TIME, V2};
+
+    #[test]
+    fn margins_and_alignment() {
+        let mut camera = Camera::new();
+        camera.zoom = Some(0.25);
+        camera.margin = (0.0, 200.0).into();
+        camera.animate = false;
+        let target = |camera: &mut Camera, y: f32| {
+            camera.target(
+                (100.0, y).into(),
+                V2::default(),
+                1000.0,
+                (800.0, 600.0).into(),
+            );
+            camera.pos
+        };
+
+        // the view starts at the top left of the text and only moves once
+        // the cursor gets closer to an edge than the margin
+        assert_eq!(target(&mut camera, -100.0), (1600.0, -1200.0).into());
+        assert_eq!(target(&mut camera, -2000.0), (1600.0, -1200.0).into());
+        assert_eq!(target(&mut camera, -2500.0), (1600.0, -1500.0).into());
+
+        camera.align(Align::Top);
+        assert_eq!(target(&mut camera, -2500.0).y, -3500.0);
+        camera.align(Align::Bottom);
+        assert_eq!(target(&mut camera, -2500.0).y, -1500.0);
+        camera.align(Align::Center);
+        assert_eq!(target(&mut camera, -2500.0).y, -2500.0);
+
+        camera.zoom = None;
+        target(&mut camera, -2500.0);
+        assert_eq!(camera.scale, MAX_SCALE);
+    }
+
+    #[test]
+    fn frame_rate_independent() {
+        let moved = |frames: usize| {
+            let mut camera = Camera::new();
+            camera.zoom = Some(0.2);
+            camera.target(
+                (5000.0, -5000.0).into(),
+                V2::default(),
+                1.0,
+                (800.0, 600.0).into(),
+            );
+            for _ in 0..frames {
+                camera.update(DELTA_TIME * 2.0 / frames as f32);
+            }
+            (camera.pos, camera.scale)
+        };
+
+        let (pos, scale) = moved(1);
+        let (other_pos, other_scale) = moved(4);
+        assert!((pos.x - other_pos.x).abs() < 0.01 && (pos.y - other_pos.y).abs() < 0.01);
+        assert!((scale - other_scale).abs() < 1e-6);
+        assert!(scale < MAX_SCALE);
     }
 }
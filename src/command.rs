@@ -1,14 +1,21 @@
-use crate::cooldown::{Cooldown, CooldownState};
+use crate::buffer::TextObject;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CommandType {
     EnterInsert,
     EnterInsertAfter,
     EnterCommand,
+    EnterSearch,
+    // repeat the last search forwards or backwards
+    SearchNext,
+    SearchPrev,
     MoveLeft,
     MoveDown,
     MoveRight,
     MoveUp,
+    // by display row of wrapped lines
+    MoveDownRow,
+    MoveUpRow,
     MoveEndOfLine,
     MoveStartOfLine,
     NextWord,
@@ -19,7 +26,33 @@ pub enum CommandType {
     AppendLine,
     PrependLine,
     DeleteLine,
+    DeleteWord,
     DeleteChar,
+    // around the object if true, inner otherwise
+    DeleteObject(TextObject, bool),
+    NextFunction,
+    PrevFunction,
+    ScrollCenter,
+    ScrollTop,
+    ScrollBottom,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Undo,
+    Redo,
+    AlternateBuffer,
+    WindowLeft,
+    WindowDown,
+    WindowUp,
+    WindowRight,
+    WindowNext,
+    WindowClose,
+    WindowOnly,
+    WindowEqualize,
+    WindowSplit,
+    WindowVSplit,
+    TabNext,
+    TabPrev,
 }
 
 struct Command {
@@ -27,7 +60,7 @@ struct Command {
     typ: CommandType,
 }
 
-const ALL_COMMANDS: [Command; 18] = [
+const ALL_COMMANDS: [Command; 59] = [
     Command {
         input: "i",
         typ: CommandType::EnterInsert,
@@ -40,6 +73,18 @@ const ALL_COMMANDS: [Command; 18] = [
         input: ":",
         typ: CommandType::EnterCommand,
     },
+    Command {
+        input: "/",
+        typ: CommandType::EnterSearch,
+    },
+    Command {
+        input: "n",
+        typ: CommandType::SearchNext,
+    },
+    Command {
+        input: "N",
+        typ: CommandType::SearchPrev,
+    },
     Command {
         input: "h",
         typ: CommandType::MoveLeft,
@@ -56,6 +101,14 @@ const ALL_COMMANDS: [Command; 18] = [
         input: "k",
         typ: CommandType::MoveUp,
     },
+    Command {
+        input: "gj",
+        typ: CommandType::MoveDownRow,
+    },
+    Command {
+        input: "gk",
+        typ: CommandType::MoveUpRow,
+    },
     Command {
         input: "$",
         typ: CommandType::MoveEndOfLine,
@@ -96,74 +149,283 @@ const ALL_COMMANDS: [Command; 18] = [
         input: "dd",
         typ: CommandType::DeleteLine,
     },
+    Command {
+        input: "dw",
+        typ: CommandType::DeleteWord,
+    },
     Command {
         input: "x",
         typ: CommandType::DeleteChar,
     },
+    Command {
+        input: "daf",
+        typ: CommandType::DeleteObject(TextObject::Function, true),
+    },
+    Command {
+        input: "dif",
+        typ: CommandType::DeleteObject(TextObject::Function, false),
+    },
+    Command {
+        input: "dac",
+        typ: CommandType::DeleteObject(TextObject::Class, true),
+    },
+    Command {
+        input: "dic",
+        typ: CommandType::DeleteObject(TextObject::Class, false),
+    },
+    Command {
+        input: "daa",
+        typ: CommandType::DeleteObject(TextObject::Argument, true),
+    },
+    Command {
+        input: "dia",
+        typ: CommandType::DeleteObject(TextObject::Argument, false),
+    },
+    Command {
+        input: "]m",
+        typ: CommandType::NextFunction,
+    },
+    Command {
+        input: "[m",
+        typ: CommandType::PrevFunction,
+    },
+    Command {
+        input: "zz",
+        typ: CommandType::ScrollCenter,
+    },
+    Command {
+        input: "zt",
+        typ: CommandType::ScrollTop,
+    },
+    Command {
+        input: "zb",
+        typ: CommandType::ScrollBottom,
+    },
+    Command {
+        input: "<C-+>",
+        typ: CommandType::ZoomIn,
+    },
+    Command {
+        input: "<C-->",
+        typ: CommandType::ZoomOut,
+    },
+    Command {
+        input: "<C-0>",
+        typ: CommandType::ZoomReset,
+    },
+    Command {
+        input: "u",
+        typ: CommandType::Undo,
+    },
+    Command {
+        input: "<C-r>",
+        typ: CommandType::Redo,
+    },
+    Command {
+        input: "<C-^>",
+        typ: CommandType::AlternateBuffer,
+    },
+    Command {
+        input: "<C-w>h",
+        typ: CommandType::WindowLeft,
+    },
+    Command {
+        input: "<C-w>j",
+        typ: CommandType::WindowDown,
+    },
+    Command {
+        input: "<C-w>k",
+        typ: CommandType::WindowUp,
+    },
+    Command {
+        input: "<C-w>l",
+        typ: CommandType::WindowRight,
+    },
+    Command {
+        input: "<C-w>w",
+        typ: CommandType::WindowNext,
+    },
+    Command {
+        input: "<C-w>c",
+        typ: CommandType::WindowClose,
+    },
+    Command {
+        input: "<C-w>o",
+        typ: CommandType::WindowOnly,
+    },
+    Command {
+        input: "<C-w>=",
+        typ: CommandType::WindowEqualize,
+    },
+    Command {
+        input: "<C-w>s",
+        typ: CommandType::WindowSplit,
+    },
+    Command {
+        input: "<C-w>v",
+        typ: CommandType::WindowVSplit,
+    },
+    Command {
+        input: "gt",
+        typ: CommandType::TabNext,
+    },
+    Command {
+        input: "gT",
+        typ: CommandType::TabPrev,
+    },
+    Command {
+        input: "<Left>",
+        typ: CommandType::MoveLeft,
+    },
+    Command {
+        input: "<Down>",
+        typ: CommandType::MoveDown,
+    },
+    Command {
+        input: "<Right>",
+        typ: CommandType::MoveRight,
+    },
+    Command {
+        input: "<Up>",
+        typ: CommandType::MoveUp,
+    },
+    Command {
+        input: "<End>",
+        typ: CommandType::MoveEndOfLine,
+    },
+    Command {
+        input: "<Home>",
+        typ: CommandType::MoveStartOfLine,
+    },
 ];
 
+#[derive(Debug, PartialEq)]
 pub struct Action {
     pub repeat: usize,
     pub cmd: CommandType,
 }
 
-enum CommandMatch<'a> {
-    PartialMatch(Vec<&'a Command>),
-    FullMatch(&'a Command),
-    NoMatch,
+pub enum InputMatch<T> {
+    // the input starts a longer key sequence, `Some` if it is a complete
+    // one on its own as well
+    Partial(Option<T>),
+    Full(T),
+    None,
 }
 
-impl Command {
-    fn from_input(input: &str) -> CommandMatch {
-        let mut candidates = Vec::new();
+// matches typed keys against the key sequences of a command or mapping table
+pub fn match_input<'a, T, I>(entries: I, input: &str) -> InputMatch<T>
+where
+    I: IntoIterator<Item = (&'a str, T)>,
+{
+    let mut full = None;
+    let mut partial = false;
 
-        for cmd in &ALL_COMMANDS {
-            if cmd.input == input {
-                return CommandMatch::FullMatch(cmd);
-            }
-            if cmd.input.starts_with(input) {
-                candidates.push(cmd)
-            }
+    for (keys, entry) in entries {
+        if keys == input {
+            full = Some(entry);
+        } else if keys.starts_with(input) {
+            partial = true;
         }
+    }
+
+    match (full, partial) {
+        (full, true) => InputMatch::Partial(full),
+        (Some(entry), false) => InputMatch::Full(entry),
+        (None, false) => InputMatch::None,
+    }
+}
+
+// how long to wait for the next key of an incomplete key sequence in
+// milliseconds, `None` waits for it without a timeout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeout {
+    // 'timeout' and 'timeoutlen' for commands and mappings
+    pub keys: Option<f32>,
+    // 'ttimeout' and 'ttimeoutlen' for key codes
+    pub key_codes: Option<f32>,
+}
 
-        if candidates.is_empty() {
-            CommandMatch::NoMatch
+impl Timeout {
+    // sequences starting with <Esc> stand for the key codes a terminal
+    // sends, e.g. for <M-x>, and are not waited for as long
+    pub fn wait_for(&self, keys: &str) -> Option<f32> {
+        if keys.starts_with("<Esc>") {
+            self.key_codes
         } else {
-            CommandMatch::PartialMatch(candidates)
+            self.keys
         }
     }
 }
 
+fn find_command(commands: &'static [Command], input: &str) -> InputMatch<&'static Command> {
+    match_input(commands.iter().map(|cmd| (cmd.input, cmd)), input)
+}
+
 pub struct InputBuffer {
-    buf: String,
-    cd: Cooldown,
+    commands: &'static [Command],
+    // notation of the keys typed since the last command
+    keys: Vec<String>,
     repeat: Option<usize>,
+    timeout: Timeout,
+    // milliseconds since the last key
+    waited: f32,
 }
 
 impl InputBuffer {
     pub fn new() -> InputBuffer {
+        InputBuffer::with_commands(&ALL_COMMANDS)
+    }
+
+    fn with_commands(commands: &'static [Command]) -> InputBuffer {
         InputBuffer {
-            buf: String::with_capacity(5),
-            cd: Cooldown::new(500.0, 100.0),
+            commands,
+            keys: Vec::with_capacity(5),
             repeat: None,
+            timeout: Timeout {
+                keys: Some(500.0),
+                key_codes: Some(100.0),
+            },
+            waited: 0.0,
         }
     }
 
-    fn reset(&mut self) {
-        self.buf.clear();
-        self.cd.reset(CooldownState::Active);
+    pub fn reset(&mut self) {
+        self.keys.clear();
         self.repeat = None;
+        self.waited = 0.0;
     }
 
-    pub fn update(&mut self, delta: f32) {
-        self.cd.update(delta);
-        if self.cd.state != CooldownState::Active {
-            self.buf.clear();
-            self.repeat = None;
+    pub fn set_timeout(&mut self, timeout: Timeout) {
+        self.timeout = timeout;
+    }
+
+    // keys typed so far of an incomplete command, including its count
+    pub fn pending(&self) -> String {
+        match self.repeat {
+            Some(repeat) => format!("{repeat}{}", self.keys.concat()),
+            None => self.keys.concat(),
         }
     }
 
-    pub fn check(&mut self, input: &str) -> Option<Action> {
+    // executes the longest complete command of the pending keys once they
+    // timed out, keys that are no command on their own keep waiting
+    pub fn update(&mut self, delta: f32) -> Vec<Action> {
+        if self.keys.is_empty() {
+            return Vec::new();
+        }
+        let Some(timeout) = self.timeout.wait_for(&self.keys.concat()) else {
+            return Vec::new();
+        };
+
+        self.waited += delta;
+        if self.waited < timeout {
+            return Vec::new();
+        }
+        self.resolve(true)
+    }
+
+    pub fn check(&mut self, input: &str) -> Vec<Action> {
         match input.parse::<usize>().ok() {
             Some(multiplier) if multiplier > 0 || self.repeat.is_some() => {
                 let current_multiplier = self.repeat.unwrap_or(0);
@@ -173,31 +435,123 @@ impl InputBuffer {
                     10usize.pow((multiplier as f32).log10().abs().floor() as u32 + 1)
                 };
                 self.repeat = Some(current_multiplier * next_multiplier + multiplier);
-                self.cd.reset(CooldownState::Active);
-                None
+                self.waited = 0.0;
+                Vec::new()
             }
             _ => {
-                self.buf.push_str(input);
-
-                match Command::from_input(&self.buf) {
-                    CommandMatch::PartialMatch(_) => {
-                        self.cd.reset(CooldownState::Active);
-                        None
-                    }
-                    CommandMatch::FullMatch(cmd) => {
-                        let action = Action {
-                            repeat: self.repeat.unwrap_or(1),
-                            cmd: cmd.typ.clone(),
-                        };
-                        self.reset();
-                        Some(action)
-                    }
-                    CommandMatch::NoMatch => {
-                        self.reset();
-                        None
-                    }
+                self.keys.push(input.to_string());
+                self.waited = 0.0;
+                self.resolve(false)
+            }
+        }
+    }
+
+    // splits the pending keys into commands, a sequence that may continue
+    // to a longer command waits for more keys unless it timed out
+    fn resolve(&mut self, timeout: bool) -> Vec<Action> {
+        let mut actions = Vec::new();
+
+        while !self.keys.is_empty() {
+            let found = find_command(self.commands, &self.keys.concat());
+            if let InputMatch::Full(cmd) = found {
+                actions.push(self.action(cmd));
+                self.keys.clear();
+                break;
+            }
+            if !timeout && matches!(found, InputMatch::Partial(_)) {
+                break;
+            }
+
+            // the keys after the longest complete prefix are checked again
+            let prefix = (1..=self.keys.len()).rev().find_map(|len| {
+                match find_command(self.commands, &self.keys[..len].concat()) {
+                    InputMatch::Full(cmd) | InputMatch::Partial(Some(cmd)) => Some((len, cmd)),
+                    _ => None,
+                }
+            });
+            match prefix {
+                Some((len, cmd)) => {
+                    actions.push(self.action(cmd));
+                    self.keys.drain(..len);
+                }
+                None if matches!(found, InputMatch::Partial(_)) => break,
+                None => {
+                    self.reset();
+                    break;
                 }
             }
         }
+        actions
+    }
+
+    fn action(&mut self, cmd: &Command) -> Action {
+        Action {
+            repeat: self.repeat.take().unwrap_or(1),
+            cmd: cmd.typ.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Command, CommandType, InputBuffer, Timeout};
+
+    const COMMANDS: [Command; 4] = [
+        Command {
+            input: "g",
+            typ: CommandType::MoveLeft,
+        },
+        Command {
+            input: "gt",
+            typ: CommandType::TabNext,
+        },
+        Command {
+            input: "gtt",
+            typ: CommandType::TabPrev,
+        },
+        Command {
+            input: "dd",
+            typ: CommandType::DeleteLine,
+        },
+    ];
+
+    fn action(repeat: usize, cmd: CommandType) -> Action {
+        Action { repeat, cmd }
+    }
+
+    #[test]
+    fn timeout_runs_longest_prefix() {
+        let mut input = InputBuffer::with_commands(&COMMANDS);
+
+        assert!(input.check("2").is_empty());
+        assert!(input.check("g").is_empty());
+        assert!(input.check("t").is_empty());
+        assert!(input.update(499.0).is_empty());
+        assert_eq!(input.update(1.0), vec![action(2, CommandType::TabNext)]);
+        assert_eq!(input.pending(), "");
+
+        // keys after the prefix are checked again
+        input.check("g");
+        assert_eq!(input.check("d"), vec![action(1, CommandType::MoveLeft)]);
+        assert_eq!(input.pending(), "d");
+        assert_eq!(input.check("d"), vec![action(1, CommandType::DeleteLine)]);
+    }
+
+    #[test]
+    fn incomplete_keys_wait() {
+        let mut input = InputBuffer::with_commands(&COMMANDS);
+
+        input.check("d");
+        assert!(input.update(10_000.0).is_empty());
+        assert_eq!(input.check("d"), vec![action(1, CommandType::DeleteLine)]);
+
+        input.set_timeout(Timeout {
+            keys: None,
+            key_codes: Some(100.0),
+        });
+        input.check("g");
+        assert!(input.update(10_000.0).is_empty());
+        assert_eq!(input.check("x"), vec![action(1, CommandType::MoveLeft)]);
+        assert_eq!(input.pending(), "");
     }
 }
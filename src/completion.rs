@@ -0,0 +1,124 @@
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletionKind {
+    Command,
+    Option,
+    Buffer,
+    Path,
+    Highlight,
+    ColorScheme,
+}
+
+// candidates for the word in the command line starting at `start`, cycling
+// through them eventually returns to the originally typed word
+pub struct Completion {
+    candidates: Vec<String>,
+    selected: Option<usize>,
+    start: usize,
+    original: String,
+}
+
+impl Completion {
+    pub fn new(candidates: Vec<String>, start: usize, original: String) -> Completion {
+        Completion {
+            candidates,
+            selected: None,
+            start,
+            original,
+        }
+    }
+
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn cycle(&mut self, forward: bool) -> &str {
+        let len = self.candidates.len();
+        self.selected = match (self.selected, forward) {
+            (None, true) => Some(0),
+            (None, false) => len.checked_sub(1),
+            (Some(idx), true) => Some(idx + 1).filter(|idx| *idx < len),
+            (Some(idx), false) => idx.checked_sub(1),
+        };
+
+        match self.selected {
+            Some(idx) => &self.candidates[idx],
+            None => &self.original,
+        }
+    }
+}
+
+// entries of the directory part of `word` starting with its file name part,
+// directories end in a slash and hidden files need a leading dot
+pub fn complete_path(word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(idx) => word.split_at(idx + 1),
+        None => ("", word),
+    };
+    let search_dir = if dir.is_empty() { "." } else { dir };
+
+    let Ok(entries) = Path::new(search_dir).read_dir() else {
+        return Vec::new();
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete_path, Completion};
+
+    #[test]
+    fn cycle_candidates() {
+        let mut c = Completion::new(vec!["edit".into(), "enew".into()], 1, "e".into());
+
+        assert_eq!(c.cycle(true), "edit");
+        assert_eq!(c.cycle(true), "enew");
+        assert_eq!(c.cycle(true), "e");
+        assert_eq!(c.selected(), None);
+        assert_eq!(c.cycle(false), "enew");
+        assert_eq!(c.cycle(false), "edit");
+    }
+
+    #[test]
+    fn paths() {
+        let dir = std::env::temp_dir().join(format!("edi-complete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let base = format!("{}/", dir.display());
+        assert_eq!(
+            complete_path(&base),
+            vec![format!("{base}Cargo.toml"), format!("{base}src/")]
+        );
+        assert_eq!(
+            complete_path(&format!("{base}.h")),
+            vec![format!("{base}.hidden")]
+        );
+        assert!(complete_path(&format!("{base}x")).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
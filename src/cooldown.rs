@@ -21,6 +21,12 @@ impl Cooldown {
         }
     }
 
+    // takes effect the next time a state starts
+    pub fn set_durations(&mut self, duration: f32, cooldown: f32) {
+        self.duration = duration;
+        self.cooldown = cooldown;
+    }
+
     pub fn reset(&mut self, state: CooldownState) {
         self.state = state;
         match self.state {
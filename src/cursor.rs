@@ -1,67 +1,69 @@
 use crate::cooldown::{Cooldown, CooldownState};
 use crate::font::FONT_SIZE;
-use crate::render::{Renderer, DELTA_TIME, V2, V4};
+use crate::render::{Backend, Renderer, DELTA_TIME, V2, V4};
 
 pub const CURSOR_OFFSET: f32 = 0.13;
 
 const CURSOR_DURATION: f32 = 600.0;
 const CURSOR_COOLDOWN: f32 = 400.0;
 
-const CURSOR_ACCEL: f32 = 0.1;
+// speed gained per millisecond
+const CURSOR_ACCEL: f32 = 0.1 / DELTA_TIME;
 const CURSOR_MIN_SPEED: f32 = 3.5;
 const CURSOR_MAX_SPEED: f32 = 10.0;
 
-const DIST_THRESHOLD_SQRD: f32 =
-    CURSOR_MAX_SPEED * CURSOR_MAX_SPEED * DELTA_TIME * DELTA_TIME * 1.05;
-
 pub struct Cursor {
     pub pos: V2,
     color: V4,
     target: V2,
     vel: V2,
     speed: f32,
+    // whether the cursor moves smoothly or jumps to its target
+    animate: bool,
     cd: Cooldown,
 }
 
 impl Cursor {
-    pub fn new(color: V4) -> Cursor {
+    pub fn new() -> Cursor {
         Cursor {
             pos: (0, 0).into(),
             target: (0, 0).into(),
             vel: (0, 0).into(),
             speed: 0.0,
-            color,
+            animate: true,
+            color: V4::default(),
             cd: Cooldown::new(CURSOR_DURATION, CURSOR_COOLDOWN),
         }
     }
 
-    pub fn render(&self, renderer: &mut Renderer, normal_mode: bool) {
+    pub fn render<B: Backend>(&self, renderer: &mut Renderer<B>, normal_mode: bool) {
         let width_factor = if normal_mode { 3.0 } else { 8.0 };
-        let cursor_size = ((FONT_SIZE as f32) / width_factor, FONT_SIZE as f32);
+        let cursor_size = (FONT_SIZE / width_factor, FONT_SIZE);
 
         renderer.render_solid_rect(self.pos, cursor_size.into(), self.color);
     }
 
     pub fn move_to<Pos: Into<V2>>(&mut self, pos: Pos) {
         self.target = pos.into();
+        if !self.animate {
+            self.pos = self.target;
+        }
     }
 
     pub fn update(&mut self, delta: f32) {
         let direction = self.target - self.pos;
         let dist_squared = direction.x * direction.x + direction.y * direction.y;
 
-        if dist_squared > DIST_THRESHOLD_SQRD {
-            let dir = direction / dist_squared.sqrt();
-
-            self.speed = (self.speed + CURSOR_ACCEL)
-                .max(CURSOR_MIN_SPEED)
-                .min(CURSOR_MAX_SPEED);
+        self.speed = (self.speed + CURSOR_ACCEL * delta).clamp(CURSOR_MIN_SPEED, CURSOR_MAX_SPEED);
+        let step = self.speed * delta;
 
+        // the cursor arrives once it would pass its target in this frame
+        if dist_squared > step * step {
+            let dir = direction / dist_squared.sqrt();
             let velocity = dir * self.speed;
-            let movement = velocity * delta;
 
             self.vel = velocity;
-            self.pos = self.pos + movement;
+            self.pos = self.pos + velocity * delta;
         } else {
             self.pos = self.target;
             self.vel = (0, 0).into();
@@ -71,6 +73,18 @@ impl Cursor {
         self.cd.update(delta);
     }
 
+    pub fn set_color(&mut self, color: V4) {
+        self.color = color;
+    }
+
+    pub fn set_animate(&mut self, animate: bool) {
+        self.animate = animate;
+    }
+
+    pub fn set_blink(&mut self, on: f32, off: f32) {
+        self.cd.set_durations(on, off);
+    }
+
     pub fn active(&mut self) {
         self.cd.reset(CooldownState::Active);
     }
@@ -1,9 +1,32 @@
-use crate::command::{CommandType, InputBuffer};
+use crate::args::{Args, StartPos, STDIN};
+use crate::buffer::{Buffer, Wrap};
+use crate::camera::Align;
+use crate::command::{Action, CommandType, InputBuffer, InputMatch};
+use crate::completion::{self, Completion, CompletionKind};
 use crate::errors::EdiError;
-use crate::excmd::{CmdBuffer, ExCmdResult};
-use crate::render::V2;
-
-const INITIAL_BUFFER_SIZE: usize = 10 * 1024;
+use crate::excmd::{self, CmdBuffer, ExCmdResult, ExCmdType};
+use crate::highlight::{self, Group, Highlights};
+use crate::key::Key;
+use crate::mapping::{self, MapMode, Mapping, Mappings};
+use crate::message::{Message, MessageKind, Messages};
+use crate::options::{self, Options};
+use crate::render::{Rect, V2};
+use crate::statusline;
+use crate::window::{Direction, Split, Windows};
+
+// nesting limit of :source
+const MAX_SOURCE_DEPTH: usize = 50;
+
+// nesting limit of recursive mappings like vim's 'maxmapdepth'
+const MAX_MAP_DEPTH: usize = 1000;
+
+// steps and limit of 'zoom' with Ctrl-+ and Ctrl--
+const ZOOM_STEP: usize = 10;
+const MAX_ZOOM: usize = 300;
+
+fn key_notation(keys: &[Key]) -> String {
+    keys.iter().map(|key| key.to_string()).collect()
+}
 
 #[derive(PartialEq)]
 pub enum Mode {
@@ -14,234 +37,407 @@ pub enum Mode {
 
 pub struct Editor {
     pub mode: Mode,
-    buffer: String,
-    lines: Vec<Line>,
-    cursor: Pos,
+    buffers: Vec<Buffer>,
+    current: usize,
+    alternate: Option<usize>,
+    next_id: usize,
+    tabs: Vec<Windows>,
+    tab: usize,
+    next_window: usize,
     input_buffer: InputBuffer,
     command_buffer: CmdBuffer,
+    completion: Option<Completion>,
+    source_depth: usize,
+    mappings: Mappings,
+    // typed keys that may start a mapping
+    pending_keys: Vec<Key>,
+    // milliseconds since the last pending key
+    map_waited: f32,
+    map_depth: usize,
+    // set by E223 until the expansion of all mappings is given up
+    map_aborted: bool,
+    messages: Messages,
+    options: Options,
+    highlights: Highlights,
+    // name of the last loaded color scheme
+    colors_name: String,
+    // cursor position when the current search was started, restored if
+    // it is cancelled
+    search_start: Option<(usize, usize)>,
+    // last search pattern, its matches are highlighted until :nohlsearch
+    hlsearch: Option<String>,
+    // text columns of the windows as last laid out, the width of the
+    // rows of wrapped lines
+    text_columns: Vec<(usize, usize)>,
+    // zz, zt and zb of the windows since their views were last updated
+    aligns: Vec<(usize, Align)>,
 }
 
-pub struct LineIter<'a> {
-    editor: &'a Editor,
-    idx: usize,
-}
+impl Editor {
+    pub fn new() -> Editor {
+        Self::with_buffer(Buffer::new())
+    }
 
-impl<'a> Iterator for LineIter<'a> {
-    type Item = WordIter<'a>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.idx < self.editor.lines.len() {
-            let idx = self.idx;
-            self.idx += 1;
-            Some(WordIter {
-                editor: self.editor,
-                line: &self.editor.lines[idx],
-                idx: 0,
+    pub fn from_file(path: &str) -> Result<Editor, EdiError> {
+        Ok(Self::with_buffer(Buffer::from_file(path)?))
+    }
+
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Editor, EdiError> {
+        Ok(Self::with_buffer(Buffer::from_reader(reader)?))
+    }
+
+    pub fn from_args(args: &Args) -> Result<Editor, EdiError> {
+        let mut buffers = args
+            .files
+            .iter()
+            .map(|path| {
+                if path == STDIN {
+                    Buffer::from_reader(std::io::stdin().lock())
+                } else {
+                    Buffer::from_file(path)
+                }
             })
-        } else {
-            None
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+
+        let mut editor = Self::with_buffer(buffers.next().unwrap_or_else(Buffer::new));
+        for buffer in buffers {
+            editor.add_buffer(buffer);
+        }
+
+        for buffer in &mut editor.buffers {
+            buffer.set_readonly(args.readonly);
+        }
+
+        let buffer = editor.buffer_mut();
+        match &args.start {
+            Some(StartPos::Line(line)) => buffer.goto_line(line.saturating_sub(1)),
+            Some(StartPos::LastLine) => buffer.goto_line(usize::MAX),
+            Some(StartPos::Search(pattern)) => {
+                if let Some(idx) = buffer.find(pattern, 0) {
+                    buffer.move_to_byte(idx);
+                }
+            }
+            None => (),
         }
+
+        Ok(editor)
     }
-}
 
-pub struct WordIter<'a> {
-    editor: &'a Editor,
-    line: &'a Line,
-    idx: usize,
-}
+    fn with_buffer(buffer: Buffer) -> Editor {
+        let mut editor = Editor {
+            mode: Mode::Normal,
+            buffers: Vec::new(),
+            current: 0,
+            alternate: None,
+            next_id: 1,
+            tabs: Vec::new(),
+            tab: 0,
+            next_window: 1,
+            input_buffer: InputBuffer::new(),
+            command_buffer: CmdBuffer::new(),
+            completion: None,
+            source_depth: 0,
+            mappings: Mappings::new(),
+            pending_keys: Vec::new(),
+            map_waited: 0.0,
+            map_depth: 0,
+            map_aborted: false,
+            messages: Messages::new(),
+            options: Options::new(),
+            highlights: Highlights::new(),
+            colors_name: "default".to_string(),
+            search_start: None,
+            hlsearch: None,
+            text_columns: Vec::new(),
+            aligns: Vec::new(),
+        };
+        editor.add_buffer(buffer);
+        editor.new_tab();
+        editor
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffers[self.current]
+    }
 
-impl<'a> Iterator for WordIter<'a> {
-    type Item = &'a str;
+    pub fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.current]
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.idx < self.line.tokens.len() {
-            let token = &self.line.tokens[self.idx];
-            let start = token.idx();
-            let end = start + token.len();
-            self.idx += 1;
-            Some(&self.editor.buffer[start..end])
-        } else {
-            None
+    pub fn buffers(&self) -> &[Buffer] {
+        &self.buffers
+    }
+
+    pub fn highlights(&self) -> &Highlights {
+        &self.highlights
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn set_text_columns(&mut self, columns: Vec<(usize, usize)>) {
+        self.text_columns = columns;
+    }
+
+    // layout of the long lines of a window with 'wrap', once its width is known
+    pub fn wrap(&self, id: usize) -> Option<Wrap> {
+        let (_, columns) = self.text_columns.iter().find(|(window, _)| *window == id)?;
+        self.options.wrap(*columns)
+    }
+
+    // gj and gk, moving by buffer line like j and k without 'wrap'
+    fn move_row(&mut self, down: bool) {
+        match self.wrap(self.current_window()) {
+            Some(wrap) => self.buffer_mut().move_row(down, &wrap),
+            None if down => self.buffer_mut().move_down(),
+            None => self.buffer_mut().move_up(),
         }
     }
-}
 
-struct Line {
-    idx: usize,
-    tokens: Vec<Token>,
-}
+    fn align(&mut self, align: Align) {
+        let id = self.current_window();
+        self.aligns.push((id, align));
+    }
 
-impl Line {
-    fn start(&self) -> usize {
-        self.idx
+    pub fn take_aligns(&mut self) -> Vec<(usize, Align)> {
+        std::mem::take(&mut self.aligns)
     }
 
-    fn end(&self) -> usize {
-        if self.tokens.is_empty() {
-            self.idx
-        } else {
-            let last_token = &self.tokens[self.tokens.len() - 1];
-            last_token.idx() + last_token.len()
+    // zooms in or out from the fixed size of 'zoom', or from the largest
+    // automatic size
+    fn zoom(&mut self, step: isize) {
+        let zoom = match self.options.zoom {
+            0 => 100,
+            zoom => zoom,
+        };
+        let zoom = zoom.saturating_add_signed(step);
+        self.options.zoom = zoom.clamp(ZOOM_STEP, MAX_ZOOM);
+    }
+
+    // cursor position in columns and lines, right of the line number gutter
+    pub fn cursor(&self) -> V2 {
+        let buffer = self.buffer();
+        let gutter = self.options.gutter_width(buffer.line_count());
+        buffer.cursor() + (gutter as f32, 0.0).into()
+    }
+
+    // adds a buffer to the buffer list and returns its index
+    pub fn add_buffer(&mut self, mut buffer: Buffer) -> usize {
+        buffer.set_id(self.next_id);
+        self.next_id += 1;
+        self.buffers.push(buffer);
+        self.buffers.len() - 1
+    }
+
+    pub fn switch_buffer(&mut self, idx: usize) {
+        if idx != self.current && idx < self.buffers.len() {
+            self.alternate = Some(self.buffer().id());
+            self.current = idx;
         }
     }
 
-    fn current_word(&self, idx: usize) -> Option<&Token> {
-        self.tokens
-            .iter()
-            .skip_while(|token| match token {
-                Token::Word {
-                    idx: token_start,
-                    len,
-                } => token_start + len <= idx,
-                _ => true,
-            })
-            .next()
+    fn windows(&self) -> &Windows {
+        &self.tabs[self.tab]
     }
 
-    fn next_word(&self, idx: usize) -> Option<&Token> {
-        self.tokens
-            .iter()
-            .skip_while(|token| match token {
-                Token::Word {
-                    idx: token_start,
-                    len: _,
-                } => *token_start <= idx,
-                _ => true,
-            })
-            .next()
+    fn windows_mut(&mut self) -> &mut Windows {
+        &mut self.tabs[self.tab]
     }
 
-    fn prev_word(&self, idx: usize) -> Option<&Token> {
-        self.tokens
-            .iter()
-            .rev()
-            .skip_while(|token| match token {
-                Token::Word {
-                    idx: token_start,
-                    len,
-                } => token_start + len > idx,
-                _ => true,
-            })
-            .next()
+    pub fn current_window(&self) -> usize {
+        self.windows().current()
     }
-}
 
-enum Token {
-    Word { idx: usize, len: usize },
-    Space { idx: usize, len: usize },
-    Newline { idx: usize },
-}
+    // window ids and the area they cover, in layout order
+    pub fn window_rects(&self, area: Rect) -> Vec<(usize, Rect)> {
+        self.windows().rects(area)
+    }
+
+    // ids of the windows of all tab pages
+    pub fn window_ids(&self) -> Vec<usize> {
+        self.tabs
+            .iter()
+            .flat_map(|tab| tab.iter().map(|window| window.id))
+            .collect()
+    }
 
-impl Token {
-    fn len(&self) -> usize {
-        match self {
-            Token::Word { idx: _, len } => *len,
-            Token::Space { idx: _, len } => *len,
-            Token::Newline { .. } => 1,
+    // buffer and cursor (line, column) shown in a window
+    pub fn window(&self, id: usize) -> Option<(&Buffer, (usize, usize))> {
+        if id == self.current_window() {
+            return Some((self.buffer(), self.buffer().cursor_pos()));
         }
+
+        let window = self.tabs.iter().find_map(|tab| tab.get(id))?;
+        let buffer = self.buffers.iter().find(|b| b.id() == window.buffer)?;
+        Some((buffer, window.cursor))
     }
 
-    fn idx(&self) -> usize {
-        match self {
-            Token::Word { idx, len: _ } => *idx,
-            Token::Space { idx, len: _ } => *idx,
-            Token::Newline { idx } => *idx,
+    pub fn switch_window(&mut self, id: usize) {
+        if id != self.current_window() && self.windows().get(id).is_some() {
+            self.store_window();
+            self.windows_mut().set_current(id);
+            self.load_window();
         }
     }
 
-    fn start_pos(&self, line: &Line, line_idx: usize) -> Pos {
-        let idx = self.idx();
-        Pos {
-            idx,
-            line: line_idx,
-            col: idx - line.start(),
+    fn next_window_id(&mut self) -> usize {
+        self.next_window += 1;
+        self.next_window - 1
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn current_tab(&self) -> usize {
+        self.tab
+    }
+
+    // the active window of every tab page
+    pub fn tab_windows(&self) -> Vec<usize> {
+        self.tabs.iter().map(|tab| tab.current()).collect()
+    }
+
+    // opens a tab page after the current one, showing the current buffer
+    fn new_tab(&mut self) {
+        if !self.tabs.is_empty() {
+            self.store_window();
+        }
+
+        let id = self.next_window_id();
+        let mut windows = Windows::new(id, self.buffer().id());
+        if let Some(window) = windows.get_mut(id) {
+            window.cursor = self.buffer().cursor_pos();
         }
+
+        self.tab = (self.tab + 1).min(self.tabs.len());
+        self.tabs.insert(self.tab, windows);
     }
 
-    fn end_pos(&self, line: &Line, line_idx: usize) -> Pos {
-        let idx = self.idx();
-        let len = self.len();
-        Pos {
-            idx: idx + len - 1,
-            line: line_idx,
-            col: idx - line.start() + len - 1,
+    fn switch_tab(&mut self, idx: usize) {
+        if idx != self.tab && idx < self.tabs.len() {
+            self.store_window();
+            self.tab = idx;
+            self.load_window();
         }
     }
-}
 
-#[derive(Debug, PartialEq)]
-struct Pos {
-    idx: usize,
-    line: usize,
-    col: usize,
-}
+    fn cycle_tab(&mut self, offset: usize) {
+        self.switch_tab((self.tab + offset) % self.tabs.len());
+    }
 
-impl Pos {
-    fn next(&mut self, offset: usize) {
-        self.idx += offset;
-        self.col += offset;
+    // :tabnext N goes to tab page N, counting from 1
+    fn next_tab(&mut self, args: &str) {
+        match args.parse::<usize>() {
+            Ok(tab) if tab > 0 && tab <= self.tabs.len() => self.switch_tab(tab - 1),
+            Ok(_) => self.error("E475: Invalid argument".to_string()),
+            Err(_) => self.cycle_tab(1),
+        }
     }
 
-    fn prev(&mut self, offset: usize) {
-        if self.col >= offset {
-            self.col -= offset;
-            self.idx -= offset;
+    fn close_tab(&mut self) {
+        if self.tabs.len() < 2 {
+            return self.error("E784: Cannot close last tab page".to_string());
         }
+
+        self.tabs.remove(self.tab);
+        self.tab = self.tab.min(self.tabs.len() - 1);
+        self.load_window();
     }
 
-    fn new_line(&mut self) {
-        self.idx += 1;
-        self.col = 0;
-        self.line += 1;
+    // the active window's buffer and cursor live in `buffers`, inactive
+    // windows keep their own copy
+    fn store_window(&mut self) {
+        let (buffer, cursor) = (self.buffer().id(), self.buffer().cursor_pos());
+        let current = self.current_window();
+        if let Some(window) = self.windows_mut().get_mut(current) {
+            window.buffer = buffer;
+            window.cursor = cursor;
+        }
     }
-}
 
-impl Editor {
-    pub fn new() -> Editor {
-        Editor {
-            mode: Mode::Normal,
-            buffer: String::with_capacity(INITIAL_BUFFER_SIZE),
-            lines: vec![Line {
-                idx: 0,
-                tokens: Vec::new(),
-            }],
-            cursor: Pos {
-                idx: 0,
-                line: 0,
-                col: 0,
-            },
-            input_buffer: InputBuffer::new(),
-            command_buffer: CmdBuffer::new(),
+    fn load_window(&mut self) {
+        let Some(window) = self.windows().get(self.current_window()) else {
+            return;
+        };
+        let (id, (line, col)) = (window.buffer, window.cursor);
+
+        if let Some(idx) = self.buffers.iter().position(|b| b.id() == id) {
+            self.current = idx;
+            self.buffer_mut().set_cursor_pos(line, col);
         }
     }
 
-    pub fn from_file(path: &str) -> Result<Editor, EdiError> {
-        let contents = std::fs::read_to_string(path)?;
-        let mut editor = Self::new();
+    fn split_window(&mut self, split: Split, path: &str) {
+        self.store_window();
+        let id = self.next_window_id();
+        let (buffer, cursor) = (self.buffer().id(), self.buffer().cursor_pos());
 
-        editor.buffer = contents;
-        editor.tokenize();
+        self.windows_mut().split(id, split, buffer, cursor);
+        self.windows_mut().set_current(id);
 
-        Ok(editor)
+        self.edit_file(path);
     }
 
-    pub fn cursor(&self) -> V2 {
-        V2 {
-            x: self.cursor.col as f32,
-            y: self.cursor.line as f32,
+    // :InspectTree, showing the syntax tree of the buffer in a new window
+    fn inspect_tree(&mut self) {
+        let Some(outline) = self.buffer().inspect_tree() else {
+            let message = if cfg!(feature = "tree-sitter") {
+                "No parser for the current buffer"
+            } else {
+                "E319: Sorry, the command is not available in this version"
+            };
+            return self.error(message.to_string());
+        };
+
+        let mut buffer = match Buffer::from_reader(outline.as_bytes()) {
+            Ok(buffer) => buffer,
+            Err(err) => return self.error(err.to_string()),
+        };
+        buffer.set_readonly(true);
+
+        self.split_window(Split::Vertical, "");
+        let idx = self.add_buffer(buffer);
+        self.switch_buffer(idx);
+    }
+
+    fn close_window(&mut self) {
+        let current = self.current_window();
+        if self.windows_mut().close(current) {
+            self.load_window();
+        } else {
+            self.error("E444: Cannot close last window".to_string());
         }
     }
 
-    pub fn iter(&self) -> LineIter {
-        LineIter {
-            editor: self,
-            idx: 0,
+    fn move_window(&mut self, direction: Direction) {
+        if let Some(id) = self.windows().neighbor(direction) {
+            self.switch_window(id);
         }
     }
 
+    pub fn message(&self) -> Option<&str> {
+        self.messages.current().map(|message| message.text.as_str())
+    }
+
+    pub fn current_message(&self) -> Option<&Message> {
+        self.messages.current()
+    }
+
+    fn show(&mut self, message: String) {
+        self.messages.info(message);
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.messages.error(message);
+    }
+
     pub fn exit_insert(&mut self) {
         if self.mode == Mode::Insert {
-            self.mode = Mode::Normal
+            self.mode = Mode::Normal;
+            self.buffer_mut().commit();
         }
     }
 
@@ -253,561 +449,1285 @@ impl Editor {
 
     fn enter_command(&mut self) {
         self.mode = Mode::Command;
-        self.command_buffer.input(":");
+        self.command_buffer.start(':');
+    }
+
+    fn enter_search(&mut self) {
+        self.mode = Mode::Command;
+        self.command_buffer.start('/');
+        self.search_start = Some(self.buffer().cursor_pos());
     }
 
     pub fn exit_command(&mut self) {
         if self.mode == Mode::Command {
             self.mode = Mode::Normal;
             self.command_buffer.reset();
+            self.restore_search_start();
         }
     }
 
-    pub fn command_delete_char(&mut self) {
-        if self.mode == Mode::Command {
-            self.command_buffer.delete_char();
+    fn restore_search_start(&mut self) {
+        if let Some((line, col)) = self.search_start.take() {
+            self.buffer_mut().set_cursor_pos(line, col);
         }
     }
 
-    pub fn command_execute(&mut self) -> ExCmdResult {
-        let result = self.command_buffer.execute();
-        match &result {
-            ExCmdResult::Command(cmd) => {
-                // TODO
-            }
-            _ => (),
-        }
-        result
+    // moves the cursor to the first match of the pattern typed so far
+    fn incsearch(&mut self) {
+        let Some((line, col)) = self.search_start else {
+            return;
+        };
+        let pattern = self.typed_pattern().to_string();
+        let buffer = self.buffer_mut();
+        buffer.set_cursor_pos(line, col);
+        buffer.search(&pattern);
     }
 
-    pub fn enter_insert_after(&mut self) {
-        if self.mode == Mode::Normal {
-            if self.cursor.col < self.line_len(self.line()) {
-                self.cursor.next(1);
-            }
-            self.mode = Mode::Insert
+    // pattern whose matches are highlighted, the one typed so far while
+    // searching
+    pub fn search_highlight(&self) -> Option<&str> {
+        if self.is_searching() {
+            return Some(self.typed_pattern()).filter(|pattern| !pattern.is_empty());
         }
+        self.hlsearch.as_deref()
+    }
+
+    // search command line without its prompt
+    fn typed_pattern(&self) -> &str {
+        self.command_buffer.as_str().get(1..).unwrap_or("")
     }
 
-    pub fn move_left(&mut self) {
-        self.cursor.prev(1);
+    // whether a search pattern is being typed
+    pub fn is_searching(&self) -> bool {
+        self.mode == Mode::Command && self.search_start.is_some()
     }
 
-    pub fn move_right(&mut self) {
-        if self.cursor.col < self.line_len(self.line()) {
-            self.cursor.next(1);
+    // backspace on an empty command line leaves command mode
+    pub fn command_delete_char(&mut self) {
+        if self.mode == Mode::Command {
+            self.command_buffer.delete_char();
+            if self.command_buffer.as_str().is_empty() {
+                self.exit_command();
+            }
         }
     }
 
-    pub fn next_word_end(&mut self) {
-        let line = &self.lines[self.cursor.line];
-        let line_next_word = line
-            .current_word(self.cursor.idx)
-            .filter(|token| token.idx() + token.len() > self.cursor.idx + 1)
-            .or_else(|| line.next_word(self.cursor.idx))
-            .map(|token| token.end_pos(line, self.cursor.line));
+    // byte offset of the cursor in the command line
+    pub fn command_cursor(&self) -> usize {
+        self.command_buffer.cursor()
+    }
 
-        if let Some(next) = line_next_word.or_else(|| {
-            self.next_line().and_then(|line| {
-                line.tokens
-                    .iter()
-                    .next()
-                    .map(|token| token.end_pos(line, self.cursor.line + 1))
-            })
-        }) {
-            self.cursor = next;
-        } else {
-            self.move_down();
+    // contents of the read-only registers for Ctrl-r in the command line
+    fn register(&self, key: Key) -> Option<String> {
+        match key {
+            Key::Char('%') => self.buffer().path().map(str::to_string),
+            Key::Char('#') => self
+                .alternate
+                .and_then(|id| self.buffers.iter().find(|b| b.id() == id))
+                .and_then(|buffer| buffer.path())
+                .map(str::to_string),
+            Key::Char(prompt @ (':' | '/')) => self.command_buffer.last(prompt).map(str::to_string),
+            _ => None,
         }
     }
 
-    pub fn next_word(&mut self) {
-        let line = &self.lines[self.cursor.line];
-        let line_next_word = line
-            .next_word(self.cursor.idx)
-            .map(|token| token.start_pos(line, self.cursor.line));
-
-        if let Some(next) = line_next_word.or_else(|| {
-            self.next_line().and_then(|line| {
-                line.tokens
+    // Tab and Shift-Tab cycle through the completions of the word before
+    // the cursor, any other key accepts the current one
+    fn complete(&mut self, forward: bool) {
+        if self.completion.is_none() {
+            let Some((kind, start)) = self.command_buffer.completion_target() else {
+                return;
+            };
+            let word = &self.command_buffer.as_str()[start..self.command_buffer.cursor()];
+            let candidates = match kind {
+                CompletionKind::Command => excmd::command_names(word)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                CompletionKind::Option => Options::names()
+                    .filter(|name| name.starts_with(word))
+                    .map(str::to_string)
+                    .collect(),
+                CompletionKind::Buffer => self
+                    .buffers
                     .iter()
-                    .next()
-                    .map(|token| token.start_pos(line, self.cursor.line + 1))
-            })
-        }) {
-            self.cursor = next;
-        } else {
-            self.move_down();
+                    .map(|buffer| buffer.name().to_string())
+                    .filter(|name| name.contains(word))
+                    .collect(),
+                CompletionKind::Path => completion::complete_path(word),
+                CompletionKind::Highlight => Group::names()
+                    .filter(|name| name.starts_with(word))
+                    .map(str::to_string)
+                    .collect(),
+                CompletionKind::ColorScheme => {
+                    let dir = options::config_dir().map(|dir| dir.join("colors"));
+                    highlight::scheme_names(dir.as_deref())
+                        .into_iter()
+                        .filter(|name| name.starts_with(word))
+                        .collect()
+                }
+            };
+
+            if candidates.is_empty() {
+                return;
+            }
+            self.completion = Some(Completion::new(candidates, start, word.to_string()));
+        }
+
+        if let Some(completion) = self.completion.as_mut() {
+            let start = completion.start();
+            let text = completion.cycle(forward).to_string();
+            self.command_buffer.replace(start, &text);
         }
     }
 
-    pub fn prev_word(&mut self) {
-        let line = &self.lines[self.cursor.line];
-        let line_prev_word = line
-            .current_word(self.cursor.idx)
-            .filter(|token| token.idx() < self.cursor.idx)
-            .or_else(|| line.prev_word(self.cursor.idx))
-            .map(|token| token.start_pos(line, self.cursor.line));
+    pub fn wildmenu(&self) -> Option<&Completion> {
+        self.completion
+            .as_ref()
+            .filter(|_| self.mode == Mode::Command)
+    }
 
-        if let Some(next) = line_prev_word.or_else(|| {
-            self.prev_line().and_then(|line| {
-                line.tokens
-                    .last()
-                    .map(|token| token.start_pos(line, self.cursor.line - 1))
-            })
-        }) {
-            self.cursor = next;
+    fn search(&mut self, pattern: &str, forward: bool) {
+        if pattern.is_empty() {
+            return self.error("E35: No previous regular expression".to_string());
+        }
+        let buffer = self.buffer_mut();
+        let found = if forward {
+            buffer.search(pattern)
         } else {
-            self.move_up();
+            buffer.search_backward(pattern)
+        };
+        if !found {
+            self.error(format!("E486: Pattern not found: {pattern}"));
         }
     }
 
-    pub fn start_prev_line(&mut self) {
-        let line_start = self.line().start();
+    // n and N search for the last pattern again and highlight it after
+    // :nohlsearch
+    fn search_next(&mut self, forward: bool) {
+        let pattern = self
+            .command_buffer
+            .last('/')
+            .unwrap_or_default()
+            .to_string();
+        self.search(&pattern, forward);
+        if !pattern.is_empty() {
+            self.hlsearch = Some(pattern);
+        }
+    }
 
-        self.buffer.insert(line_start, '\n');
+    pub fn command_execute(&mut self) -> ExCmdResult {
+        self.restore_search_start();
+        let result = self.command_buffer.execute();
+        self.execute(result)
+    }
 
-        self.cursor.col = 0;
-        self.cursor.idx = line_start;
+    // runs the ex command or search of the command line or a sourced line
+    fn execute(&mut self, mut result: ExCmdResult) -> ExCmdResult {
+        let mut quit = false;
 
-        self.tokenize();
-        self.enter_insert();
-    }
+        if let ExCmdResult::Command(cmd, args) = &result {
+            let (force, args) = match args.strip_prefix('!') {
+                Some(args) => (true, args.trim_start()),
+                None => (false, args.as_str()),
+            };
 
-    pub fn start_next_line(&mut self) {
-        let line_end = self.line().end();
+            match cmd {
+                ExCmdType::Substitute => self.buffer_mut().substitute(args),
+                ExCmdType::Edit => self.edit_file(args),
+                ExCmdType::New => {
+                    let idx = self.add_buffer(Buffer::new());
+                    self.switch_buffer(idx);
+                }
+                ExCmdType::BufferNext => self.cycle_buffer(1),
+                ExCmdType::BufferPrev => self.cycle_buffer(self.buffers.len() - 1),
+                ExCmdType::Buffer => match self.find_buffer(args) {
+                    Ok(idx) => self.switch_buffer(idx),
+                    Err(err) => self.error(err),
+                },
+                ExCmdType::Buffers => self.messages.print(self.list_buffers()),
+                ExCmdType::Messages => self.messages.print(self.messages.history()),
+                ExCmdType::BufferDelete => self.delete_buffer(args, force),
+                ExCmdType::Write => self.write_buffer(args, force),
+                ExCmdType::Read => self.read_file(args),
+                ExCmdType::Split => self.split_window(Split::Horizontal, args),
+                ExCmdType::VSplit => self.split_window(Split::Vertical, args),
+                ExCmdType::Close => self.close_window(),
+                ExCmdType::Only => self.windows_mut().only(),
+                ExCmdType::TabNew => {
+                    self.new_tab();
+                    if args.is_empty() {
+                        let idx = self.add_buffer(Buffer::new());
+                        self.switch_buffer(idx);
+                    } else {
+                        self.edit_file(args);
+                    }
+                }
+                ExCmdType::TabNext => self.next_tab(args),
+                ExCmdType::TabPrev => self.cycle_tab(self.tabs.len() - 1),
+                ExCmdType::TabClose => self.close_tab(),
+                ExCmdType::Set => match self.options.set(args) {
+                    Ok(shown) if shown.is_empty() => (),
+                    Ok(shown) => self.messages.print(shown),
+                    Err(err) => self.error(err),
+                },
+                ExCmdType::Source => self.source_file(args),
+                ExCmdType::Highlight => match self.highlights.set(args) {
+                    Ok(shown) if shown.is_empty() => (),
+                    Ok(shown) => self.messages.print(shown),
+                    Err(err) => self.error(err),
+                },
+                ExCmdType::ColorScheme => self.color_scheme(args.trim()),
+                ExCmdType::InspectTree => self.inspect_tree(),
+                ExCmdType::NoHlSearch => self.hlsearch = None,
+                ExCmdType::Map(mode, noremap) => self.map(*mode, *noremap, args, force),
+                ExCmdType::Unmap(mode) => self.unmap(*mode, args, force),
+                // :q closes the current window as long as there are others
+                ExCmdType::Quit if self.windows().len() > 1 => self.close_window(),
+                ExCmdType::Quit if self.tabs.len() > 1 => self.close_tab(),
+                ExCmdType::Quit => match self.unsaved_changes(force) {
+                    Some(err) => self.error(err),
+                    None => quit = true,
+                },
+                ExCmdType::CancelQuit => (),
+            }
+            self.buffer_mut().commit();
+        }
 
-        self.buffer.insert(line_end, '\n');
+        if let ExCmdResult::Search(pattern) = &result {
+            self.search(pattern, true);
+            self.hlsearch = Some(pattern.clone());
+        }
 
-        self.cursor.line = self.cursor.line + 1;
-        self.cursor.col = 0;
-        self.cursor.idx = line_end + 1;
+        if let ExCmdResult::UnknownCommand(cmd) = &result {
+            let cmd = cmd.strip_prefix(':').unwrap_or(cmd).trim();
+            if !cmd.is_empty() {
+                self.error(format!("E492: Not an editor command: {cmd}"));
+            }
+        }
 
-        self.tokenize();
-        self.enter_insert();
+        if quit {
+            result = ExCmdResult::Quit(true);
+        }
+        self.mode = Mode::Normal;
+        result
     }
 
-    pub fn append_line(&mut self) {
-        self.move_end_of_line();
-        self.enter_insert();
-    }
+    // :e path, switching to an already loaded buffer of the same file
+    fn edit_file(&mut self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
 
-    pub fn prepend_line(&mut self) {
-        self.move_start_of_line();
-        self.enter_insert();
+        if let Some(idx) = self.buffers.iter().position(|b| b.path() == Some(path)) {
+            self.switch_buffer(idx);
+            return;
+        }
+
+        match Buffer::from_file(path) {
+            Ok(buffer) => {
+                let idx = self.add_buffer(buffer);
+                self.switch_buffer(idx);
+            }
+            Err(err) => self.error(format!("\"{path}\": {err}")),
+        }
     }
 
-    pub fn handle_command(&mut self, input: &str) {
-        self.command_buffer.input(input);
+    // :w [path], writing a copy if the buffer already has another name
+    // :r {file} inserts the file below the cursor line, without a file
+    // name the file of the buffer
+    fn read_file(&mut self, path: &str) {
+        let path = match (path, self.buffer().path()) {
+            ("", Some(own)) => own.to_string(),
+            ("", None) => return self.error("E32: No file name".to_string()),
+            (path, _) => path.to_string(),
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => self.buffer_mut().insert_below(&text),
+            Err(err) => self.error(format!("E484: Can't open file {path}: {err}")),
+        }
     }
 
-    pub fn handle_normal(&mut self, input: &str) -> bool {
-        if let Some(cmd) = self.input_buffer.check(&input) {
-            let action = match cmd.cmd {
-                CommandType::EnterInsert => Editor::enter_insert,
-                CommandType::EnterInsertAfter => Editor::enter_insert_after,
-                CommandType::EnterCommand => Editor::enter_command,
-                CommandType::MoveLeft => Editor::move_left,
-                CommandType::MoveDown => Editor::move_down,
-                CommandType::MoveRight => Editor::move_right,
-                CommandType::MoveUp => Editor::move_up,
-                CommandType::MoveEndOfLine => Editor::move_end_of_line,
-                CommandType::MoveStartOfLine => Editor::move_start_of_line,
-                CommandType::NextWord => Editor::next_word,
-                CommandType::NextWordEnd => Editor::next_word_end,
-                CommandType::PrevWord => Editor::prev_word,
-                CommandType::StartNextLine => Editor::start_next_line,
-                CommandType::StartPrevLine => Editor::start_prev_line,
-                CommandType::AppendLine => Editor::append_line,
-                CommandType::PrependLine => Editor::prepend_line,
-                CommandType::DeleteLine => Editor::delete_line,
-                CommandType::DeleteChar => Editor::delete_char,
-            };
+    fn write_buffer(&mut self, path: &str, force: bool) {
+        let buffer = self.buffer_mut();
 
-            for _ in 0..cmd.repeat {
-                action(self);
+        if buffer.is_readonly() && !force {
+            return self.error("E45: 'readonly' option is set (add ! to override)".to_string());
+        }
+
+        if !path.is_empty() && buffer.path().is_none() {
+            buffer.set_path(path);
+        }
+
+        let result = match buffer.path() {
+            Some(name) if path.is_empty() || path == name => buffer.write(),
+            Some(_) => buffer.write_file(path),
+            None => return self.error("E32: No file name".to_string()),
+        };
+        let name = if path.is_empty() { buffer.name() } else { path };
+
+        match result {
+            Ok(bytes) => {
+                let message = format!("\"{name}\" {}L, {bytes}B written", buffer.line_count());
+                self.show(message)
+            }
+            Err(err) => {
+                let message = format!("\"{name}\" {err}");
+                self.error(message)
             }
-            true
-        } else {
-            false
         }
     }
 
-    pub fn update(&mut self, delta: f32) {
-        self.input_buffer.update(delta);
+    fn cycle_buffer(&mut self, offset: usize) {
+        let idx = (self.current + offset) % self.buffers.len();
+        self.switch_buffer(idx);
     }
 
-    fn line(&self) -> &Line {
-        &self.lines[self.cursor.line]
+    pub fn alternate_buffer(&mut self) {
+        match self
+            .alternate
+            .and_then(|id| self.buffers.iter().position(|b| b.id() == id))
+        {
+            Some(idx) => self.switch_buffer(idx),
+            None => self.error("E23: No alternate file".to_string()),
+        }
     }
 
-    fn next_line(&self) -> Option<&Line> {
-        if self.lines.len() > self.cursor.line + 1 {
-            Some(&self.lines[self.cursor.line + 1])
-        } else {
-            None
+    // buffer index by number or by a unique part of its name
+    fn find_buffer(&self, arg: &str) -> Result<usize, String> {
+        if arg.is_empty() {
+            return Ok(self.current);
+        }
+
+        if let Ok(id) = arg.parse::<usize>() {
+            return self
+                .buffers
+                .iter()
+                .position(|b| b.id() == id)
+                .ok_or_else(|| format!("E86: Buffer {id} does not exist"));
+        }
+
+        let mut matches = self
+            .buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.path().is_some_and(|path| path.contains(arg)));
+
+        match (matches.next(), matches.next()) {
+            (Some((idx, _)), None) => Ok(idx),
+            (Some(_), Some(_)) => Err(format!("E93: More than one match for {arg}")),
+            _ => Err(format!("E94: No matching buffer for {arg}")),
         }
     }
 
-    fn prev_line(&self) -> Option<&Line> {
-        if self.cursor.line > 0 {
-            Some(&self.lines[self.cursor.line - 1])
-        } else {
-            None
+    fn list_buffers(&self) -> String {
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(idx, buffer)| {
+                let flag = if idx == self.current {
+                    '%'
+                } else if Some(buffer.id()) == self.alternate {
+                    '#'
+                } else {
+                    ' '
+                };
+                let modified = if buffer.is_modified() { '+' } else { ' ' };
+
+                format!(
+                    "{:>3} {flag}{modified} \"{}\" line {}",
+                    buffer.id(),
+                    buffer.name(),
+                    buffer.cursor_pos().0 + 1
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // :q without ! refuses to drop the changes of the current buffer or
+    // of a hidden one
+    fn unsaved_changes(&self, force: bool) -> Option<String> {
+        if force {
+            return None;
+        }
+        if self.buffer().is_modified() {
+            return Some("E37: No write since last change (add ! to override)".to_string());
         }
+        self.buffers
+            .iter()
+            .find(|buffer| buffer.is_modified())
+            .map(|buffer| {
+                format!(
+                    "E162: No write since last change for buffer \"{}\"",
+                    buffer.name()
+                )
+            })
     }
 
-    pub fn move_start_of_line(&mut self) {
-        let line_start = self.line().start();
+    fn delete_buffer(&mut self, arg: &str, force: bool) {
+        let idx = match self.find_buffer(arg) {
+            Ok(idx) => idx,
+            Err(err) => return self.error(err),
+        };
+
+        let id = self.buffers[idx].id();
+        if !force && self.buffers[idx].is_modified() {
+            return self.error(format!(
+                "E89: No write since last change for buffer {id} (add ! to override)"
+            ));
+        }
+
+        let alternate = self
+            .alternate
+            .filter(|alt| *alt != id)
+            .and_then(|alt| self.buffers.iter().position(|b| b.id() == alt));
+
+        self.buffers.remove(idx);
+        if self.alternate == Some(id) {
+            self.alternate = None;
+        }
+
+        if self.buffers.is_empty() {
+            self.current = self.add_buffer(Buffer::new());
+        } else if idx == self.current {
+            // prefer the alternate buffer over the following one
+            let next = match alternate {
+                Some(alt) if alt > idx => alt - 1,
+                Some(alt) => alt,
+                None => idx,
+            };
+            self.current = next.min(self.buffers.len() - 1);
+            self.alternate = None;
+        } else if idx < self.current {
+            self.current -= 1;
+        }
 
-        self.cursor.col = 0;
-        self.cursor.idx = line_start;
+        let current = self.buffer().id();
+        for tab in &mut self.tabs {
+            for window in tab.iter_mut().filter(|window| window.buffer == id) {
+                window.buffer = current;
+                window.cursor = (0, 0);
+            }
+        }
     }
 
-    pub fn move_end_of_line(&mut self) {
-        let current_line = self.line();
-        let line_end = current_line.end();
+    pub fn handle_key(&mut self, key: Key) -> Option<ExCmdResult> {
+        // a multi-line message is dismissed by ENTER, any other key is
+        // handled as usual
+        let pager = self.messages.current().is_some_and(Message::is_multiline);
+        self.messages.clear();
+        if !matches!(key, Key::Tab | Key::ShiftTab) {
+            self.completion = None;
+        }
+        if pager && matches!(key, Key::Enter | Key::Escape | Key::Char(' ')) {
+            return None;
+        }
+
+        self.pending_keys.push(key);
+        self.map_waited = 0.0;
+        self.resolve_keys(false)
+    }
 
-        self.cursor.col = line_end - current_line.start();
-        self.cursor.idx = line_end;
+    fn map_mode(&self) -> MapMode {
+        match self.mode {
+            Mode::Normal => MapMode::Normal,
+            Mode::Insert => MapMode::Insert,
+            Mode::Command => MapMode::Command,
+        }
     }
 
-    pub fn move_down(&mut self) {
-        if self.cursor.line + 1 < self.lines.len() {
-            self.cursor.line += 1;
+    // runs the pending keys through the mappings of the current mode; keys
+    // that may start a longer mapping wait for more input unless the
+    // keys timed out, then the longest mapped prefix is used
+    fn resolve_keys(&mut self, timeout: bool) -> Option<ExCmdResult> {
+        let mut result = None;
+
+        while !self.pending_keys.is_empty() {
+            let mode = self.map_mode();
+            let keys = key_notation(&self.pending_keys);
+            if !timeout && matches!(self.mappings.lookup(mode, &keys), InputMatch::Partial(_)) {
+                break;
+            }
 
-            let line = &self.lines[self.cursor.line];
-            let column = self.cursor.col.min(self.line_len(line));
-            let line_start_idx = line.start();
+            let mapped = (1..=self.pending_keys.len()).rev().find_map(|len| {
+                let keys = key_notation(&self.pending_keys[..len]);
+                match self.mappings.lookup(mode, &keys) {
+                    InputMatch::Full(mapping) | InputMatch::Partial(Some(mapping)) => {
+                        Some((len, mapping.clone()))
+                    }
+                    _ => None,
+                }
+            });
 
-            self.cursor.col = self.cursor.col.min(self.line_len(line));
-            self.cursor.idx = line_start_idx + column;
+            let key_result = match mapped {
+                Some((len, mapping)) => {
+                    self.pending_keys.drain(..len);
+                    self.apply_mapping(&mapping)
+                }
+                None => {
+                    let key = self.pending_keys.remove(0);
+                    self.process_key(key)
+                }
+            };
+            result = key_result.or(result);
         }
+        result
     }
 
-    pub fn move_up(&mut self) {
-        if self.cursor.line > 0 {
-            self.cursor.line -= 1;
+    fn apply_mapping(&mut self, mapping: &Mapping) -> Option<ExCmdResult> {
+        let mut keys = Key::parse(&mapping.rhs);
+        if mapping.noremap {
+            return keys
+                .into_iter()
+                .fold(None, |result, key| self.process_key(key).or(result));
+        }
+
+        if self.map_depth >= MAX_MAP_DEPTH {
+            self.pending_keys.clear();
+            self.map_aborted = true;
+            self.error("E223: Recursive mapping".to_string());
+            return None;
+        }
+
+        // a right hand side starting with the left hand side, like
+        // `:nmap n nzz`, does not map those keys again
+        let lhs = Key::parse(&mapping.lhs);
+        let mut result = None;
+        if keys.starts_with(&lhs) {
+            for key in keys.drain(..lhs.len()) {
+                result = self.process_key(key).or(result);
+            }
+        }
+
+        // the other keys of the right hand side are mapped on their own
+        let typed = std::mem::replace(&mut self.pending_keys, keys);
+        self.map_depth += 1;
+        result = self.resolve_keys(true).or(result);
+        self.map_depth -= 1;
+
+        // E223 drops the keys of every mapping being expanded and the
+        // typed keys after them
+        if self.map_aborted {
+            self.map_aborted = self.map_depth > 0;
+            return result;
+        }
+        self.pending_keys.splice(0..0, typed);
+        result
+    }
 
-            let line = &self.lines[self.cursor.line];
-            let column = self.cursor.col.min(self.line_len(line));
-            let line_start_idx = line.start();
+    fn process_key(&mut self, key: Key) -> Option<ExCmdResult> {
+        match self.mode {
+            Mode::Normal => match key {
+                Key::Escape => self.input_buffer.reset(),
+                key => {
+                    self.handle_normal(&key.to_string());
+                }
+            },
+            Mode::Insert => match key {
+                Key::Char(c) => self.buffer_mut().insert(c.encode_utf8(&mut [0; 4])),
+                Key::Escape => self.exit_insert(),
+                Key::Enter => self.buffer_mut().new_line(),
+                Key::Backspace => self.buffer_mut().delete(),
+                Key::Delete => self.buffer_mut().delete_char(),
+                Key::Left => self.buffer_mut().move_left(),
+                Key::Right => self.buffer_mut().move_right(),
+                Key::Up => self.buffer_mut().move_up(),
+                Key::Down => self.buffer_mut().move_down(),
+                Key::Home => self.buffer_mut().move_start_of_line(),
+                Key::End => self.buffer_mut().move_end_of_line(),
+                _ => (),
+            },
+            Mode::Command if matches!(key, Key::Tab | Key::ShiftTab) => {
+                self.complete(key == Key::Tab);
+            }
+            Mode::Command if self.command_buffer.take_register() => {
+                if let Some(text) = self.register(key) {
+                    self.command_buffer.input(&text);
+                }
+            }
+            Mode::Command => match key {
+                Key::Char(c) => self.handle_command(c.encode_utf8(&mut [0; 4])),
+                Key::Escape => self.exit_command(),
+                Key::Backspace => self.command_delete_char(),
+                Key::Delete => self.command_buffer.delete_char_under(),
+                Key::Left => self.command_buffer.move_left(),
+                Key::Right => self.command_buffer.move_right(),
+                Key::Home | Key::Ctrl('b') => self.command_buffer.move_start(),
+                Key::End | Key::Ctrl('e') => self.command_buffer.move_end(),
+                Key::Up => self.command_buffer.history_prev(),
+                Key::Down => self.command_buffer.history_next(),
+                Key::Ctrl('w') => self.command_buffer.delete_word(),
+                Key::Ctrl('u') => self.command_buffer.delete_to_start(),
+                Key::Ctrl('r') => self.command_buffer.await_register(),
+                Key::Enter => return Some(self.command_execute()),
+                _ => (),
+            },
+        }
+        if self.mode == Mode::Command {
+            self.incsearch();
+        }
+        None
+    }
 
-            self.cursor.col = column;
-            self.cursor.idx = line_start_idx + column;
+    pub fn enter_insert_after(&mut self) {
+        if self.mode == Mode::Normal {
+            self.buffer_mut().move_right();
+            self.mode = Mode::Insert
         }
     }
 
-    pub fn new_line(&mut self) {
-        self.buffer.insert(self.cursor.idx, '\n');
-        self.cursor.new_line();
-        self.tokenize();
+    pub fn start_prev_line(&mut self) {
+        self.buffer_mut().open_line_above();
+        self.enter_insert();
     }
 
-    pub fn insert(&mut self, input: &str) {
-        self.buffer.insert_str(self.cursor.idx, input);
-        self.cursor.next(input.len());
-        self.tokenize();
+    pub fn start_next_line(&mut self) {
+        self.buffer_mut().open_line_below();
+        self.enter_insert();
     }
 
-    pub fn delete_char(&mut self) {
-        let line_len = self.line_len(self.line());
+    pub fn append_line(&mut self) {
+        self.buffer_mut().move_end_of_line();
+        self.enter_insert();
+    }
 
-        if self.cursor.idx < self.buffer.len() && self.cursor.col < line_len {
-            self.buffer.remove(self.cursor.idx);
+    pub fn prepend_line(&mut self) {
+        self.buffer_mut().move_start_of_line();
+        self.enter_insert();
+    }
 
-            self.tokenize();
+    fn undo(&mut self) {
+        if !self.buffer_mut().undo() {
+            self.show("Already at oldest change".to_string());
         }
     }
 
-    pub fn delete_line(&mut self) {
-        let start_idx = self.line().start();
-        let len = self.line().end() - start_idx + 1;
-        for _ in 0..len {
-            let idx = start_idx.max(1) - 1;
-            if idx < self.buffer.len() {
-                self.buffer.remove(idx);
-            }
-        }
-        if let Some(next_line) = self.next_line() {
-            self.cursor.col = (next_line.end() - next_line.start()).min(self.cursor.col);
-        } else {
-            if self.cursor.line > 0 {
-                self.cursor.line -= 1;
-            }
-            self.cursor.idx = 0;
-            self.cursor.col = 0;
+    fn redo(&mut self) {
+        if !self.buffer_mut().redo() {
+            self.show("Already at newest change".to_string());
         }
-        self.tokenize();
     }
 
-    pub fn delete(&mut self) {
-        if self.cursor.idx > 0 && self.cursor.idx <= self.buffer.len() {
-            self.cursor.idx -= 1;
-            self.buffer.remove(self.cursor.idx);
+    pub fn handle_command(&mut self, input: &str) {
+        self.command_buffer.input(input);
+    }
 
-            // update cursor position
-            if self.cursor.col > 0 {
-                self.cursor.col -= 1;
-            } else if self.cursor.line > 0 {
-                self.cursor.line -= 1;
-                self.cursor.col = self.line_len(&self.lines[self.cursor.line]);
+    pub fn handle_normal(&mut self, input: &str) -> bool {
+        let actions = self.input_buffer.check(input);
+        let handled = !actions.is_empty();
+        for action in actions {
+            self.run_action(action);
+        }
+        handled
+    }
+
+    fn run_action(&mut self, cmd: Action) {
+        let action: fn(&mut Editor) = match cmd.cmd {
+            CommandType::EnterInsert => Editor::enter_insert,
+            CommandType::EnterInsertAfter => Editor::enter_insert_after,
+            CommandType::EnterCommand => Editor::enter_command,
+            CommandType::EnterSearch => Editor::enter_search,
+            CommandType::SearchNext => |e| e.search_next(true),
+            CommandType::SearchPrev => |e| e.search_next(false),
+            CommandType::MoveLeft => |e| e.buffer_mut().move_left(),
+            CommandType::MoveDown => |e| e.buffer_mut().move_down(),
+            CommandType::MoveRight => |e| e.buffer_mut().move_right(),
+            CommandType::MoveUp => |e| e.buffer_mut().move_up(),
+            CommandType::MoveDownRow => |e| e.move_row(true),
+            CommandType::MoveUpRow => |e| e.move_row(false),
+            CommandType::MoveEndOfLine => |e| e.buffer_mut().move_end_of_line(),
+            CommandType::MoveStartOfLine => |e| e.buffer_mut().move_start_of_line(),
+            CommandType::NextWord => |e| e.buffer_mut().next_word(),
+            CommandType::NextWordEnd => |e| e.buffer_mut().next_word_end(),
+            CommandType::PrevWord => |e| e.buffer_mut().prev_word(),
+            CommandType::StartNextLine => Editor::start_next_line,
+            CommandType::StartPrevLine => Editor::start_prev_line,
+            CommandType::AppendLine => Editor::append_line,
+            CommandType::PrependLine => Editor::prepend_line,
+            CommandType::DeleteLine => |e| e.buffer_mut().delete_line(),
+            CommandType::DeleteWord => |e| e.buffer_mut().delete_word(),
+            CommandType::DeleteChar => |e| e.buffer_mut().delete_char(),
+            // the only command with arguments
+            CommandType::DeleteObject(object, around) => {
+                for _ in 0..cmd.repeat {
+                    self.buffer_mut().delete_object(object, around);
+                }
+                return self.buffer_mut().commit();
             }
-            self.tokenize();
+            CommandType::NextFunction => |e| e.buffer_mut().next_function(true),
+            CommandType::PrevFunction => |e| e.buffer_mut().next_function(false),
+            CommandType::ScrollCenter => |e| e.align(Align::Center),
+            CommandType::ScrollTop => |e| e.align(Align::Top),
+            CommandType::ScrollBottom => |e| e.align(Align::Bottom),
+            CommandType::ZoomIn => |e| e.zoom(ZOOM_STEP as isize),
+            CommandType::ZoomOut => |e| e.zoom(-(ZOOM_STEP as isize)),
+            CommandType::ZoomReset => |e| e.options.zoom = 0,
+            CommandType::Undo => Editor::undo,
+            CommandType::Redo => Editor::redo,
+            CommandType::AlternateBuffer => Editor::alternate_buffer,
+            CommandType::WindowLeft => |e| e.move_window(Direction::Left),
+            CommandType::WindowDown => |e| e.move_window(Direction::Down),
+            CommandType::WindowUp => |e| e.move_window(Direction::Up),
+            CommandType::WindowRight => |e| e.move_window(Direction::Right),
+            CommandType::WindowNext => |e| e.switch_window(e.windows().next()),
+            CommandType::WindowClose => Editor::close_window,
+            CommandType::WindowOnly => |e| e.windows_mut().only(),
+            CommandType::WindowEqualize => |e| e.windows_mut().equalize(),
+            CommandType::WindowSplit => |e| e.split_window(Split::Horizontal, ""),
+            CommandType::WindowVSplit => |e| e.split_window(Split::Vertical, ""),
+            CommandType::TabNext => |e| e.cycle_tab(1),
+            CommandType::TabPrev => |e| e.cycle_tab(e.tabs.len() - 1),
+        };
+
+        for _ in 0..cmd.repeat {
+            action(self);
+        }
+
+        // an insert started by this command belongs to the same undo step
+        if self.mode == Mode::Normal {
+            self.buffer_mut().commit();
         }
     }
 
-    fn line_len(&self, line: &Line) -> usize {
-        // move to previous line's end
-        WordIter {
-            editor: self,
-            line,
-            idx: 0,
+    // advances the key timeouts by `delta` milliseconds
+    pub fn update(&mut self, delta: f32) -> Option<ExCmdResult> {
+        let timeout = self.options.key_timeout();
+        self.input_buffer.set_timeout(timeout);
+        for action in self.input_buffer.update(delta) {
+            self.run_action(action);
+        }
+
+        let keys = key_notation(&self.pending_keys);
+        let wait = timeout.wait_for(&keys).filter(|_| !keys.is_empty())?;
+        self.map_waited += delta;
+        if self.map_waited < wait {
+            return None;
         }
-        .map(|word| word.len())
-        .sum()
+        self.resolve_keys(true)
     }
 
-    fn tokenize(&mut self) {
-        let mut lines = Vec::new();
-        let mut tokens = Vec::new();
-        let mut tokenizer = Tokenizer::new();
-        let mut start_of_line = 0usize;
+    // :map {lhs} {rhs}, listing the mappings starting with {lhs} if there
+    // is no {rhs}
+    fn map(&mut self, mode: Option<MapMode>, noremap: bool, args: &str, force: bool) {
+        let modes = Self::map_modes(mode, force);
+        let (lhs, rhs) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let lhs = mapping::normalize(lhs, &self.options.mapleader);
+        let rhs = rhs.trim_start();
 
-        while let Some(token) = tokenizer.next(&self.buffer) {
-            match token {
-                Token::Word { .. } => {
-                    tokens.push(token);
-                }
-                Token::Space { .. } => {
-                    tokens.push(token);
-                }
-                Token::Newline { idx } => {
-                    let new_line = tokens;
-                    lines.push(Line {
-                        idx: start_of_line,
-                        tokens: new_line,
-                    });
-                    tokens = Vec::new();
-                    start_of_line = idx + 1;
-                }
+        if rhs.is_empty() {
+            let list = self.mappings.list(modes, &lhs);
+            if list.is_empty() {
+                self.show("No mapping found".to_string());
+            } else {
+                self.messages.print(list.join("\n"));
             }
+            return;
         }
 
-        lines.push(Line {
-            idx: start_of_line,
-            tokens,
-        });
-        self.lines = lines;
+        let rhs = mapping::normalize(rhs, &self.options.mapleader);
+        for mode in modes {
+            self.mappings.add(Mapping {
+                mode: *mode,
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+                noremap,
+            });
+        }
     }
 
-    pub fn status_line(&self) -> &str {
-        match self.mode {
-            Mode::Normal => "NORMAL",
-            Mode::Insert => "INSERT",
-            Mode::Command => self.command_buffer.as_str(),
+    fn unmap(&mut self, mode: Option<MapMode>, args: &str, force: bool) {
+        let lhs = mapping::normalize(args.trim(), &self.options.mapleader);
+        let mut removed = false;
+        for mode in Self::map_modes(mode, force) {
+            removed |= self.mappings.remove(*mode, &lhs);
+        }
+        if !removed {
+            self.error("E31: No such mapping".to_string());
         }
     }
-}
 
-struct Tokenizer {
-    idx: usize,
-}
+    fn map_modes(mode: Option<MapMode>, force: bool) -> &'static [MapMode] {
+        match (mode, force) {
+            (Some(MapMode::Normal), _) | (None, false) => &[MapMode::Normal],
+            (Some(MapMode::Insert), _) => &[MapMode::Insert],
+            (Some(MapMode::Command), _) => &[MapMode::Command],
+            (None, true) => &[MapMode::Insert, MapMode::Command],
+        }
+    }
 
-impl Tokenizer {
-    fn new() -> Tokenizer {
-        Tokenizer { idx: 0 }
+    // runs the startup config, if there is one
+    pub fn source_config(&mut self) {
+        if let Some(path) = options::config_path().filter(|path| path.is_file()) {
+            self.source_file(&path.to_string_lossy());
+        }
     }
 
-    fn next(&mut self, val: &str) -> Option<Token> {
-        if self.idx >= val.len() {
-            None
+    // :colorscheme {name} sources colors/{name}.vim of the configuration
+    // directory or a built-in scheme of that name
+    fn color_scheme(&mut self, name: &str) {
+        if name.is_empty() {
+            return self.show(self.colors_name.clone());
+        }
+
+        let path = options::config_dir()
+            .map(|dir| dir.join("colors").join(format!("{name}.vim")))
+            .filter(|path| path.is_file());
+        if let Some(path) = path {
+            self.source_file(&path.to_string_lossy());
+        } else if let Some(commands) = highlight::builtin_scheme(name) {
+            self.source(&format!("colors/{name}.vim"), commands);
         } else {
-            let current = val.as_bytes()[self.idx];
-            if current == 32 {
-                self.take_space(val)
-            } else if current == 10 {
-                let idx = self.idx;
-                self.idx += 1;
-                Some(Token::Newline { idx })
-            } else {
-                self.take_word(val)
-            }
+            return self.error(format!("E185: Cannot find color scheme '{name}'"));
         }
+        self.colors_name = name.to_string();
     }
 
-    fn take_word(&mut self, val: &str) -> Option<Token> {
-        let start = self.idx;
-        while self.idx < val.len() {
-            let current = val.as_bytes()[self.idx];
-            if current == 32 || current == 10 {
-                break;
+    // :source {file}, running every line as an ex command; lines starting
+    // with a double quote are comments
+    fn source_file(&mut self, path: &str) {
+        match std::fs::read_to_string(path) {
+            Ok(commands) => self.source(path, &commands),
+            Err(err) => self.error(format!("E484: Can't open file {path}: {err}")),
+        }
+    }
+
+    pub fn source(&mut self, name: &str, commands: &str) {
+        if self.source_depth >= MAX_SOURCE_DEPTH {
+            return self.error("E169: Command too recursive".to_string());
+        }
+        self.source_depth += 1;
+        let mut errors = Vec::new();
+
+        for (idx, line) in commands.lines().enumerate() {
+            let line = line.trim_start().trim_start_matches(':');
+            if line.is_empty() || line.starts_with('"') {
+                continue;
+            }
+
+            self.messages.clear();
+            let result = self.command_buffer.execute_line(line);
+            self.execute(result);
+
+            if let Some(message) = self.messages.current() {
+                if message.kind == MessageKind::Error {
+                    errors.push(format!("line {:>3}: {}", idx + 1, message.text));
+                }
             }
-            self.idx += 1;
         }
-        Some(Token::Word {
-            idx: start,
-            len: self.idx - start,
+        self.source_depth -= 1;
+
+        self.messages.clear();
+        if !errors.is_empty() {
+            self.error(format!(
+                "Error detected while processing {name}:\n{}",
+                errors.join("\n")
+            ));
+        }
+    }
+
+    // the 'statusline' of the given window, split into its left and
+    // right aligned part
+    pub fn status_line(&self, id: usize) -> (String, String) {
+        let Some((buffer, (line, col))) = self.window(id) else {
+            return Default::default();
+        };
+        let active = id == self.current_window();
+
+        statusline::expand(&self.options.statusline, |item| {
+            let value = match item {
+                "f" => buffer.name().to_string(),
+                "m" if buffer.is_modified() => "[+]".to_string(),
+                "r" if buffer.is_readonly() => "[RO]".to_string(),
+                "m" | "r" => String::new(),
+                "y" => buffer
+                    .filetype()
+                    .map(|filetype| format!("[{filetype}]"))
+                    .unwrap_or_default(),
+                "l" => (line + 1).to_string(),
+                "c" => (col + 1).to_string(),
+                "L" => buffer.line_count().to_string(),
+                "p" => ((line + 1) * 100 / buffer.line_count().max(1)).to_string(),
+                "S" if active => self.input_buffer.pending(),
+                "S" => String::new(),
+                "mode" if active => match self.mode {
+                    Mode::Normal => "NORMAL",
+                    Mode::Insert => "INSERT",
+                    Mode::Command => "COMMAND",
+                }
+                .to_string(),
+                "mode" => String::new(),
+                "encoding" => "utf-8".to_string(),
+                "fileformat" => buffer.fileformat().to_string(),
+                _ => return None,
+            };
+            Some(value)
         })
     }
 
-    fn take_space(&mut self, val: &str) -> Option<Token> {
-        let start = self.idx;
-        let mut len = 0usize;
-        while self.idx < val.len() {
-            let current = val.as_bytes()[self.idx];
-            // TODO: handle other whitespace
-            if current != 32 {
-                break;
-            }
-            self.idx += 1;
-            len += 1;
+    // the bottom line shows messages, the mode or the command being typed
+    pub fn command_line(&self) -> &str {
+        match self.mode {
+            Mode::Normal => self.message().unwrap_or(""),
+            Mode::Insert => "-- INSERT --",
+            Mode::Command => self.command_buffer.as_str(),
         }
-        Some(Token::Space { idx: start, len })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::editor::Pos;
-
     use super::Editor;
+    use crate::args::Args;
+    use crate::buffer::Buffer;
+    use crate::excmd::ExCmdResult;
+    use crate::highlight::Group;
+    use crate::key::Key;
+    use crate::render::V4;
+
+    fn editor(buffers: &[&str]) -> Editor {
+        let mut e = Editor::from_reader(buffers[0].as_bytes()).unwrap();
+        for text in &buffers[1..] {
+            e.add_buffer(Buffer::from_reader(text.as_bytes()).unwrap());
+        }
+        e
+    }
 
-    #[test]
-    fn tokenize_one_word() {
-        let mut e = Editor::new();
-        e.insert("test");
+    fn ex(e: &mut Editor, cmd: &str) {
+        e.handle_normal(":");
+        e.handle_command(cmd);
+        e.command_execute();
+    }
 
-        assert_eq!(join(&e), vec!["test"])
+    fn keys(e: &mut Editor, keys: &str) {
+        for key in Key::parse(keys) {
+            e.handle_key(key);
+        }
     }
 
     #[test]
-    fn tokenize_multiple_words() {
-        let mut e = Editor::new();
-        e.insert("foo bar ham");
+    fn cycle_buffers() {
+        let mut e = editor(&["one", "two", "three"]);
+
+        ex(&mut e, "bn");
+        assert_eq!(e.buffer().text(), "two");
+        ex(&mut e, "bp");
+        ex(&mut e, "bp");
+        assert_eq!(e.buffer().text(), "three");
 
-        assert_eq!(join(&e), vec!["foo bar ham"])
+        ex(&mut e, "b 2");
+        assert_eq!(e.buffer().text(), "two");
+        e.handle_normal("<C-^>");
+        assert_eq!(e.buffer().text(), "three");
+
+        ex(&mut e, "b 7");
+        assert_eq!(e.message(), Some("E86: Buffer 7 does not exist"));
     }
 
     #[test]
-    fn tokenize_multiple_spaces() {
-        let mut e = Editor::new();
-        e.insert(" ham   eggs ");
+    fn delete_buffers() {
+        let mut e = editor(&["one", "two", "three"]);
+        ex(&mut e, "b3");
+        ex(&mut e, "b1");
+        e.buffer_mut().insert("x");
+
+        ex(&mut e, "bd");
+        assert_eq!(e.buffers().len(), 3);
+        assert!(e.message().unwrap().starts_with("E89"));
 
-        assert_eq!(join(&e), vec![" ham   eggs "])
+        ex(&mut e, "bd!");
+        assert_eq!(e.buffer().text(), "three");
+        assert_eq!(e.buffers().len(), 2);
+
+        ex(&mut e, "ls");
+        assert_eq!(
+            e.message(),
+            Some("  2    \"[No Name]\" line 1\n  3 %  \"[No Name]\" line 1")
+        );
+
+        ex(&mut e, "bd 2");
+        ex(&mut e, "bd");
+        assert_eq!(e.buffers().len(), 1);
+        assert_eq!(e.buffer().id(), 4);
     }
 
     #[test]
-    fn tokenize_multiple_lines() {
-        let mut e = Editor::new();
-        e.insert("foo\nbar");
+    fn quit_with_modified_buffers() {
+        let mut e = editor(&["one", "two"]);
+        e.buffer_mut().insert("x");
 
-        assert_eq!(join(&e), vec!["foo", "bar"])
+        ex(&mut e, "q");
+        assert_eq!(
+            e.message(),
+            Some("E37: No write since last change (add ! to override)")
+        );
+
+        ex(&mut e, "bn");
+        e.handle_normal(":");
+        e.handle_command("q");
+        assert!(e.command_execute() != ExCmdResult::Quit(true));
+        assert_eq!(
+            e.message(),
+            Some("E162: No write since last change for buffer \"[No Name]\"")
+        );
+
+        e.handle_normal(":");
+        e.handle_command("q!");
+        assert!(e.command_execute() == ExCmdResult::Quit(true));
     }
 
     #[test]
-    fn tokenize_empty_lines() {
+    fn inspect_tree() {
         let mut e = Editor::new();
-        e.insert("\n\n\n");
+        ex(&mut e, "InspectTree");
+        if !cfg!(feature = "tree-sitter") {
+            let message = "E319: Sorry, the command is not available in this version";
+            assert_eq!(e.message(), Some(message));
+            return;
+        }
+        assert_eq!(e.message(), Some("No parser for the current buffer"));
 
-        assert_eq!(join(&e), vec!["", "", "", ""])
+        ex(&mut e, "e Cargo.toml");
+        ex(&mut e, "Inspect");
+        assert_eq!(e.windows().len(), 2);
+        assert!(e.buffer().is_readonly());
+        assert!(e.buffer().text().starts_with("(document) ; [0, 0]"));
     }
 
     #[test]
-    fn tokenize_empty() {
+    fn edit_files() {
         let mut e = Editor::new();
-        e.insert("");
+        ex(&mut e, "e Cargo.toml");
+        assert_eq!(e.buffer().path(), Some("Cargo.toml"));
+        assert!(e.buffer().text().starts_with("[package]"));
 
-        assert_eq!(join(&e), vec![""])
+        ex(&mut e, "ene");
+        ex(&mut e, "e Cargo.toml");
+        assert_eq!(e.buffers().len(), 3);
+        assert_eq!(e.buffer().id(), 2);
+
+        ex(&mut e, "b Cargo");
+        assert_eq!(e.buffer().id(), 2);
+        ex(&mut e, "b foo");
+        assert_eq!(e.message(), Some("E94: No matching buffer for foo"));
     }
 
     #[test]
-    fn tokenize_delete_single_line() {
-        let mut e = Editor::new();
-        e.insert("fooo");
-        e.delete();
+    fn open_and_write_files() {
+        let dir = std::env::temp_dir().join(format!("edi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("existing.txt");
+        let new = dir.join("new.txt");
+        std::fs::write(&existing, "foo\nbar baz\n").unwrap();
+
+        let args = [
+            "+/baz".to_string(),
+            existing.display().to_string(),
+            new.display().to_string(),
+        ];
+        let mut e = Editor::from_args(&Args::parse(args).unwrap()).unwrap();
+        assert_eq!(e.buffers().len(), 2);
+        assert_eq!(e.buffer().cursor_pos(), (1, 4));
+
+        ex(&mut e, "bn");
+        assert!(!new.exists());
+        e.buffer_mut().insert("hello");
+        assert!(e.buffer().is_modified());
+
+        ex(&mut e, "w");
+        assert_eq!(std::fs::read_to_string(&new).unwrap(), "hello");
+        assert!(e.message().unwrap().ends_with("1L, 5B written"));
+        assert!(!e.buffer().is_modified());
+
+        ex(&mut e, &format!("r {}", existing.display()));
+        assert_eq!(e.buffer().text(), "hello\nfoo\nbar baz");
+        assert_eq!(e.buffer().cursor_pos(), (1, 0));
+        ex(&mut e, "r");
+        assert_eq!(e.buffer().text(), "hello\nfoo\nhello\nbar baz");
+        ex(&mut e, "r /nonexistent");
+        assert!(e.message().unwrap().starts_with("E484"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 
-        assert_eq!(join(&e), vec!["foo"])
+    #[test]
+    fn readonly_buffers() {
+        let args = Args::parse(["-R".to_string(), "Cargo.toml".to_string(), "+3".to_string()]);
+        let mut e = Editor::from_args(&args.unwrap()).unwrap();
+        assert_eq!(e.buffer().cursor_pos(), (2, 0));
+
+        ex(&mut e, "w");
+        assert_eq!(
+            e.message(),
+            Some("E45: 'readonly' option is set (add ! to override)")
+        );
     }
 
     #[test]
-    fn tokenize_delete_multiline() {
-        let mut e = Editor::new();
-        e.insert("f");
-        e.new_line();
-        e.delete();
-        e.insert("oobar");
+    fn windows_keep_cursors() {
+        let mut e = editor(&["foo\nbar\nbaz"]);
+        for key in ["<C-w>", "s", "j", "j"] {
+            e.handle_normal(key);
+        }
+        let top = e.current_window();
 
-        assert_eq!(join(&e), vec!["foobar"])
+        e.handle_normal("<C-w>");
+        e.handle_normal("j");
+        assert_ne!(e.current_window(), top);
+        assert_eq!(e.buffer().cursor_pos(), (0, 0));
+        assert_eq!(e.window(top).map(|(_, cursor)| cursor), Some((2, 0)));
+
+        e.handle_normal(":");
+        e.handle_command("q");
+        assert!(e.command_execute() != ExCmdResult::Quit(true));
+        assert_eq!(e.current_window(), top);
+        assert_eq!(e.buffer().cursor_pos(), (2, 0));
+
+        ex(&mut e, "clo");
+        assert_eq!(e.message(), Some("E444: Cannot close last window"));
     }
 
     #[test]
-    fn cursor_pos() {
-        let mut e = Editor::new();
-        e.insert("foo");
+    fn tab_pages() {
+        let mut e = editor(&["foo\nbar"]);
+        e.handle_normal("j");
+        let first = e.current_window();
+
+        ex(&mut e, "tabnew");
+        assert_eq!((e.tab_count(), e.current_tab()), (2, 1));
+        assert_eq!(e.buffer().name(), "[No Name]");
+
+        e.handle_normal("g");
+        e.handle_normal("t");
+        assert_eq!(e.current_tab(), 0);
+        assert_eq!(e.current_window(), first);
+        assert_eq!(e.buffer().cursor_pos(), (1, 0));
+
+        ex(&mut e, "tabn 2");
+        assert_eq!(e.current_tab(), 1);
+        e.handle_normal("g");
+        e.handle_normal("T");
+        assert_eq!(e.current_tab(), 0);
+
+        // closing the last window of a tab page closes the tab
+        ex(&mut e, "q");
+        assert_eq!(e.tab_count(), 1);
+        ex(&mut e, "tabc");
+        assert_eq!(e.message(), Some("E784: Cannot close last tab page"));
+    }
 
-        assert_eq!(join(&e), vec!["foo"]);
-        assert_eq!(
-            e.cursor,
-            Pos {
-                idx: 3,
-                col: 3,
-                line: 0
-            }
-        );
+    #[test]
+    fn status_line_items() {
+        let mut e = editor(&["foo\nbar\nbaz\nqux"]);
+        e.buffer_mut().set_path("src/main.rs");
+        ex(&mut e, "set stl=%{mode}\\ %f%m%r%=%S\\ %y\\ %l:%c\\ %p%%");
+        for key in ["j", "l", "2", "d"] {
+            e.handle_normal(key);
+        }
 
-        e.new_line();
+        let id = e.current_window();
         assert_eq!(
-            e.cursor,
-            Pos {
-                idx: 4,
-                col: 0,
-                line: 1
-            }
+            e.status_line(id),
+            (
+                "NORMAL src/main.rs".to_string(),
+                "2d [rust] 2:2 50%".to_string()
+            )
         );
+
+        e.handle_normal("d");
+        e.buffer_mut().set_readonly(true);
+        assert_eq!(e.status_line(id).0, "NORMAL src/main.rs[+][RO]");
     }
 
     #[test]
-    fn cursor_newline() {
-        let mut e = Editor::new();
-        e.insert("foo");
-        e.new_line();
-        e.insert("bar");
-
-        assert_eq!(join(&e), vec!["foo", "bar"]);
-        assert_eq!(
-            e.cursor,
-            Pos {
-                idx: 7,
-                col: 3,
-                line: 1
-            }
+    fn source_commands() {
+        let mut e = editor(&["foo"]);
+        e.source(
+            "edirc",
+            "\" comment\n\nset nu\n:set tm=200\nfoo\nset nofoo\n",
         );
 
-        e.move_start_of_line();
+        assert!(e.options().number);
+        assert_eq!(e.options().timeoutlen, 200);
         assert_eq!(
-            e.cursor,
-            Pos {
-                idx: 4,
-                col: 0,
-                line: 1
-            }
+            e.message(),
+            Some(
+                "Error detected while processing edirc:\n\
+                 line   5: E492: Not an editor command: foo\n\
+                 line   6: E518: Unknown option: foo"
+            )
         );
 
-        e.move_end_of_line();
+        ex(&mut e, "so /nonexistent/edirc");
+        assert!(e.message().unwrap().starts_with("E484"));
+
+        // sourced lines are not added to the command line history
+        let mut e = editor(&["foo"]);
+        e.source("edirc", "set nu\nset tm=300\n");
+        keys(&mut e, ":<Up>");
+        assert_eq!(e.command_line(), ":");
+    }
+
+    #[test]
+    fn key_mappings() {
+        let mut e = editor(&["foo bar"]);
+        ex(&mut e, "inoremap jk <Esc>");
+        ex(&mut e, "set mapleader=,");
+        ex(&mut e, "nnoremap <Leader>d dw");
+        ex(&mut e, "nmap X ,d");
+
+        keys(&mut e, "ijxjk");
+        assert_eq!(e.buffer().text(), "jxfoo bar");
+        assert_eq!(e.command_line(), "");
+
+        keys(&mut e, "0X");
+        assert_eq!(e.buffer().text(), "bar");
+
+        ex(&mut e, "map");
         assert_eq!(
-            e.cursor,
-            Pos {
-                idx: 7,
-                col: 3,
-                line: 1
-            }
+            e.message(),
+            Some("n  ,d           * dw\nn  X              ,d")
         );
+
+        ex(&mut e, "nmap a b");
+        ex(&mut e, "nmap b a");
+        keys(&mut e, "a");
+        assert_eq!(e.message(), Some("E223: Recursive mapping"));
+
+        ex(&mut e, "unmap X");
+        ex(&mut e, "unmap X");
+        assert_eq!(e.message(), Some("E31: No such mapping"));
     }
 
-    fn join(e: &Editor) -> Vec<String> {
-        e.iter()
-            .map(|line| line.map(|s| s.to_string()).collect::<Vec<_>>().join(""))
-            .collect()
+    #[test]
+    fn ambiguous_mapping_timeout() {
+        let mut e = editor(&["foo"]);
+        ex(&mut e, "inoremap j <Right>");
+        ex(&mut e, "inoremap jk <Esc>");
+
+        keys(&mut e, "ij");
+        assert_eq!(e.buffer().text(), "foo");
+        e.update(e.options().timeoutlen as f32);
+        keys(&mut e, "x");
+        assert_eq!(e.buffer().text(), "fxoo");
+    }
+
+    #[test]
+    fn color_schemes() {
+        let mut e = editor(&["foo"]);
+        let normal = e.highlights().fg(Group::Normal);
+
+        ex(&mut e, "colo desert");
+        assert_eq!(e.highlights().fg(Group::Normal), V4::rgb(1.0, 1.0, 1.0));
+        ex(&mut e, "colorscheme");
+        assert_eq!(e.message(), Some("desert"));
+
+        ex(&mut e, "colo nope");
+        assert_eq!(e.message(), Some("E185: Cannot find color scheme 'nope'"));
+        ex(&mut e, "hi Normal guifg=#zzzzzz");
+        assert_eq!(e.message(), Some("E254: Cannot allocate color #zzzzzz"));
+
+        ex(&mut e, "colo default");
+        assert_eq!(e.highlights().fg(Group::Normal), normal);
     }
 }
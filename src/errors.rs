@@ -14,8 +14,13 @@ pub enum EdiError {
     ProgramLinkingFailed(String),
     #[error("lookup of uniform '{0}' failed")]
     UniformLookupFailed(String),
+    #[cfg(feature = "gui")]
     #[error("font error: {0}")]
     FontError(#[from] crossfont::Error),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("no file name")]
+    NoFileName,
     #[error("terminated without success")]
     Cancelled,
 }
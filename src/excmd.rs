@@ -1,12 +1,17 @@
+use crate::completion::CompletionKind;
+use crate::mapping::MapMode;
+
 #[derive(PartialEq)]
 pub enum ExCmdResult {
-    Command(ExCmdType),
+    Command(ExCmdType, String),
     UnknownCommand(String),
+    Search(String),
     Quit(bool),
 }
 
 struct ExCmd {
-    input: &'static str,
+    name: &'static str,
+    abbrev: usize,
     typ: ExCmdType,
 }
 
@@ -14,61 +19,660 @@ struct ExCmd {
 pub enum ExCmdType {
     Quit,
     CancelQuit,
+    Substitute,
+    Edit,
+    New,
+    BufferNext,
+    BufferPrev,
+    Buffer,
+    Buffers,
+    BufferDelete,
+    Write,
+    Read,
+    Split,
+    VSplit,
+    Close,
+    Only,
+    TabNew,
+    TabNext,
+    TabPrev,
+    TabClose,
+    Set,
+    Messages,
+    Source,
+    // :map applies to normal mode, :map! to insert and command mode
+    Map(Option<MapMode>, bool),
+    Unmap(Option<MapMode>),
+    Highlight,
+    ColorScheme,
+    InspectTree,
+    NoHlSearch,
 }
 
-const ALL_COMMANDS: [ExCmd; 2] = [
+const ALL_COMMANDS: [ExCmd; 40] = [
     ExCmd {
-        input: ":q",
+        name: "quit",
+        abbrev: 1,
         typ: ExCmdType::Quit,
     },
     ExCmd {
-        input: ":cq",
+        name: "cquit",
+        abbrev: 2,
         typ: ExCmdType::CancelQuit,
     },
+    ExCmd {
+        name: "substitute",
+        abbrev: 1,
+        typ: ExCmdType::Substitute,
+    },
+    ExCmd {
+        name: "edit",
+        abbrev: 1,
+        typ: ExCmdType::Edit,
+    },
+    ExCmd {
+        name: "enew",
+        abbrev: 3,
+        typ: ExCmdType::New,
+    },
+    ExCmd {
+        name: "bnext",
+        abbrev: 2,
+        typ: ExCmdType::BufferNext,
+    },
+    ExCmd {
+        name: "bprevious",
+        abbrev: 2,
+        typ: ExCmdType::BufferPrev,
+    },
+    ExCmd {
+        name: "buffer",
+        abbrev: 1,
+        typ: ExCmdType::Buffer,
+    },
+    ExCmd {
+        name: "buffers",
+        abbrev: 7,
+        typ: ExCmdType::Buffers,
+    },
+    ExCmd {
+        name: "ls",
+        abbrev: 2,
+        typ: ExCmdType::Buffers,
+    },
+    ExCmd {
+        name: "bdelete",
+        abbrev: 2,
+        typ: ExCmdType::BufferDelete,
+    },
+    ExCmd {
+        name: "write",
+        abbrev: 1,
+        typ: ExCmdType::Write,
+    },
+    ExCmd {
+        name: "read",
+        abbrev: 1,
+        typ: ExCmdType::Read,
+    },
+    ExCmd {
+        name: "split",
+        abbrev: 2,
+        typ: ExCmdType::Split,
+    },
+    ExCmd {
+        name: "vsplit",
+        abbrev: 2,
+        typ: ExCmdType::VSplit,
+    },
+    ExCmd {
+        name: "close",
+        abbrev: 3,
+        typ: ExCmdType::Close,
+    },
+    ExCmd {
+        name: "only",
+        abbrev: 2,
+        typ: ExCmdType::Only,
+    },
+    ExCmd {
+        name: "tabnew",
+        abbrev: 6,
+        typ: ExCmdType::TabNew,
+    },
+    ExCmd {
+        name: "tabnext",
+        abbrev: 4,
+        typ: ExCmdType::TabNext,
+    },
+    ExCmd {
+        name: "tabprevious",
+        abbrev: 4,
+        typ: ExCmdType::TabPrev,
+    },
+    ExCmd {
+        name: "tabclose",
+        abbrev: 4,
+        typ: ExCmdType::TabClose,
+    },
+    ExCmd {
+        name: "set",
+        abbrev: 2,
+        typ: ExCmdType::Set,
+    },
+    ExCmd {
+        name: "messages",
+        abbrev: 3,
+        typ: ExCmdType::Messages,
+    },
+    ExCmd {
+        name: "source",
+        abbrev: 2,
+        typ: ExCmdType::Source,
+    },
+    ExCmd {
+        name: "map",
+        abbrev: 3,
+        typ: ExCmdType::Map(None, false),
+    },
+    ExCmd {
+        name: "nmap",
+        abbrev: 2,
+        typ: ExCmdType::Map(Some(MapMode::Normal), false),
+    },
+    ExCmd {
+        name: "imap",
+        abbrev: 2,
+        typ: ExCmdType::Map(Some(MapMode::Insert), false),
+    },
+    ExCmd {
+        name: "cmap",
+        abbrev: 2,
+        typ: ExCmdType::Map(Some(MapMode::Command), false),
+    },
+    ExCmd {
+        name: "noremap",
+        abbrev: 2,
+        typ: ExCmdType::Map(None, true),
+    },
+    ExCmd {
+        name: "nnoremap",
+        abbrev: 2,
+        typ: ExCmdType::Map(Some(MapMode::Normal), true),
+    },
+    ExCmd {
+        name: "inoremap",
+        abbrev: 3,
+        typ: ExCmdType::Map(Some(MapMode::Insert), true),
+    },
+    ExCmd {
+        name: "cnoremap",
+        abbrev: 3,
+        typ: ExCmdType::Map(Some(MapMode::Command), true),
+    },
+    ExCmd {
+        name: "unmap",
+        abbrev: 3,
+        typ: ExCmdType::Unmap(None),
+    },
+    ExCmd {
+        name: "nunmap",
+        abbrev: 3,
+        typ: ExCmdType::Unmap(Some(MapMode::Normal)),
+    },
+    ExCmd {
+        name: "iunmap",
+        abbrev: 2,
+        typ: ExCmdType::Unmap(Some(MapMode::Insert)),
+    },
+    ExCmd {
+        name: "cunmap",
+        abbrev: 2,
+        typ: ExCmdType::Unmap(Some(MapMode::Command)),
+    },
+    ExCmd {
+        name: "highlight",
+        abbrev: 2,
+        typ: ExCmdType::Highlight,
+    },
+    ExCmd {
+        name: "colorscheme",
+        abbrev: 4,
+        typ: ExCmdType::ColorScheme,
+    },
+    ExCmd {
+        name: "InspectTree",
+        abbrev: 7,
+        typ: ExCmdType::InspectTree,
+    },
+    ExCmd {
+        name: "nohlsearch",
+        abbrev: 3,
+        typ: ExCmdType::NoHlSearch,
+    },
 ];
 
+impl ExCmd {
+    fn from_input(name: &str) -> Option<&'static ExCmd> {
+        ALL_COMMANDS
+            .iter()
+            .find(|cmd| name.len() >= cmd.abbrev && cmd.name.starts_with(name))
+    }
+}
+
+pub fn command_names(prefix: &str) -> Vec<&'static str> {
+    ALL_COMMANDS
+        .iter()
+        .map(|cmd| cmd.name)
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+// number of entries kept per command line history
+const MAX_HISTORY: usize = 100;
+
+// history of one prompt, browsing only considers the entries that start
+// with the text typed before browsing started
+struct CmdHistory {
+    entries: Vec<String>,
+    idx: Option<usize>,
+    prefix: String,
+}
+
+impl CmdHistory {
+    fn new() -> CmdHistory {
+        CmdHistory {
+            entries: Vec::new(),
+            idx: None,
+            prefix: String::new(),
+        }
+    }
+
+    fn add(&mut self, entry: &str) {
+        if entry.is_empty() {
+            return;
+        }
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+    }
+
+    fn last(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+
+    fn prev(&mut self, current: &str) -> Option<String> {
+        let end = match self.idx {
+            Some(idx) => idx,
+            None => {
+                self.prefix = current.to_string();
+                self.entries.len()
+            }
+        };
+        let idx = self.entries[..end]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.prefix))?;
+        self.idx = Some(idx);
+        Some(self.entries[idx].clone())
+    }
+
+    // moving past the newest entry restores the typed text
+    fn next(&mut self) -> Option<String> {
+        let start = self.idx? + 1;
+        match self.entries[start..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.prefix))
+        {
+            Some(offset) => {
+                self.idx = Some(start + offset);
+                Some(self.entries[start + offset].clone())
+            }
+            None => {
+                self.idx = None;
+                Some(self.prefix.clone())
+            }
+        }
+    }
+}
+
+// the command line, starting with its prompt character ':' or '/'
 pub struct CmdBuffer {
     buffer: String,
+    // byte offset of the cursor in `buffer`
+    cursor: usize,
+    // waiting for the register name after Ctrl-r
+    register: bool,
+    commands: CmdHistory,
+    searches: CmdHistory,
 }
 
 impl CmdBuffer {
     pub fn new() -> CmdBuffer {
         CmdBuffer {
             buffer: String::with_capacity(128),
+            cursor: 0,
+            register: false,
+            commands: CmdHistory::new(),
+            searches: CmdHistory::new(),
+        }
+    }
+
+    pub fn start(&mut self, prompt: char) {
+        self.reset();
+        self.input(prompt.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn is_search(&self) -> bool {
+        self.buffer.starts_with('/')
+    }
+
+    fn history(&mut self) -> &mut CmdHistory {
+        if self.is_search() {
+            &mut self.searches
+        } else {
+            &mut self.commands
+        }
+    }
+
+    // the last entered command line or search pattern
+    pub fn last(&self, prompt: char) -> Option<&str> {
+        match prompt {
+            '/' => self.searches.last(),
+            _ => self.commands.last(),
         }
     }
 
+    fn content(&self) -> &str {
+        self.buffer.get(1..).unwrap_or_default()
+    }
+
+    fn set_content(&mut self, content: &str) {
+        self.buffer.truncate(1);
+        self.buffer.push_str(content);
+        self.cursor = self.buffer.len();
+    }
+
     pub fn input(&mut self, input: &str) {
-        self.buffer.push_str(input);
+        self.buffer.insert_str(self.cursor, input);
+        self.cursor += input.len();
     }
 
     pub fn execute(&mut self) -> ExCmdResult {
-        let cmd = ALL_COMMANDS
-            .iter()
-            .find(|cmd| cmd.input == self.buffer)
-            .map(|cmd| cmd.typ.clone());
+        let content = self.content().to_string();
+
+        if self.is_search() {
+            let pattern = match content.as_str() {
+                "" => self.searches.last().unwrap_or_default().to_string(),
+                _ => content,
+            };
+            self.searches.add(&pattern);
+            self.reset();
+            return ExCmdResult::Search(pattern);
+        }
+
+        self.commands.add(&content);
+        self.reset();
+        self.execute_line(&content)
+    }
+
+    // ex command of a line that is not typed into the command line, like
+    // the lines of a sourced file, which are kept out of the history
+    pub fn execute_line(&self, line: &str) -> ExCmdResult {
+        let name_len = line
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(line.len());
+        let (name, args) = line.split_at(name_len);
 
-        let result = match cmd {
-            Some(ExCmdType::Quit) => ExCmdResult::Quit(true),
+        match ExCmd::from_input(name).map(|cmd| cmd.typ.clone()) {
             Some(ExCmdType::CancelQuit) => ExCmdResult::Quit(false),
-            None => ExCmdResult::UnknownCommand(self.buffer.clone()),
+            Some(typ) => ExCmdResult::Command(typ, args.trim_start().to_string()),
+            None => ExCmdResult::UnknownCommand(format!(":{line}")),
+        }
+    }
+
+    // what to complete for the word before the cursor and where it starts
+    pub fn completion_target(&self) -> Option<(CompletionKind, usize)> {
+        if self.is_search() || self.buffer.is_empty() {
+            return None;
+        }
+
+        let typed = &self.buffer[1..self.cursor];
+        let name_len = typed
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(typed.len());
+        if name_len == typed.len() {
+            return Some((CompletionKind::Command, 1));
+        }
+
+        let (name, args) = typed.split_at(name_len);
+        let word = args
+            .rfind(|c: char| c.is_whitespace() || c == '!')
+            .map_or(0, |idx| idx + 1);
+        let kind = match ExCmd::from_input(name)?.typ {
+            ExCmdType::Set => CompletionKind::Option,
+            ExCmdType::Buffer | ExCmdType::BufferDelete => CompletionKind::Buffer,
+            ExCmdType::Highlight => CompletionKind::Highlight,
+            ExCmdType::ColorScheme => CompletionKind::ColorScheme,
+            ExCmdType::Edit
+            | ExCmdType::Write
+            | ExCmdType::Read
+            | ExCmdType::Split
+            | ExCmdType::VSplit
+            | ExCmdType::TabNew
+            | ExCmdType::Source => CompletionKind::Path,
+            _ => return None,
         };
+        Some((kind, 1 + name_len + word))
+    }
 
-        self.reset();
-        result
+    // replaces the text between `start` and the cursor
+    pub fn replace(&mut self, start: usize, text: &str) {
+        self.buffer.replace_range(start..self.cursor, text);
+        self.cursor = start + text.len();
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.buffer[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(idx, _)| idx)
     }
 
+    // deletes the character before the cursor, the prompt is only
+    // removed once the command line is empty
     pub fn delete_char(&mut self) {
-        if !self.buffer.is_empty() {
-            self.buffer.remove(self.buffer.len() - 1);
+        if self.cursor > 1 {
+            let start = self.prev_boundary();
+            self.buffer.replace_range(start..self.cursor, "");
+            self.cursor = start;
+        } else if self.buffer.len() <= 1 {
+            self.reset();
         }
     }
 
+    pub fn delete_char_under(&mut self) {
+        if let Some(c) = self.buffer[self.cursor..].chars().next() {
+            self.buffer
+                .replace_range(self.cursor..self.cursor + c.len_utf8(), "");
+        }
+    }
+
+    // Ctrl-w: deletes the word before the cursor
+    pub fn delete_word(&mut self) {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let before = &self.buffer[1.min(self.cursor)..self.cursor];
+        let trimmed = before.trim_end();
+        let word = match trimmed.chars().next_back() {
+            Some(c) if is_word(c) => trimmed.trim_end_matches(is_word),
+            Some(_) => trimmed.trim_end_matches(|c: char| !is_word(c) && !c.is_whitespace()),
+            None => trimmed,
+        };
+        let start = 1.min(self.cursor) + word.len();
+        self.buffer.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    // Ctrl-u: deletes everything before the cursor
+    pub fn delete_to_start(&mut self) {
+        let start = 1.min(self.cursor);
+        self.buffer.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.cursor > 1 {
+            self.cursor = self.prev_boundary();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.buffer[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn move_start(&mut self) {
+        self.cursor = 1.min(self.buffer.len());
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.buffer.len();
+    }
+
+    pub fn history_prev(&mut self) {
+        let content = self.content().to_string();
+        if let Some(entry) = self.history().prev(&content) {
+            self.set_content(&entry);
+        }
+    }
+
+    pub fn history_next(&mut self) {
+        if let Some(entry) = self.history().next() {
+            self.set_content(&entry);
+        }
+    }
+
+    pub fn await_register(&mut self) {
+        self.register = true;
+    }
+
+    // whether the next key names a register to insert
+    pub fn take_register(&mut self) -> bool {
+        std::mem::take(&mut self.register)
+    }
+
     pub fn reset(&mut self) {
         self.buffer.clear();
+        self.cursor = 0;
+        self.register = false;
+        self.commands.idx = None;
+        self.searches.idx = None;
     }
 
     pub fn as_str(&self) -> &str {
         &self.buffer
     }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CmdBuffer, CompletionKind, ExCmdResult};
+
+    fn cmd(input: &str) -> CmdBuffer {
+        let mut cmd = CmdBuffer::new();
+        cmd.start(':');
+        cmd.input(input);
+        cmd
+    }
+
+    #[test]
+    fn cursor_editing() {
+        let mut c = cmd("e foo.rs");
+        c.move_left();
+        c.move_left();
+        c.delete_word();
+        assert_eq!((c.as_str(), c.cursor()), (":e foors", 6));
+        c.delete_word();
+        assert_eq!((c.as_str(), c.cursor()), (":e rs", 3));
+
+        c.move_start();
+        c.input("tab");
+        c.move_end();
+        c.input("x");
+        assert_eq!(c.as_str(), ":tabe rsx");
+
+        c.move_left();
+        c.delete_to_start();
+        assert_eq!((c.as_str(), c.cursor()), (":x", 1));
+        c.delete_char();
+        c.delete_char_under();
+        assert_eq!(c.as_str(), ":");
+        c.delete_char();
+        assert_eq!(c.as_str(), "");
+    }
+
+    #[test]
+    fn completion_targets() {
+        assert_eq!(
+            cmd("tab").completion_target(),
+            Some((CompletionKind::Command, 1))
+        );
+        assert_eq!(
+            cmd("set nu rn").completion_target(),
+            Some((CompletionKind::Option, 8))
+        );
+        assert_eq!(
+            cmd("b ").completion_target(),
+            Some((CompletionKind::Buffer, 3))
+        );
+        assert_eq!(
+            cmd("w! src/").completion_target(),
+            Some((CompletionKind::Path, 4))
+        );
+        assert_eq!(
+            cmd("r ").completion_target(),
+            Some((CompletionKind::Path, 3))
+        );
+        assert_eq!(
+            cmd("colo de").completion_target(),
+            Some((CompletionKind::ColorScheme, 6))
+        );
+        assert_eq!(cmd("q ").completion_target(), None);
+    }
+
+    #[test]
+    fn prefix_history() {
+        let mut c = CmdBuffer::new();
+        for input in ["e foo", "set nu", "e bar"] {
+            c.start(':');
+            c.input(input);
+            c.execute();
+        }
+        c.start('/');
+        c.input("pattern");
+        assert!(c.execute() == ExCmdResult::Search("pattern".to_string()));
+
+        c.start(':');
+        c.input("e");
+        c.history_prev();
+        assert_eq!(c.as_str(), ":e bar");
+        c.history_prev();
+        c.history_prev();
+        assert_eq!(c.as_str(), ":e foo");
+        c.history_next();
+        c.history_next();
+        assert_eq!(c.as_str(), ":e");
+
+        // searches have their own history
+        c.start('/');
+        c.history_prev();
+        assert_eq!(c.as_str(), "/pattern");
+        assert_eq!(c.last(':'), Some("e bar"));
+    }
 }
@@ -1,10 +1,7 @@
+#[cfg(feature = "gui")]
 use crossfont::{FontDesc, GlyphKey, Rasterize, Size, Slant, Weight};
-use gl33::{
-    global_loader::*, GL_CLAMP_TO_EDGE, GL_LINEAR, GL_RGB, GL_RGBA, GL_TEXTURE0, GL_TEXTURE_2D,
-    GL_TEXTURE_MAG_FILTER, GL_TEXTURE_MIN_FILTER, GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T,
-    GL_UNPACK_ALIGNMENT, GL_UNSIGNED_BYTE,
-};
 
+#[cfg(feature = "gui")]
 use crate::errors::EdiError;
 
 #[derive(Clone, Copy)]
@@ -33,15 +30,16 @@ impl GlyphInfo {
 }
 
 pub struct FontAtlas {
-    pub texture: u32,
     pub atlas_height: i32,
     pub atlas_width: i32,
+    pub pixels: Vec<u8>, // RGBA bitmap of all glyphs side by side
     pub glyphs: [GlyphInfo; 128],
 }
 
 pub const FONT_SIZE: f32 = 256.0;
 
 impl FontAtlas {
+    #[cfg(feature = "gui")]
     pub fn new(font_name: &str) -> Result<FontAtlas, EdiError> {
         let device_pixel_ratio = 1.0;
         let mut rasterizer: crossfont::Rasterizer = crossfont::Rasterize::new(device_pixel_ratio)?;
@@ -52,9 +50,10 @@ impl FontAtlas {
                 weight: Weight::Normal,
             },
         );
-        let size = Size::new(256.0);
+        let size = Size::new(FONT_SIZE);
         let font_key = rasterizer.load_font(&desc, size)?;
 
+        let mut rasterized = Vec::with_capacity(96);
         let mut atlas_width = 0i32;
         let mut atlas_height = 0i32;
 
@@ -68,81 +67,73 @@ impl FontAtlas {
 
             atlas_width += glyph.width;
             atlas_height = atlas_height.max(glyph.height);
+            rasterized.push(glyph);
         }
 
         let mut glyphs = [GlyphInfo::new(); 128];
-        let mut glyph_texture = 0u32;
-
-        unsafe {
-            glActiveTexture(GL_TEXTURE0);
-            glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
-            glGenTextures(1, &mut glyph_texture);
-            glBindTexture(GL_TEXTURE_2D, glyph_texture);
-
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR.0 as i32);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR.0 as i32);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE.0 as i32);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE.0 as i32);
-
-            glTexImage2D(
-                GL_TEXTURE_2D,
-                0,
-                GL_RGBA.0 as i32,
-                atlas_width,
-                atlas_height,
-                0,
-                GL_RGBA,
-                GL_UNSIGNED_BYTE,
-                0 as *const _,
-            );
-
-            let mut x = 0;
-
-            for i in 32..128usize {
-                let character = char::from_u32_unchecked(i as u32);
-                let glyph = rasterizer.get_glyph(GlyphKey {
-                    font_key,
-                    character,
-                    size,
-                })?;
-
-                glyphs[i].ax = glyph.advance.0 as f32;
-                glyphs[i].ay = glyph.advance.1 as f32;
-                glyphs[i].bw = glyph.width;
-                glyphs[i].bh = glyph.height;
-                glyphs[i].bl = glyph.left;
-                glyphs[i].bt = glyph.top;
-                glyphs[i].tx = (x as f32) / (atlas_width as f32);
-
-                let (format, buffer) = match &glyph.buffer {
-                    crossfont::BitmapBuffer::Rgb(buffer) => (GL_RGB, buffer),
-                    crossfont::BitmapBuffer::Rgba(buffer) => (GL_RGBA, buffer),
-                };
-
-                glTexSubImage2D(
-                    GL_TEXTURE_2D,
-                    0,
-                    x,
-                    0,
-                    glyph.width,
-                    glyph.height,
-                    format,
-                    GL_UNSIGNED_BYTE,
-                    buffer.as_ptr() as *const _,
-                );
-
-                x += glyphs[i].bw;
+        let mut pixels = vec![0u8; (atlas_width * atlas_height * 4) as usize];
+        let mut x = 0;
+
+        for (info, glyph) in glyphs.iter_mut().skip(32).zip(rasterized) {
+            info.ax = glyph.advance.0 as f32;
+            info.ay = glyph.advance.1 as f32;
+            info.bw = glyph.width;
+            info.bh = glyph.height;
+            info.bl = glyph.left;
+            info.bt = glyph.top;
+            info.tx = (x as f32) / (atlas_width as f32);
+
+            let (channels, buffer) = match &glyph.buffer {
+                crossfont::BitmapBuffer::Rgb(buffer) => (3, buffer),
+                crossfont::BitmapBuffer::Rgba(buffer) => (4, buffer),
+            };
+
+            for row in 0..glyph.height {
+                for col in 0..glyph.width {
+                    let src = ((row * glyph.width + col) * channels) as usize;
+                    let dst = ((row * atlas_width + x + col) * 4) as usize;
+
+                    pixels[dst..dst + 3].copy_from_slice(&buffer[src..src + 3]);
+                    pixels[dst + 3] = if channels == 4 { buffer[src + 3] } else { 255 };
+                }
             }
+
+            x += info.bw;
         }
 
         Ok(FontAtlas {
-            texture: glyph_texture,
             atlas_height,
             atlas_width,
+            pixels,
             glyphs,
         })
     }
 
+    // atlas of empty glyphs with fixed metrics, e.g. for headless rendering
+    pub fn monospace(advance: f32, height: i32) -> FontAtlas {
+        let width = advance as i32;
+        let mut glyphs = [GlyphInfo::new(); 128];
+
+        for (i, info) in glyphs.iter_mut().enumerate().skip(32) {
+            info.ax = advance;
+            info.bw = width;
+            info.bh = height;
+            info.bt = height;
+            info.tx = (i - 32) as f32 / 96.0;
+        }
+
+        FontAtlas {
+            atlas_height: height,
+            atlas_width: width * 96,
+            pixels: vec![0u8; (width * 96 * height * 4) as usize],
+            glyphs,
+        }
+    }
+
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.chars().map(|c| self.glyph(c).ax).sum()
+    }
+
     pub fn glyph(&self, chr: char) -> &GlyphInfo {
         let idx = if chr as usize >= 128 {
             '?' as usize
@@ -0,0 +1,1360 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::buffer::{Buffer, Wrap};
+use crate::camera::{Camera, MAX_SCALE};
+use crate::completion::Completion;
+use crate::cursor::{Cursor, CURSOR_OFFSET};
+use crate::editor::{Editor, Mode};
+use crate::font::{FontAtlas, FONT_SIZE};
+use crate::highlight::{Group, Highlights};
+use crate::message::{MessageKind, PRESS_ENTER};
+use crate::options::Options;
+use crate::render::{Backend, LineColors, Projection, Rect, Renderer, Shader, V2, V4};
+use crate::syntax::Span;
+
+// lines rendered above and below the visible area
+const LINE_MARGIN: usize = 2;
+
+const SEPARATOR_WIDTH: f32 = 2.0;
+
+const TAB_PADDING: f32 = 10.0;
+
+const COMMAND_CURSOR_WIDTH: f32 = 2.0;
+
+const WILDMENU_PADDING: f32 = 5.0;
+
+// scale of the status line, tab line and command line text
+const UI_SCALE: f32 = 0.1;
+
+pub const STATUS_BAR_HEIGHT: f32 = 25.0;
+
+pub struct View {
+    pub camera: Camera,
+    pub cursor: Cursor,
+    rows: RowIndex,
+}
+
+// first screen row of every line of the buffer shown in a window with
+// 'wrap', computed again once the buffer, its text or the layout changes
+struct RowIndex {
+    key: Option<(usize, usize, Wrap)>,
+    first_rows: Vec<usize>,
+}
+
+impl RowIndex {
+    fn update(&mut self, buffer: &Buffer, wrap: Option<Wrap>) {
+        let key = wrap.map(|wrap| (buffer.id(), buffer.generation(), wrap));
+        if key == self.key {
+            return;
+        }
+        self.key = key;
+        self.first_rows.clear();
+        if let Some(wrap) = &wrap {
+            let mut row = 0;
+            for line in 0..buffer.line_count() {
+                self.first_rows.push(row);
+                row += buffer.rows(line, wrap).len();
+            }
+        }
+    }
+}
+
+// camera and cursor of every editor window
+pub struct Views {
+    views: BTreeMap<usize, View>,
+}
+
+impl Views {
+    pub fn new() -> Views {
+        Views {
+            views: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, id: usize) -> &mut View {
+        self.views.entry(id).or_insert_with(|| View {
+            camera: Camera::new(),
+            cursor: Cursor::new(),
+            rows: RowIndex {
+                key: None,
+                first_rows: Vec::new(),
+            },
+        })
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        for view in self.views.values_mut() {
+            view.cursor.update(delta);
+            view.camera.update(delta);
+        }
+    }
+}
+
+pub fn render_frame<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    editor: &Editor,
+    views: &mut Views,
+    resolution: V2,
+) {
+    let highlights = editor.highlights();
+    renderer.clear(resolution, highlights.bg(Group::Normal));
+
+    let area = windows_area(editor, resolution);
+    let windows = editor.window_rects(area);
+
+    // windows of other tab pages keep their camera and cursor
+    let ids = editor.window_ids();
+    views.views.retain(|id, _| ids.contains(id));
+
+    for (id, rect) in &windows {
+        let Some((buffer, cursor_pos)) = editor.window(*id) else {
+            continue;
+        };
+        let options = editor.options();
+        let text_area = text_area(rect, &area);
+        let gutter = options.gutter_width(buffer.line_count());
+        let wrap = options.wrap(columns(editor, atlas, &text_area, gutter));
+        let view = views.get(*id);
+        view.camera.speed = options.cameraspeed as f32;
+        view.camera.zoom = zoom(options);
+        view.camera.margin = V2 {
+            x: (options.sidescrolloff as f32 + 0.5) * atlas.glyph('?').ax,
+            y: (options.scrolloff as f32 + 0.5) * FONT_SIZE,
+        };
+        view.camera.scroll_x = wrap.is_none();
+        view.camera.animate = options.animate;
+        view.cursor.set_animate(options.animate);
+        view.cursor
+            .set_blink(options.blinkon as f32, options.blinkoff as f32);
+        view.cursor.set_color(highlights.bg(Group::Cursor));
+        view.rows.update(buffer, wrap);
+
+        let View {
+            camera,
+            cursor,
+            rows,
+        } = view;
+        let layout = Layout {
+            buffer,
+            wrap,
+            gutter,
+            advance: atlas.glyph('?').ax,
+            first_rows: &rows.first_rows,
+            lines: 0..0,
+            rows: Vec::new(),
+        }
+        .visible(visible_rows(camera, text_area.size));
+
+        renderer.viewport(text_area);
+        render_line_backgrounds(renderer, editor, *id, &layout, camera, text_area.size);
+        render_matches(renderer, editor, *id, &layout, camera, text_area.size);
+        render_text(renderer, atlas, editor, &layout, camera, text_area.size);
+        if editor.options().list {
+            render_list_chars(renderer, atlas, editor, &layout, camera, text_area.size);
+        }
+        if layout.gutter > 0 {
+            render_gutter(
+                renderer,
+                atlas,
+                editor,
+                &layout,
+                cursor_pos.0,
+                camera,
+                text_area.size,
+            );
+        }
+        render_cursor(
+            renderer,
+            &layout,
+            cursor_pos,
+            (camera, cursor),
+            text_area.size,
+            (*id == editor.current_window()).then_some(&editor.mode),
+        );
+    }
+
+    renderer.viewport(Rect::new((0.0, 0.0), resolution));
+    render_ui(renderer, atlas, editor, &windows, &area, resolution);
+    if editor.tab_count() > 1 {
+        render_tab_line(renderer, atlas, editor, resolution);
+    }
+    if let Some(completion) = editor.wildmenu() {
+        render_wildmenu(renderer, atlas, highlights, completion, resolution);
+    }
+}
+
+// text columns of every window of the current tab page, right of its
+// line number gutter
+pub fn text_columns(editor: &Editor, atlas: &FontAtlas, resolution: V2) -> Vec<(usize, usize)> {
+    let area = windows_area(editor, resolution);
+    editor
+        .window_rects(area)
+        .iter()
+        .filter_map(|(id, rect)| {
+            let (buffer, _) = editor.window(*id)?;
+            let gutter = editor.options().gutter_width(buffer.line_count());
+            let columns = columns(editor, atlas, &text_area(rect, &area), gutter);
+            Some((*id, columns))
+        })
+        .collect()
+}
+
+// fixed camera scale given by 'zoom'
+fn zoom(options: &Options) -> Option<f32> {
+    (options.zoom > 0).then(|| MAX_SCALE * options.zoom as f32 / 100.0)
+}
+
+// columns fitting into the text area at the zoomed or the largest
+// automatic camera scale
+fn columns(editor: &Editor, atlas: &FontAtlas, text_area: &Rect, gutter: usize) -> usize {
+    let scale = zoom(editor.options()).unwrap_or(MAX_SCALE);
+    let columns = (text_area.size.x / (atlas.glyph('?').ax * scale)) as usize;
+    columns.saturating_sub(gutter).max(1)
+}
+
+// the bottom row is left to the command line and the top row to the tab
+// line if there are multiple tab pages
+fn windows_area(editor: &Editor, resolution: V2) -> Rect {
+    let tab_line = if editor.tab_count() > 1 {
+        STATUS_BAR_HEIGHT
+    } else {
+        0.0
+    };
+    Rect::new(
+        (0.0, STATUS_BAR_HEIGHT),
+        (resolution.x, resolution.y - STATUS_BAR_HEIGHT - tab_line),
+    )
+}
+
+fn separator(rect: &Rect, area: &Rect) -> f32 {
+    if rect.right() < area.right() - 1.0 {
+        SEPARATOR_WIDTH
+    } else {
+        0.0
+    }
+}
+
+// window area without its status line and the separator to its right
+fn text_area(rect: &Rect, area: &Rect) -> Rect {
+    Rect::new(
+        rect.pos + (0.0, STATUS_BAR_HEIGHT).into(),
+        rect.size - (separator(rect, area), STATUS_BAR_HEIGHT).into(),
+    )
+}
+
+// screen rows of a buffer line and the byte offsets of its characters,
+// which are only needed for lines that are not all ASCII
+#[derive(Clone)]
+struct LineRows {
+    first_row: usize,
+    starts: Vec<usize>,
+    chars: Vec<usize>,
+}
+
+impl LineRows {
+    // screen column of a byte offset from the start of the line
+    fn column(&self, col: usize) -> usize {
+        if self.chars.is_empty() {
+            col
+        } else {
+            self.chars.partition_point(|idx| *idx < col)
+        }
+    }
+
+    fn last_row(&self) -> usize {
+        self.first_row + self.starts.len() - 1
+    }
+}
+
+// screen rows of the buffer lines of a window, one per line unless long
+// lines are wrapped
+struct Layout<'a> {
+    buffer: &'a Buffer,
+    wrap: Option<Wrap>,
+    gutter: usize,
+    // glyph width of the monospace font
+    advance: f32,
+    // first row of every line with 'wrap'
+    first_rows: &'a [usize],
+    // lines inside the view and their rows
+    lines: Range<usize>,
+    rows: Vec<LineRows>,
+}
+
+impl<'a> Layout<'a> {
+    // lays out the lines shown in the given rows
+    fn visible(mut self, (first_row, last_row): (usize, usize)) -> Layout<'a> {
+        let count = self.buffer.line_count();
+        self.lines = if self.wrap.is_none() {
+            first_row.min(count)..(last_row + 1).min(count)
+        } else {
+            let first = self.first_rows.partition_point(|row| *row <= first_row);
+            let last = self.first_rows.partition_point(|row| *row <= last_row);
+            first.saturating_sub(1)..last
+        };
+        self.rows = self.lines.clone().map(|line| self.layout(line)).collect();
+        self
+    }
+
+    fn layout(&self, line: usize) -> LineRows {
+        let text = self.buffer.line_text(line);
+        let chars = match text.is_ascii() {
+            true => Vec::new(),
+            false => text.char_indices().map(|(idx, _)| idx).collect(),
+        };
+        LineRows {
+            first_row: self.first_rows.get(line).copied().unwrap_or(line),
+            starts: self
+                .wrap
+                .map_or_else(|| vec![0], |wrap| self.buffer.rows(line, &wrap)),
+            chars,
+        }
+    }
+
+    // rows of a line, laid out again if it is outside of the view
+    fn line(&self, line: usize) -> Cow<'_, LineRows> {
+        match self.rows.get(line.wrapping_sub(self.lines.start)) {
+            Some(rows) if self.lines.contains(&line) => Cow::Borrowed(rows),
+            _ => Cow::Owned(self.layout(line)),
+        }
+    }
+
+    // 'showbreak' columns in front of the continued rows of a line
+    fn indent(&self, row: usize) -> usize {
+        match &self.wrap {
+            Some(wrap) if row > 0 => wrap.prefix,
+            _ => 0,
+        }
+    }
+
+    // x position of a screen column right of the gutter
+    fn x(&self, col: usize) -> f32 {
+        (self.gutter + col) as f32 * self.advance
+    }
+
+    // (row, column) on screen of a byte offset in a line
+    fn pos(&self, rows: &LineRows, col: usize) -> (usize, usize) {
+        let row = rows.starts.partition_point(|start| *start <= col) - 1;
+        let col = rows.column(col) - rows.column(rows.starts[row]) + self.indent(row);
+        (rows.first_row + row, col)
+    }
+
+    // (row, column on screen, bytes) of the parts of a byte range of a line
+    // in the rows it is shown in
+    fn pieces<'b>(
+        &'b self,
+        rows: &'b LineRows,
+        cols: Range<usize>,
+    ) -> impl Iterator<Item = (usize, usize, Range<usize>)> + 'b {
+        rows.starts
+            .iter()
+            .enumerate()
+            .filter_map(move |(row, start)| {
+                let end = rows.starts.get(row + 1).copied().unwrap_or(usize::MAX);
+                let piece = cols.start.max(*start)..cols.end.min(end);
+                let col = rows.column(piece.start) - rows.column(*start) + self.indent(row);
+                (!piece.is_empty()).then_some((rows.first_row + row, col, piece))
+            })
+    }
+
+    // width of the laid out text including the gutter, the lines are
+    // not scrolled horizontally if they are wrapped
+    fn columns(&self) -> usize {
+        self.gutter
+            + self
+                .wrap
+                .map_or(self.buffer.longest_line(), |wrap| wrap.width)
+    }
+}
+
+// range of the screen rows inside the camera's view
+fn visible_rows(camera: &Camera, resolution: V2) -> (usize, usize) {
+    let (view_min, view_max) = camera.view(resolution);
+    let first_row = ((-view_max.y / FONT_SIZE).max(0.0) as usize).saturating_sub(LINE_MARGIN);
+    let last_row = (-view_min.y / FONT_SIZE).max(0.0) as usize + LINE_MARGIN;
+    (first_row, last_row)
+}
+
+fn row_y(row: usize) -> f32 {
+    -(row as f32 + CURSOR_OFFSET) * FONT_SIZE
+}
+
+// 'cursorline' bar behind the cursor line of the window and the
+// 'colorcolumn' rulers over the lines of the buffer
+fn render_line_backgrounds<B: Backend>(
+    renderer: &mut Renderer<B>,
+    editor: &Editor,
+    id: usize,
+    layout: &Layout,
+    camera: &Camera,
+    resolution: V2,
+) {
+    let Some((_, (line, _))) = editor.window(id) else {
+        return;
+    };
+    let (options, highlights) = (editor.options(), editor.highlights());
+    let columns = options.color_columns();
+    if !options.cursorline && columns.is_empty() {
+        return;
+    }
+    renderer.activate(Shader::Color, camera, resolution);
+
+    let (view_min, view_max) = camera.view(resolution);
+
+    if options.cursorline {
+        let rows = layout.line(line);
+        renderer.render_solid_rect(
+            (view_min.x, row_y(rows.last_row())).into(),
+            (
+                view_max.x - view_min.x,
+                rows.starts.len() as f32 * FONT_SIZE,
+            )
+                .into(),
+            highlights.bg(Group::CursorLine),
+        );
+    }
+
+    if let (Some(first), Some(last)) = (layout.rows.first(), layout.rows.last()) {
+        let first_row = first.first_row.max(visible_rows(camera, resolution).0);
+        let last_row = last.last_row();
+        for column in columns {
+            renderer.render_solid_rect(
+                (layout.x(column), row_y(last_row)).into(),
+                (
+                    layout.advance,
+                    (last_row - first_row + 1) as f32 * FONT_SIZE,
+                )
+                    .into(),
+                highlights.bg(Group::ColorColumn),
+            );
+        }
+    }
+    renderer.flush();
+}
+
+// backgrounds of the visible matches of the search pattern behind the
+// text, the one at the cursor in IncSearch while the pattern is typed
+fn render_matches<B: Backend>(
+    renderer: &mut Renderer<B>,
+    editor: &Editor,
+    id: usize,
+    layout: &Layout,
+    camera: &Camera,
+    resolution: V2,
+) {
+    let (Some(pattern), Some((buffer, cursor_pos))) =
+        (editor.search_highlight(), editor.window(id))
+    else {
+        return;
+    };
+    let current = (id == editor.current_window() && editor.is_searching()).then_some(cursor_pos);
+    let highlights = editor.highlights();
+    renderer.activate(Shader::Color, camera, resolution);
+
+    for (line, cols) in buffer.matches(pattern, layout.lines.clone()) {
+        let group = if current == Some((line, cols.start)) {
+            Group::IncSearch
+        } else {
+            Group::Search
+        };
+        let rows = layout.line(line);
+        for (row, col, piece) in layout.pieces(&rows, cols) {
+            let size = V2 {
+                x: (rows.column(piece.end) - rows.column(piece.start)) as f32 * layout.advance,
+                y: FONT_SIZE,
+            };
+            renderer.render_solid_rect(
+                (layout.x(col), row_y(row)).into(),
+                size,
+                highlights.bg(group),
+            );
+        }
+    }
+    renderer.flush();
+}
+
+// words of the visible lines right of the gutter, each part of a wrapped
+// line after the 'showbreak' text
+fn render_text<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    editor: &Editor,
+    layout: &Layout,
+    camera: &Camera,
+    resolution: V2,
+) {
+    let highlights = editor.highlights();
+    let showbreak = &editor.options().showbreak;
+    renderer.activate(Shader::Text, camera, resolution);
+
+    let (view_min, view_max) = camera.view(resolution);
+    let lines = layout.lines.clone();
+
+    for ((idx, line), rows) in lines
+        .clone()
+        .zip(layout.buffer.iter_lines(lines))
+        .zip(&layout.rows)
+    {
+        let colors = span_colors(highlights, layout.buffer.spans(idx));
+        let mut col = 0;
+
+        for word in line {
+            for (row, x_col, piece) in layout.pieces(rows, col..col + word.len()) {
+                let x_offset = layout.x(x_col);
+                let text = &word[piece.start - col..piece.end - col];
+                if x_offset > view_max.x || x_offset + atlas.text_width(text) < view_min.x {
+                    continue;
+                }
+                let color = LineColors {
+                    colors: &colors,
+                    offset: piece.start,
+                };
+                let pos = (x_offset, -(row as f32) * FONT_SIZE).into();
+                renderer.render_text(atlas, text, pos, color, 1.0);
+            }
+            col += word.len();
+        }
+
+        if !showbreak.is_empty() {
+            for row in rows.first_row + 1..=rows.last_row() {
+                let pos = (layout.x(0), -(row as f32) * FONT_SIZE).into();
+                renderer.render_text(atlas, showbreak, pos, highlights.fg(Group::NonText), 1.0);
+            }
+        }
+    }
+    renderer.flush();
+}
+
+// 'listchars' markers of the blanks and line ends of the visible lines
+fn render_list_chars<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    editor: &Editor,
+    layout: &Layout,
+    camera: &Camera,
+    resolution: V2,
+) {
+    let highlights = editor.highlights();
+    let list_chars = editor.options().list_chars();
+    let buffer = layout.buffer;
+    renderer.activate(Shader::Text, camera, resolution);
+
+    let mut marker = |c: char, col: usize, rows: &LineRows, group: Group| {
+        let (row, col) = layout.pos(rows, col);
+        let pos = (layout.x(col), -(row as f32) * FONT_SIZE).into();
+        renderer.render_text(
+            atlas,
+            c.encode_utf8(&mut [0; 4]),
+            pos,
+            highlights.fg(group),
+            1.0,
+        );
+    };
+
+    for (line, rows) in layout.lines.clone().zip(&layout.rows) {
+        for (col, blank, trailing) in buffer.blanks(line) {
+            let c = match blank {
+                '\t' => list_chars.tab,
+                _ if trailing => list_chars.trail.or(list_chars.space),
+                _ => list_chars.space,
+            };
+            if let Some(c) = c {
+                marker(c, col, rows, Group::Whitespace);
+            }
+        }
+        if let Some(eol) = list_chars.eol.filter(|_| line + 1 < buffer.line_count()) {
+            marker(eol, buffer.line_len(line), rows, Group::NonText);
+        }
+    }
+    renderer.flush();
+}
+
+// text color from the start of each highlighted part and after it
+fn span_colors(highlights: &Highlights, spans: &[Span]) -> Vec<(usize, V4)> {
+    let normal = highlights.fg(Group::Normal);
+    let mut colors = vec![(0, normal)];
+    for span in spans {
+        colors.push((span.start, highlights.fg(span.group)));
+        colors.push((span.end, normal));
+    }
+    colors
+}
+
+// absolute, relative or hybrid line numbers left of the first row of
+// each line
+fn render_gutter<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    editor: &Editor,
+    layout: &Layout,
+    current: usize,
+    camera: &Camera,
+    resolution: V2,
+) {
+    let (options, highlights) = (editor.options(), editor.highlights());
+    renderer.activate(Shader::Text, camera, resolution);
+
+    let width = layout.gutter - 1;
+
+    for (line, rows) in layout.lines.clone().zip(&layout.rows) {
+        let (label, color) = if line == current {
+            let label = match (options.number, options.relativenumber) {
+                (true, true) => format!("{:<width$}", line + 1),
+                (true, false) => format!("{:>width$}", line + 1),
+                _ => format!("{:>width$}", 0),
+            };
+            (label, highlights.fg(Group::CursorLineNr))
+        } else {
+            let number = if options.relativenumber {
+                line.abs_diff(current)
+            } else {
+                line + 1
+            };
+            (format!("{number:>width$}"), highlights.fg(Group::LineNr))
+        };
+
+        let y_offset = -(rows.first_row as f32) * FONT_SIZE;
+        renderer.render_text(atlas, &label, (0.0, y_offset).into(), color, 1.0);
+    }
+    renderer.flush();
+}
+
+// the cursor is only drawn in the active window, given its mode
+fn render_cursor<B: Backend>(
+    renderer: &mut Renderer<B>,
+    layout: &Layout,
+    (line, col): (usize, usize),
+    (camera, cursor): (&mut Camera, &mut Cursor),
+    resolution: V2,
+    mode: Option<&Mode>,
+) {
+    let cursor_size = V2 {
+        x: layout.advance,
+        y: -FONT_SIZE,
+    };
+    let max_line_length = layout.columns() as f32 * cursor_size.x;
+
+    let (row, col) = layout.pos(&layout.line(line), col);
+    let cursor_target = V2 {
+        x: (layout.gutter + col) as f32,
+        y: row as f32 + CURSOR_OFFSET,
+    } * cursor_size;
+    cursor.move_to(cursor_target);
+
+    // the camera keeps the center of the cursor cell in view, below the
+    // top of the first row
+    let center = cursor.pos + V2::from((cursor_size.x, FONT_SIZE)) / 2.0;
+    let origin = (0.0, (1.0 - CURSOR_OFFSET) * FONT_SIZE).into();
+    camera.target(center, origin, max_line_length, resolution);
+
+    if let Some(mode) = mode.filter(|_| cursor.visible()) {
+        renderer.activate(Shader::Color, &*camera, resolution);
+        cursor.render(renderer, *mode != Mode::Insert);
+        renderer.flush();
+    }
+}
+
+fn render_ui<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    editor: &Editor,
+    windows: &[(usize, Rect)],
+    area: &Rect,
+    resolution: V2,
+) {
+    let to_screen = |pos: V2| pos - resolution / 2.0;
+    let highlights = editor.highlights();
+    let status_group = |id: usize| {
+        if id == editor.current_window() {
+            Group::StatusLine
+        } else {
+            Group::StatusLineNC
+        }
+    };
+
+    renderer.activate(Shader::Color, Projection::Screen, resolution);
+    for (id, rect) in windows {
+        let separator = separator(rect, area);
+        let color = highlights.bg(status_group(*id));
+
+        renderer.render_solid_rect(
+            to_screen(rect.pos),
+            (rect.size.x - separator, STATUS_BAR_HEIGHT).into(),
+            color,
+        );
+        if separator > 0.0 {
+            renderer.render_solid_rect(
+                to_screen(rect.pos + (rect.size.x - separator, 0.0).into()),
+                (separator, rect.size.y).into(),
+                highlights.bg(Group::VertSplit),
+            );
+        }
+    }
+    if editor.mode == Mode::Command {
+        let typed = &editor.command_line()[..editor.command_cursor()];
+        renderer.render_solid_rect(
+            to_screen((atlas.text_width(typed) * UI_SCALE, 0.0).into()),
+            (COMMAND_CURSOR_WIDTH, STATUS_BAR_HEIGHT).into(),
+            highlights.fg(Group::MsgArea),
+        );
+    }
+    renderer.flush();
+
+    renderer.activate(Shader::Text, Projection::Screen, resolution);
+    for (id, rect) in windows {
+        let (left, right) = editor.status_line(*id);
+        let right_width = atlas.text_width(&right) * UI_SCALE;
+        let right_pos = rect.right() - separator(rect, area) - right_width;
+        let color = highlights.fg(status_group(*id));
+
+        renderer.render_text(atlas, &left, to_screen(rect.pos), color, UI_SCALE);
+        renderer.render_text(
+            atlas,
+            &right,
+            to_screen((right_pos, rect.pos.y).into()),
+            color,
+            UI_SCALE,
+        );
+    }
+
+    // multi-line messages like :ls grow upwards from the command line
+    for (idx, (line, color)) in command_lines(editor).into_iter().rev().enumerate() {
+        renderer.render_text(
+            atlas,
+            line,
+            to_screen((0.0, idx as f32 * STATUS_BAR_HEIGHT).into()),
+            color,
+            UI_SCALE,
+        );
+    }
+    renderer.flush();
+}
+
+// errors are shown in red, multi-line messages end in a "Press ENTER" prompt
+fn command_lines(editor: &Editor) -> Vec<(&str, V4)> {
+    let highlights = editor.highlights();
+    let text_color = highlights.fg(Group::MsgArea);
+
+    match editor.current_message() {
+        Some(message) if editor.mode == Mode::Normal => {
+            let color = match message.kind {
+                MessageKind::Info => text_color,
+                MessageKind::Error => highlights.fg(Group::ErrorMsg),
+            };
+            let mut lines = message
+                .text
+                .lines()
+                .map(|line| (line, color))
+                .collect::<Vec<_>>();
+            if message.is_multiline() {
+                lines.push((PRESS_ENTER, highlights.fg(Group::MoreMsg)));
+            }
+            lines
+        }
+        _ => editor
+            .command_line()
+            .lines()
+            .map(|line| (line, text_color))
+            .collect(),
+    }
+}
+
+// completion candidates in a row over the status line above the command
+// line, scrolled so that the selected one is visible
+fn render_wildmenu<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    highlights: &Highlights,
+    completion: &Completion,
+    resolution: V2,
+) {
+    let bottom_left = V2 {
+        x: -resolution.x / 2.0,
+        y: -resolution.y / 2.0 + STATUS_BAR_HEIGHT,
+    };
+    let widths = completion
+        .candidates()
+        .iter()
+        .map(|candidate| atlas.text_width(candidate) * UI_SCALE + 2.0 * WILDMENU_PADDING)
+        .collect::<Vec<_>>();
+
+    let selected = completion.selected().unwrap_or(0);
+    let mut first = 0;
+    while first < selected && widths[first..=selected].iter().sum::<f32>() > resolution.x {
+        first += 1;
+    }
+
+    renderer.activate(Shader::Color, Projection::Screen, resolution);
+    renderer.render_solid_rect(
+        bottom_left,
+        (resolution.x, STATUS_BAR_HEIGHT).into(),
+        highlights.bg(Group::Pmenu),
+    );
+    if let Some(selected) = completion.selected() {
+        let x_offset = widths[first..selected].iter().sum::<f32>();
+        renderer.render_solid_rect(
+            bottom_left + (x_offset, 0.0).into(),
+            (widths[selected], STATUS_BAR_HEIGHT).into(),
+            highlights.bg(Group::WildMenu),
+        );
+    }
+    renderer.flush();
+
+    renderer.activate(Shader::Text, Projection::Screen, resolution);
+    let mut x_offset = WILDMENU_PADDING;
+    for (idx, candidate) in completion.candidates().iter().enumerate().skip(first) {
+        if x_offset > resolution.x {
+            break;
+        }
+        let color = if Some(idx) == completion.selected() {
+            highlights.fg(Group::WildMenu)
+        } else {
+            highlights.fg(Group::Pmenu)
+        };
+        renderer.render_text(
+            atlas,
+            candidate,
+            bottom_left + (x_offset, 0.0).into(),
+            color,
+            UI_SCALE,
+        );
+        x_offset += widths[idx];
+    }
+    renderer.flush();
+}
+
+fn render_tab_line<B: Backend>(
+    renderer: &mut Renderer<B>,
+    atlas: &FontAtlas,
+    editor: &Editor,
+    resolution: V2,
+) {
+    let top_left = V2 {
+        x: -resolution.x / 2.0,
+        y: resolution.y / 2.0 - STATUS_BAR_HEIGHT,
+    };
+    let highlights = editor.highlights();
+    let tab_group = |active: bool| {
+        if active {
+            Group::TabLineSel
+        } else {
+            Group::TabLine
+        }
+    };
+
+    let labels = editor
+        .tab_windows()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, id)| {
+            let (buffer, _) = editor.window(*id)?;
+            let modified = if buffer.is_modified() { " [+]" } else { "" };
+            let label = format!("{} {}{modified}", idx + 1, buffer.name());
+            let width = atlas.text_width(&label) * UI_SCALE + 2.0 * TAB_PADDING;
+            Some((idx == editor.current_tab(), label, width))
+        })
+        .collect::<Vec<_>>();
+
+    renderer.activate(Shader::Color, Projection::Screen, resolution);
+    renderer.render_solid_rect(
+        top_left,
+        (resolution.x, STATUS_BAR_HEIGHT).into(),
+        highlights.bg(Group::TabLineFill),
+    );
+    let mut x_offset = 0.0;
+    for (active, _, width) in &labels {
+        renderer.render_solid_rect(
+            top_left + (x_offset, 0.0).into(),
+            (width - SEPARATOR_WIDTH, STATUS_BAR_HEIGHT).into(),
+            highlights.bg(tab_group(*active)),
+        );
+        x_offset += width;
+    }
+    renderer.flush();
+
+    renderer.activate(Shader::Text, Projection::Screen, resolution);
+    let mut x_offset = TAB_PADDING;
+    for (active, label, width) in &labels {
+        renderer.render_text(
+            atlas,
+            label,
+            top_left + (x_offset, 0.0).into(),
+            highlights.fg(tab_group(*active)),
+            UI_SCALE,
+        );
+        x_offset += width;
+    }
+    renderer.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Key;
+    use crate::render::{Batch, HeadlessBackend, DELTA_TIME};
+
+    const ADVANCE: f32 = 100.0;
+
+    fn render(editor: &Editor, views: &mut Views) -> Vec<Batch> {
+        let atlas = FontAtlas::monospace(ADVANCE, FONT_SIZE as i32);
+        let mut renderer = Renderer::new(HeadlessBackend::new());
+
+        render_frame(&mut renderer, &atlas, editor, views, (800, 600).into());
+        renderer.backend().batches.clone()
+    }
+
+    fn quads(batch: &Batch) -> Vec<(V2, V2)> {
+        batch
+            .vertices
+            .chunks(6)
+            .map(|quad| (quad[0].pos, quad[5].pos))
+            .collect()
+    }
+
+    fn rect<P: Into<V2>, S: Into<V2>>(pos: P, size: S) -> (V2, V2) {
+        let pos = pos.into();
+        (pos, pos + size.into())
+    }
+
+    #[test]
+    fn glyph_quads() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("ab c\nd");
+
+        let batches = render(&editor, &mut Views::new());
+        let text = &batches[0];
+
+        assert_eq!(text.shader, Shader::Text);
+        assert!(matches!(text.projection, Projection::Camera { .. }));
+        assert_eq!(
+            quads(text),
+            vec![
+                rect((0.0, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+                rect((ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+                rect((2.0 * ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+                rect((3.0 * ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+                rect((0.0, 0.0), (ADVANCE, -FONT_SIZE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_colors() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("fn x() // a");
+        editor.buffer_mut().set_path("main.rs");
+
+        let batches = render(&editor, &mut Views::new());
+        let highlights = editor.highlights();
+        let colors = batches[0]
+            .vertices
+            .chunks(6)
+            .map(|quad| quad[0].color)
+            .collect::<Vec<_>>();
+
+        let keyword = highlights.fg(Group::Keyword);
+        let comment = highlights.fg(Group::Comment);
+        assert_eq!(colors[..2], [keyword; 2]);
+        assert_eq!(colors[2], highlights.fg(Group::Normal));
+        assert_eq!(colors[3], highlights.fg(Group::Function));
+        assert_eq!(colors[7..], [comment; 4]);
+    }
+
+    #[test]
+    fn offscreen_lines_are_culled() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert(&"x\n".repeat(1000));
+
+        let batches = render(&editor, &mut Views::new());
+        let glyphs = batches[0].vertices.len() / 6;
+
+        assert!(glyphs > 0);
+        assert!(glyphs < 20);
+    }
+
+    #[test]
+    fn cursor_rect() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("foo\nbar");
+
+        let mut views = Views::new();
+        render(&editor, &mut views);
+
+        for _ in 0..100 {
+            views.update(DELTA_TIME);
+        }
+        views.get(editor.current_window()).cursor.active();
+
+        let batches = render(&editor, &mut views);
+        let rects = batches
+            .iter()
+            .filter(|batch| batch.shader == Shader::Color)
+            .filter(|batch| matches!(batch.projection, Projection::Camera { .. }))
+            .flat_map(quads)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rects,
+            vec![rect(
+                (3.0 * ADVANCE, -(1.0 + CURSOR_OFFSET) * FONT_SIZE),
+                (FONT_SIZE / 3.0, FONT_SIZE)
+            )]
+        );
+    }
+
+    #[test]
+    fn search_matches() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("ab ab\nxab");
+        editor.buffer_mut().goto_line(0);
+        for key in Key::parse("/ab") {
+            editor.handle_key(key);
+        }
+
+        let matches = |editor: &Editor| {
+            let batches = render(editor, &mut Views::new());
+            let batch = batches
+                .iter()
+                .find(|batch| batch.shader == Shader::Color)
+                .unwrap()
+                .clone();
+            quads(&batch)
+                .into_iter()
+                .zip(batch.vertices.chunks(6).map(|quad| quad[0].color))
+                .collect::<Vec<_>>()
+        };
+        let highlights = editor.highlights();
+        let (search, current) = (
+            highlights.bg(Group::Search),
+            highlights.bg(Group::IncSearch),
+        );
+        let size = (2.0 * ADVANCE, FONT_SIZE);
+        assert_eq!(
+            matches(&editor),
+            vec![
+                (rect((0.0, -CURSOR_OFFSET * FONT_SIZE), size), search),
+                (
+                    rect((3.0 * ADVANCE, -CURSOR_OFFSET * FONT_SIZE), size),
+                    current
+                ),
+                (
+                    rect((ADVANCE, -(1.0 + CURSOR_OFFSET) * FONT_SIZE), size),
+                    search
+                ),
+            ]
+        );
+
+        // all matches look the same once the search is done
+        editor.command_execute();
+        assert!(matches(&editor).iter().all(|(_, color)| *color == search));
+    }
+
+    #[test]
+    fn cursor_line_columns_and_list() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("a b \nc");
+        editor.handle_normal(":");
+        editor.handle_command("set cul cc=2 list lcs=eol:$,space:.,trail:~");
+        editor.command_execute();
+
+        let batches = render(&editor, &mut Views::new());
+        let highlights = editor.highlights();
+        let backgrounds = &batches[0];
+        assert_eq!(backgrounds.shader, Shader::Color);
+        let colors = backgrounds
+            .vertices
+            .chunks(6)
+            .map(|quad| quad[0].color)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            colors,
+            vec![
+                highlights.bg(Group::CursorLine),
+                highlights.bg(Group::ColorColumn)
+            ]
+        );
+        // the ruler covers both lines of the buffer
+        assert_eq!(
+            quads(backgrounds)[1],
+            rect(
+                (ADVANCE, -(1.0 + CURSOR_OFFSET) * FONT_SIZE),
+                (ADVANCE, 2.0 * FONT_SIZE)
+            )
+        );
+
+        // a space, a trailing space and the end of the first line
+        let markers = &batches[2];
+        assert_eq!(markers.shader, Shader::Text);
+        assert_eq!(
+            quads(markers),
+            vec![
+                rect((ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+                rect((3.0 * ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+                rect((4.0 * ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+            ]
+        );
+        assert_eq!(markers.vertices[0].color, highlights.fg(Group::Whitespace));
+        assert_eq!(markers.vertices[12].color, highlights.fg(Group::NonText));
+    }
+
+    #[test]
+    fn wrapped_lines() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("abcdefghijklmnopqrstuvw xyz\nd");
+        editor.handle_normal(":");
+        editor.handle_command("set wrap lbr sbr=>");
+        editor.command_execute();
+
+        // 26 columns fit into the window, the last word moves to the
+        // second row after the 'showbreak' text
+        let batches = render(&editor, &mut Views::new());
+        let text = quads(&batches[0]);
+        assert_eq!(
+            text[text.len() - 5..],
+            [
+                rect((ADVANCE, 0.0), (ADVANCE, -FONT_SIZE)),
+                rect((2.0 * ADVANCE, 0.0), (ADVANCE, -FONT_SIZE)),
+                rect((3.0 * ADVANCE, 0.0), (ADVANCE, -FONT_SIZE)),
+                rect((0.0, 0.0), (ADVANCE, -FONT_SIZE)),
+                rect((0.0, -FONT_SIZE), (ADVANCE, -FONT_SIZE)),
+            ]
+        );
+        assert_eq!(
+            batches[0].vertices[(text.len() - 2) * 6].color,
+            editor.highlights().fg(Group::NonText)
+        );
+    }
+
+    #[test]
+    fn line_number_gutter() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("ab\ncd\nef");
+        editor.handle_normal(":");
+        editor.handle_command("set nu rnu");
+        editor.command_execute();
+        assert_eq!(
+            editor.cursor(),
+            editor.buffer().cursor() + (4.0, 0.0).into()
+        );
+
+        let batches = render(&editor, &mut Views::new());
+        let (text, gutter) = (quads(&batches[0]), &batches[1]);
+        assert_eq!(
+            text[0],
+            rect((4.0 * ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE))
+        );
+
+        // relative numbers with the absolute one in the current line
+        assert_eq!(gutter.vertices.len(), 3 * 3 * 6);
+        assert_eq!(
+            quads(gutter)[2],
+            rect((2.0 * ADVANCE, FONT_SIZE), (ADVANCE, -FONT_SIZE))
+        );
+        assert_eq!(
+            quads(gutter)[6],
+            rect((0.0, -FONT_SIZE), (ADVANCE, -FONT_SIZE))
+        );
+        assert_eq!(gutter.vertices[6 * 6].color, V4::rgb(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn status_bar() {
+        let mut editor = Editor::new();
+        editor.handle_normal(":");
+        editor.handle_command("set stl=%f%m%=%l:%c");
+        editor.command_execute();
+
+        let batches = render(&editor, &mut Views::new());
+        let ui = batches
+            .iter()
+            .filter(|batch| batch.projection == Projection::Screen)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ui.len(), 2);
+        assert_eq!(ui[0].shader, Shader::Color);
+        assert_eq!(
+            quads(ui[0]),
+            vec![rect(
+                (-400.0, -300.0 + STATUS_BAR_HEIGHT),
+                (800.0, STATUS_BAR_HEIGHT)
+            )]
+        );
+        assert_eq!(ui[1].shader, Shader::Text);
+        assert_eq!(ui[1].vertices.len(), ("[No Name]".len() + "1:1".len()) * 6);
+
+        // the right part is aligned to the right edge of the window
+        let right = quads(ui[1])["[No Name]".len()];
+        assert_eq!(right.0.x, 400.0 - 3.0 * ADVANCE * UI_SCALE);
+        assert_eq!(ui[0].vertices[0].color, V4::rgb(0.5, 0.8, 0.5));
+
+        // colors follow the highlight groups
+        editor.handle_normal(":");
+        editor.handle_command("hi StatusLine guifg=#ff0000 guibg=#0000ff");
+        editor.command_execute();
+        let batches = render(&editor, &mut Views::new());
+        let ui = batches
+            .iter()
+            .filter(|batch| batch.projection == Projection::Screen)
+            .collect::<Vec<_>>();
+        assert_eq!(ui[0].vertices[0].color, V4::rgb(0.0, 0.0, 1.0));
+        assert_eq!(ui[1].vertices[0].color, V4::rgb(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn error_messages() {
+        let mut editor = Editor::new();
+        editor.handle_normal(":");
+        editor.handle_command("foo");
+        editor.command_execute();
+
+        let batches = render(&editor, &mut Views::new());
+        let text = batches.last().unwrap();
+        let message = "E492: Not an editor command: foo";
+        let first = text.vertices.len() - message.len() * 6;
+
+        assert_eq!(text.vertices[first].color, V4::rgb(0.9, 0.2, 0.2));
+        assert_eq!(
+            command_lines(&editor),
+            vec![(message, V4::rgb(0.9, 0.2, 0.2))]
+        );
+    }
+
+    #[test]
+    fn command_line_cursor() {
+        let mut editor = Editor::new();
+        editor.handle_normal(":");
+        editor.handle_command("set nu");
+        editor.handle_key(Key::Left);
+
+        let batches = render(&editor, &mut Views::new());
+        let cursor = batches
+            .iter()
+            .filter(|batch| batch.projection == Projection::Screen)
+            .find(|batch| batch.shader == Shader::Color)
+            .map(quads)
+            .unwrap()
+            .pop();
+
+        let x = ":set n".len() as f32 * ADVANCE * UI_SCALE;
+        assert_eq!(
+            cursor,
+            Some(rect(
+                (x - 400.0, -300.0),
+                (COMMAND_CURSOR_WIDTH, STATUS_BAR_HEIGHT)
+            ))
+        );
+    }
+
+    #[test]
+    fn wildmenu() {
+        let mut editor = Editor::new();
+        editor.handle_normal(":");
+        editor.handle_command("tab");
+        editor.handle_key(Key::Tab);
+        editor.handle_key(Key::Tab);
+        assert_eq!(editor.command_line(), ":tabnext");
+
+        let batches = render(&editor, &mut Views::new());
+        let menu = batches
+            .iter()
+            .filter(|batch| batch.projection == Projection::Screen)
+            .rfind(|batch| batch.shader == Shader::Color)
+            .map(quads)
+            .unwrap();
+
+        // the second candidate is selected
+        let width = |name: &str| name.len() as f32 * ADVANCE * UI_SCALE + 2.0 * WILDMENU_PADDING;
+        assert_eq!(
+            menu,
+            vec![
+                rect((-400.0, -275.0), (800.0, STATUS_BAR_HEIGHT)),
+                rect(
+                    (width("tabnew") - 400.0, -275.0),
+                    (width("tabnext"), STATUS_BAR_HEIGHT)
+                ),
+            ]
+        );
+
+        editor.handle_key(Key::Enter);
+        assert!(editor.wildmenu().is_none());
+    }
+
+    #[test]
+    fn split_viewports() {
+        let mut editor = Editor::new();
+        editor.buffer_mut().insert("foo");
+        editor.handle_normal(":");
+        editor.handle_command("vsp");
+        editor.command_execute();
+
+        let batches = render(&editor, &mut Views::new());
+        let viewports = batches
+            .iter()
+            .filter(|batch| batch.shader == Shader::Text)
+            .filter(|batch| matches!(batch.projection, Projection::Camera { .. }))
+            .map(|batch| batch.viewport)
+            .collect::<Vec<_>>();
+
+        let height = 600.0 - 2.0 * STATUS_BAR_HEIGHT;
+        assert_eq!(
+            viewports,
+            vec![
+                Rect::new(
+                    (0.0, 2.0 * STATUS_BAR_HEIGHT),
+                    (400.0 - SEPARATOR_WIDTH, height)
+                ),
+                Rect::new((400.0, 2.0 * STATUS_BAR_HEIGHT), (400.0, height)),
+            ]
+        );
+    }
+
+    #[test]
+    fn tab_line() {
+        let mut editor = Editor::new();
+        let mut views = Views::new();
+        render(&editor, &mut views);
+        views.get(editor.current_window()).camera.pos = (10.0, 20.0).into();
+
+        editor.handle_normal(":");
+        editor.handle_command("tabnew");
+        editor.command_execute();
+
+        let batches = render(&editor, &mut views);
+        let tabs = batches
+            .iter()
+            .rfind(|batch| batch.projection == Projection::Screen && batch.shader == Shader::Color)
+            .map(quads)
+            .unwrap();
+
+        // background and one label per tab page
+        let label = "1 [No Name]".len() as f32 * ADVANCE * UI_SCALE + 2.0 * TAB_PADDING;
+        assert_eq!(
+            tabs,
+            vec![
+                rect(
+                    (-400.0, 300.0 - STATUS_BAR_HEIGHT),
+                    (800.0, STATUS_BAR_HEIGHT)
+                ),
+                rect(
+                    (-400.0, 300.0 - STATUS_BAR_HEIGHT),
+                    (label - SEPARATOR_WIDTH, STATUS_BAR_HEIGHT)
+                ),
+                rect(
+                    (-400.0 + label, 300.0 - STATUS_BAR_HEIGHT),
+                    (label - SEPARATOR_WIDTH, STATUS_BAR_HEIGHT)
+                ),
+            ]
+        );
+
+        editor.handle_normal("g");
+        editor.handle_normal("T");
+        render(&editor, &mut views);
+        assert_eq!(
+            views.get(editor.current_window()).camera.pos,
+            (10.0, 20.0).into()
+        );
+    }
+}
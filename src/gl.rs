@@ -1,11 +1,17 @@
+use std::mem::size_of;
+
+use bytemuck::offset_of;
 use gl33::{
-    global_loader::*, ShaderType, GL_COMPILE_STATUS, GL_FRAGMENT_SHADER, GL_LINK_STATUS,
-    GL_VERTEX_SHADER,
+    global_loader::*, ShaderType, GL_ARRAY_BUFFER, GL_CLAMP_TO_EDGE, GL_COLOR_BUFFER_BIT,
+    GL_COMPILE_STATUS, GL_DYNAMIC_DRAW, GL_FALSE, GL_FLOAT, GL_FRAGMENT_SHADER, GL_LINEAR,
+    GL_LINK_STATUS, GL_RGBA, GL_TEXTURE0, GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER,
+    GL_TEXTURE_MIN_FILTER, GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T, GL_TRIANGLES, GL_UNPACK_ALIGNMENT,
+    GL_UNSIGNED_BYTE, GL_VERTEX_SHADER,
 };
 
-use crate::camera::Camera;
 use crate::errors::EdiError;
-use crate::render::V2;
+use crate::font::FontAtlas;
+use crate::render::{Backend, Projection, Rect, Shader, Vertex, MAX_VERTICES, V2, V4};
 
 const ERROR_BUFFER_SIZE: usize = 1024;
 
@@ -20,14 +26,14 @@ pub struct CameraShader {
 }
 
 impl CameraShader {
-    pub fn activate(&self, resolution: &V2, camera: &Camera) {
+    pub fn activate(&self, resolution: &V2, pos: V2, scale: f32) {
         glUseProgram(self.program);
 
         unsafe {
             glUniform2f(self.resolution_uniform, resolution.x, resolution.y);
 
-            glUniform1f(self.camera_scale_uniform, camera.scale);
-            glUniform2f(self.camera_pos_uniform, camera.pos.x, camera.pos.y);
+            glUniform1f(self.camera_scale_uniform, scale);
+            glUniform2f(self.camera_pos_uniform, pos.x, pos.y);
         }
     }
 }
@@ -188,3 +194,159 @@ impl GL {
         Ok(shader_id)
     }
 }
+
+pub struct GlBackend {
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+
+    text_shader: CameraShader,
+    color_shader: CameraShader,
+    ui_text_shader: UiShader,
+    ui_color_shader: UiShader,
+}
+
+impl GlBackend {
+    pub fn new(
+        text_shader: CameraShader,
+        color_shader: CameraShader,
+        ui_text_shader: UiShader,
+        ui_color_shader: UiShader,
+    ) -> GlBackend {
+        let mut backend = GlBackend {
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            text_shader,
+            color_shader,
+            ui_text_shader,
+            ui_color_shader,
+        };
+
+        unsafe {
+            glGenVertexArrays(1, &mut backend.vao);
+            glBindVertexArray(backend.vao);
+
+            glGenBuffers(1, &mut backend.vbo);
+            glBindBuffer(GL_ARRAY_BUFFER, backend.vbo);
+            glBufferData(
+                GL_ARRAY_BUFFER,
+                (size_of::<Vertex>() * MAX_VERTICES) as isize,
+                std::ptr::null(),
+                GL_DYNAMIC_DRAW,
+            );
+
+            // position
+            glEnableVertexAttribArray(0);
+            glVertexAttribPointer(
+                0, // location 0
+                2, // 2 values (V2)
+                GL_FLOAT,
+                GL_FALSE.0 as u8,
+                size_of::<Vertex>() as i32,
+                offset_of!(Vertex, pos) as *const _,
+            );
+
+            // color
+            glEnableVertexAttribArray(1);
+            glVertexAttribPointer(
+                1, // location 1
+                4, // 4 values (V4)
+                GL_FLOAT,
+                GL_FALSE.0 as u8,
+                size_of::<Vertex>() as i32,
+                offset_of!(Vertex, color) as *const _,
+            );
+
+            // uv
+            glEnableVertexAttribArray(2);
+            glVertexAttribPointer(
+                2, // location 2
+                2, // 2 values (V2)
+                GL_FLOAT,
+                GL_FALSE.0 as u8,
+                size_of::<Vertex>() as i32,
+                offset_of!(Vertex, uv) as *const _,
+            );
+        }
+
+        backend
+    }
+}
+
+impl Backend for GlBackend {
+    fn upload_atlas(&mut self, atlas: &FontAtlas) {
+        unsafe {
+            if self.texture == 0 {
+                glGenTextures(1, &mut self.texture);
+            }
+
+            glActiveTexture(GL_TEXTURE0);
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+            glBindTexture(GL_TEXTURE_2D, self.texture);
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR.0 as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR.0 as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE.0 as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE.0 as i32);
+
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA.0 as i32,
+                atlas.atlas_width,
+                atlas.atlas_height,
+                0,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                atlas.pixels.as_ptr().cast(),
+            );
+        }
+    }
+
+    fn clear(&mut self, resolution: V2, color: V4) {
+        self.viewport(Rect::new((0.0, 0.0), resolution));
+        unsafe {
+            glClearColor(color.x, color.y, color.z, color.a);
+            glClear(GL_COLOR_BUFFER_BIT);
+        }
+    }
+
+    fn viewport(&mut self, area: Rect) {
+        let (x, y) = (area.pos.x as i32, area.pos.y as i32);
+        let (w, h) = (area.size.x as i32, area.size.y as i32);
+        unsafe {
+            glViewport(x, y, w, h);
+            glScissor(x, y, w, h);
+        }
+    }
+
+    fn activate(&mut self, shader: Shader, projection: Projection, resolution: V2) {
+        match (shader, projection) {
+            (Shader::Text, Projection::Camera { pos, scale }) => {
+                self.text_shader.activate(&resolution, pos, scale)
+            }
+            (Shader::Color, Projection::Camera { pos, scale }) => {
+                self.color_shader.activate(&resolution, pos, scale)
+            }
+            (Shader::Text, Projection::Screen) => self.ui_text_shader.activate(&resolution),
+            (Shader::Color, Projection::Screen) => self.ui_color_shader.activate(&resolution),
+        }
+    }
+
+    fn draw(&mut self, vertices: &[Vertex]) {
+        if vertices.is_empty() {
+            return;
+        }
+
+        unsafe {
+            glBufferSubData(
+                GL_ARRAY_BUFFER,
+                0,
+                std::mem::size_of_val(vertices) as isize,
+                vertices.as_ptr().cast(),
+            );
+            glDrawArrays(GL_TRIANGLES, 0, vertices.len() as i32);
+        }
+    }
+}
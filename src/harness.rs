@@ -0,0 +1,337 @@
+use crate::editor::Editor;
+use crate::excmd::ExCmdResult;
+use crate::key::Key;
+
+// drives an editor with vim-like key sequences, e.g. for behavior tests
+pub struct Harness {
+    pub editor: Editor,
+    pub result: Option<ExCmdResult>,
+}
+
+impl Harness {
+    pub fn new(text: &str) -> Harness {
+        Harness {
+            editor: Editor::from_reader(text.as_bytes()).expect("reading from memory"),
+            result: None,
+        }
+    }
+
+    pub fn keys(&mut self, keys: &str) -> &mut Harness {
+        for key in Key::parse(keys) {
+            if let Some(result) = self.editor.handle_key(key) {
+                self.result = Some(result);
+            }
+        }
+        self
+    }
+
+    // lets `ms` milliseconds pass for key timeouts
+    pub fn wait(&mut self, ms: f32) -> &mut Harness {
+        if let Some(result) = self.editor.update(ms) {
+            self.result = Some(result);
+        }
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.editor.buffer().text()
+    }
+
+    // (line, column) of the cursor
+    pub fn cursor(&self) -> (usize, usize) {
+        self.editor.buffer().cursor_pos()
+    }
+}
+
+pub fn run(text: &str, keys: &str) -> (String, (usize, usize)) {
+    let mut harness = Harness::new(text);
+    harness.keys(keys);
+    (harness.text(), harness.cursor())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, Harness};
+    use crate::camera::Align;
+    use crate::editor::Mode;
+    use crate::excmd::ExCmdResult;
+    use crate::message::MessageKind;
+
+    #[test]
+    fn delete_insert_substitute() {
+        let (text, cursor) = run("foo bar baz qua\nham", "3dwihello<Esc>:s/a/b/<CR>");
+
+        assert_eq!(text, "helloqub\nham");
+        assert_eq!(cursor, (0, 0));
+    }
+
+    #[test]
+    fn counts_and_motions() {
+        let (text, cursor) = run("one two three\nfour five", "2wjx$ia<Esc>");
+
+        assert_eq!(text, "one two three\nfour fiva");
+        assert_eq!(cursor, (1, 9));
+    }
+
+    #[test]
+    fn modes_and_results() {
+        let mut h = Harness::new("foo");
+
+        h.keys("A");
+        assert!(h.editor.mode == Mode::Insert);
+
+        h.keys("bar<BS><Esc>:cq<CR>");
+        assert!(h.editor.mode == Mode::Normal);
+        assert!(h.result == Some(ExCmdResult::Quit(false)));
+        assert_eq!(h.text(), "fooba");
+    }
+
+    #[test]
+    fn move_by_characters() {
+        assert_eq!(run("äb", "lix<Esc>"), ("äxb".to_string(), (0, 3)));
+        assert_eq!(run("日本語", "llh").1, (0, 3));
+        assert_eq!(run("ab\näb", "lj").1, (1, 0));
+        assert_eq!(run("äö x", "e").1, (0, 2));
+    }
+
+    #[test]
+    fn delete_multibyte_chars() {
+        assert_eq!(run("äb", "x"), ("b".to_string(), (0, 0)));
+        assert_eq!(run("äöb", "xx").0, "b");
+        assert_eq!(run("äb", "A<BS><BS><Esc>").0, "");
+        assert_eq!(run("a\nä日", "jA<BS><BS><BS><Esc>").0, "a");
+        assert_eq!(run("bä", "lx").0, "b");
+        assert_eq!(run("äb", "lx").0, "ä");
+        assert_eq!(run("ab", ":s/a/ä/<CR>x").0, "b");
+    }
+
+    #[test]
+    fn substitute_global() {
+        let (text, _) = run("a a a\na", ":s/a/bc/g<CR>");
+        assert_eq!(text, "bc bc bc\na");
+    }
+
+    #[test]
+    fn undo_steps() {
+        let mut h = Harness::new("a");
+
+        h.keys("ofoo<Esc>0x");
+        assert_eq!(h.text(), "a\noo");
+        h.keys("u");
+        assert_eq!(h.text(), "a\nfoo");
+        h.keys("u");
+        assert_eq!(h.text(), "a");
+        h.keys("<C-r>");
+        assert_eq!(h.text(), "a\nfoo");
+
+        // deleting nothing leaves no undo step
+        let mut h = Harness::new("a\nb");
+        h.keys("dddddd");
+        assert_eq!(h.text(), "");
+        h.keys("u");
+        assert_eq!(h.text(), "b");
+    }
+
+    #[test]
+    fn messages_and_pager() {
+        let mut h = Harness::new("foo\nbar");
+
+        h.keys(":foo<CR>");
+        let message = h.editor.current_message().unwrap();
+        assert_eq!(message.kind, MessageKind::Error);
+        assert_eq!(message.text, "E492: Not an editor command: foo");
+
+        // single line messages are cleared by the next key
+        h.keys("j");
+        assert!(h.editor.message().is_none());
+        assert_eq!(h.cursor(), (1, 0));
+
+        h.keys("u:mes<CR>");
+        assert_eq!(
+            h.editor.message(),
+            Some("E492: Not an editor command: foo\nAlready at oldest change")
+        );
+        h.keys("<CR>");
+        assert!(h.editor.message().is_none());
+        assert_eq!(h.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn command_line_editing() {
+        let mut h = Harness::new("foo\nbar foo");
+
+        h.keys("/foo<CR>");
+        assert_eq!(h.cursor(), (1, 4));
+        h.keys("/<CR>");
+        assert_eq!(h.cursor(), (0, 0));
+        h.keys("/eggs<CR>");
+        assert_eq!(h.editor.message(), Some("E486: Pattern not found: eggs"));
+
+        h.keys("/o<CR>");
+        assert_eq!(h.cursor(), (0, 1));
+
+        // insert the last search pattern with Ctrl-r and recall the command
+        h.keys(":s/<C-r>//0/g<CR>");
+        assert_eq!(h.text(), "f00\nbar foo");
+        h.keys("j:<Up><CR>");
+        assert_eq!(h.text(), "f00\nbar f00");
+
+        h.keys(":s/r/R/<Left><Left><Left><C-w>a<CR>");
+        assert_eq!(h.text(), "f00\nbRr f00");
+    }
+
+    #[test]
+    fn incremental_search() {
+        let mut h = Harness::new("foo\nbar baz\nbaz");
+
+        // the cursor follows the pattern while it is typed
+        h.keys("/ba");
+        assert_eq!(h.cursor(), (1, 0));
+        assert!(h.editor.is_searching());
+        assert_eq!(h.editor.search_highlight(), Some("ba"));
+        h.keys("z");
+        assert_eq!(h.cursor(), (1, 4));
+        h.keys("<BS><BS><BS>");
+        assert_eq!(h.cursor(), (0, 0));
+
+        // escape restores the cursor, enter searches from where it started
+        h.keys("z<Esc>");
+        assert_eq!(h.cursor(), (0, 0));
+        assert_eq!(h.editor.search_highlight(), None);
+        h.keys("/baz<CR>");
+        assert_eq!(h.cursor(), (1, 4));
+        assert_eq!(h.editor.search_highlight(), Some("baz"));
+
+        h.keys(":noh<CR>");
+        assert_eq!(h.editor.search_highlight(), None);
+        h.keys("/<CR>");
+        assert_eq!(h.cursor(), (2, 0));
+        assert_eq!(h.editor.search_highlight(), Some("baz"));
+    }
+
+    #[test]
+    fn display_rows() {
+        let mut h = Harness::new("foo bar baz\nqux");
+
+        // without 'wrap' gj and gk move by line
+        h.keys("wgj");
+        assert_eq!(h.cursor(), (1, 3));
+        h.keys("gk");
+        assert_eq!(h.cursor(), (0, 3));
+
+        let id = h.editor.current_window();
+        h.editor.set_text_columns(vec![(id, 4)]);
+        h.keys(":set wrap<CR>gj");
+        assert_eq!(h.cursor(), (0, 7));
+        h.keys("j");
+        assert_eq!(h.cursor(), (1, 3));
+        h.keys("gk");
+        assert_eq!(h.cursor(), (0, 11));
+
+        // rows of wide characters hold as many characters as columns
+        let mut h = Harness::new("日本語テキスト\näb");
+        h.editor.set_text_columns(vec![(id, 4)]);
+        h.keys(":set wrap<CR>llgj");
+        assert_eq!(h.cursor(), (0, 18));
+        h.keys("gk");
+        assert_eq!(h.cursor(), (0, 6));
+        h.keys("j0lgk");
+        assert_eq!(h.cursor(), (0, 15));
+    }
+
+    #[test]
+    fn zoom_and_scroll() {
+        let mut h = Harness::new("foo");
+
+        h.keys("<C-+><C-+>");
+        assert_eq!(h.editor.options().zoom, 120);
+        h.keys("<C-->");
+        assert_eq!(h.editor.options().zoom, 110);
+        h.keys("<C-0>");
+        assert_eq!(h.editor.options().zoom, 0);
+        h.keys(":set zoom=10<CR><C-->");
+        assert_eq!(h.editor.options().zoom, 10);
+
+        let id = h.editor.current_window();
+        h.keys("zzzb");
+        assert_eq!(
+            h.editor.take_aligns(),
+            vec![(id, Align::Center), (id, Align::Bottom)]
+        );
+        assert!(h.editor.take_aligns().is_empty());
+    }
+
+    #[test]
+    fn tab_completion() {
+        let mut h = Harness::new("foo");
+
+        h.keys(":se<Tab>");
+        assert_eq!(h.editor.command_line(), ":set");
+        h.keys(" rel<Tab> nu<S-Tab>");
+        assert_eq!(h.editor.command_line(), ":set relativenumber number");
+        // cycling past the last candidate restores the typed word
+        h.keys("<S-Tab>");
+        assert_eq!(h.editor.command_line(), ":set relativenumber nu");
+        h.keys("<CR>");
+        assert!(h.editor.options().relativenumber && h.editor.options().number);
+
+        h.keys(":b <Tab>");
+        assert_eq!(h.editor.command_line(), ":b [No Name]");
+    }
+
+    #[test]
+    fn repeat_search() {
+        let mut h = Harness::new("a foo\nb\nc foo");
+        h.keys("/foo<CR>n");
+        assert_eq!(h.cursor(), (2, 2));
+        h.keys("N");
+        assert_eq!(h.cursor(), (0, 2));
+        h.keys("N");
+        assert_eq!(h.cursor(), (2, 2));
+        h.keys(":noh<CR>n");
+        assert_eq!(h.cursor(), (0, 2));
+        assert_eq!(h.editor.search_highlight(), Some("foo"));
+
+        let mut h = Harness::new("foo");
+        h.keys("n");
+        assert_eq!(
+            h.editor.message(),
+            Some("E35: No previous regular expression")
+        );
+    }
+
+    #[test]
+    fn mappings_starting_with_their_keys() {
+        let mut h = Harness::new("a foo\nb\nc foo");
+        h.keys(":nmap n nzz<CR>/foo<CR>n");
+        assert_eq!(h.cursor(), (2, 2));
+        assert_eq!(h.editor.message(), None);
+
+        // every x maps again, until the mapping is too deep
+        let mut h = Harness::new("abcd");
+        h.keys(":nmap x xx<CR>x");
+        assert_eq!(h.text(), "");
+        assert_eq!(h.editor.message(), Some("E223: Recursive mapping"));
+        h.keys("ifoo<Esc>");
+        assert_eq!(h.text(), "foo");
+    }
+
+    #[test]
+    fn key_timeouts() {
+        let mut h = Harness::new("foo\nbar\nbaz");
+
+        // an operator keeps waiting for its motion
+        h.keys("d").wait(5000.0).keys("d");
+        assert_eq!(h.text(), "bar\nbaz");
+
+        // <Esc> sequences like <M-j> time out after 'ttimeoutlen'
+        h.keys(":inoremap <lt>Esc>j <lt>Down><CR>");
+        h.keys("ix<Esc>j");
+        assert_eq!(h.cursor(), (1, 1));
+        h.keys("<Esc>").wait(99.0);
+        assert!(h.editor.mode == Mode::Insert);
+        h.wait(1.0);
+        assert!(h.editor.mode == Mode::Normal);
+        assert_eq!(h.text(), "xbar\nbaz");
+    }
+}
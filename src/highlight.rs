@@ -0,0 +1,432 @@
+use std::path::Path;
+
+use crate::render::V4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Group {
+    Normal,
+    MsgArea,
+    Cursor,
+    StatusLine,
+    StatusLineNC,
+    VertSplit,
+    LineNr,
+    CursorLineNr,
+    ErrorMsg,
+    MoreMsg,
+    TabLine,
+    TabLineSel,
+    TabLineFill,
+    Pmenu,
+    WildMenu,
+    Visual,
+    Search,
+    IncSearch,
+    CursorLine,
+    ColorColumn,
+    NonText,
+    Whitespace,
+    Comment,
+    Constant,
+    String,
+    Number,
+    Boolean,
+    Identifier,
+    Function,
+    Statement,
+    Keyword,
+    Operator,
+    Type,
+    PreProc,
+    Special,
+    Todo,
+    Title,
+}
+
+const GROUP_COUNT: usize = 37;
+
+const GROUPS: [(&str, Group); GROUP_COUNT] = [
+    ("Normal", Group::Normal),
+    ("MsgArea", Group::MsgArea),
+    ("Cursor", Group::Cursor),
+    ("StatusLine", Group::StatusLine),
+    ("StatusLineNC", Group::StatusLineNC),
+    ("VertSplit", Group::VertSplit),
+    ("LineNr", Group::LineNr),
+    ("CursorLineNr", Group::CursorLineNr),
+    ("ErrorMsg", Group::ErrorMsg),
+    ("MoreMsg", Group::MoreMsg),
+    ("TabLine", Group::TabLine),
+    ("TabLineSel", Group::TabLineSel),
+    ("TabLineFill", Group::TabLineFill),
+    ("Pmenu", Group::Pmenu),
+    ("WildMenu", Group::WildMenu),
+    ("Visual", Group::Visual),
+    ("Search", Group::Search),
+    ("IncSearch", Group::IncSearch),
+    ("CursorLine", Group::CursorLine),
+    ("ColorColumn", Group::ColorColumn),
+    ("NonText", Group::NonText),
+    ("Whitespace", Group::Whitespace),
+    ("Comment", Group::Comment),
+    ("Constant", Group::Constant),
+    ("String", Group::String),
+    ("Number", Group::Number),
+    ("Boolean", Group::Boolean),
+    ("Identifier", Group::Identifier),
+    ("Function", Group::Function),
+    ("Statement", Group::Statement),
+    ("Keyword", Group::Keyword),
+    ("Operator", Group::Operator),
+    ("Type", Group::Type),
+    ("PreProc", Group::PreProc),
+    ("Special", Group::Special),
+    ("Todo", Group::Todo),
+    ("Title", Group::Title),
+];
+
+// color schemes shipped with the editor, a scheme in the colors directory
+// of the configuration takes precedence
+const BUILTIN_SCHEMES: [(&str, &str); 3] = [
+    ("default", include_str!("../colors/default.vim")),
+    ("desert", include_str!("../colors/desert.vim")),
+    ("morning", include_str!("../colors/morning.vim")),
+];
+
+pub fn builtin_scheme(name: &str) -> Option<&'static str> {
+    BUILTIN_SCHEMES
+        .iter()
+        .find(|(scheme, _)| *scheme == name)
+        .map(|(_, commands)| *commands)
+}
+
+// names of the built-in schemes and of the *.vim files in `dir`
+pub fn scheme_names(dir: Option<&Path>) -> Vec<String> {
+    let mut names = BUILTIN_SCHEMES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+
+    if let Some(entries) = dir.and_then(|dir| dir.read_dir().ok()) {
+        names.extend(entries.filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "vim" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        }));
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+impl Group {
+    pub fn from_name(name: &str) -> Option<Group> {
+        GROUPS
+            .iter()
+            .find(|(group, _)| group.eq_ignore_ascii_case(name))
+            .map(|(_, group)| *group)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        GROUPS.iter().map(|(name, _)| *name)
+    }
+
+    fn name(&self) -> &'static str {
+        GROUPS[*self as usize].0
+    }
+}
+
+// colors of a group, unset ones fall back to those of Normal
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Highlight {
+    pub fg: Option<V4>,
+    pub bg: Option<V4>,
+    // another group used instead as long as no color is set
+    pub link: Option<Group>,
+}
+
+impl Highlight {
+    fn colors(fg: Option<V4>, bg: Option<V4>) -> Highlight {
+        Highlight { fg, bg, link: None }
+    }
+
+    fn link(group: Group) -> Highlight {
+        Highlight {
+            link: Some(group),
+            ..Highlight::default()
+        }
+    }
+}
+
+fn default_highlight(group: Group) -> Highlight {
+    let black = Some(V4::rgb(0.0, 0.0, 0.0));
+    let green = Some(V4::rgb(0.5, 0.8, 0.5));
+    let dark_green = Some(V4::rgb(0.3, 0.4, 0.3));
+    let grey = Some(V4::rgb(0.2, 0.2, 0.2));
+    let white = Some(V4::rgb(0.9, 0.9, 0.9));
+    let fg = |r, g, b| Highlight::colors(Some(V4::rgb(r, g, b)), None);
+
+    match group {
+        Group::Normal => {
+            Highlight::colors(Some(V4::rgb(1.0, 1.0, 0.1)), Some(V4::rgb(0.1, 0.1, 0.1)))
+        }
+        Group::MsgArea => Highlight::colors(white, None),
+        Group::Cursor => Highlight::colors(None, Some(V4::rgba(1.0, 1.0, 1.0, 0.5))),
+        Group::StatusLine | Group::TabLineSel | Group::WildMenu => Highlight::colors(black, green),
+        Group::StatusLineNC | Group::TabLine => Highlight::colors(black, dark_green),
+        Group::VertSplit => Highlight::colors(None, dark_green),
+        Group::LineNr => fg(0.4, 0.4, 0.4),
+        Group::CursorLineNr => Highlight::colors(white, None),
+        Group::ErrorMsg => fg(0.9, 0.2, 0.2),
+        Group::MoreMsg => Highlight::colors(green, None),
+        Group::TabLineFill => Highlight::colors(None, grey),
+        Group::Pmenu => Highlight::colors(white, grey),
+        Group::Visual => Highlight::colors(None, Some(V4::rgb(0.3, 0.3, 0.4))),
+        Group::Search => Highlight::colors(black, Some(V4::rgb(0.9, 0.8, 0.3))),
+        Group::IncSearch => Highlight::colors(black, Some(V4::rgb(0.9, 0.5, 0.2))),
+        Group::CursorLine => Highlight::colors(None, grey),
+        Group::ColorColumn => Highlight::colors(None, Some(V4::rgb(0.3, 0.1, 0.1))),
+        Group::NonText => fg(0.3, 0.3, 0.6),
+        Group::Whitespace => Highlight::link(Group::NonText),
+        Group::Comment => fg(0.5, 0.5, 0.5),
+        Group::Constant => fg(0.9, 0.5, 0.4),
+        Group::String => fg(0.6, 0.8, 0.4),
+        Group::Number | Group::Boolean => Highlight::link(Group::Constant),
+        Group::Identifier => fg(0.5, 0.8, 0.9),
+        Group::Function => Highlight::link(Group::Identifier),
+        Group::Statement => fg(0.9, 0.7, 0.3),
+        Group::Keyword | Group::Operator => Highlight::link(Group::Statement),
+        Group::Type => fg(0.5, 0.9, 0.6),
+        Group::PreProc => fg(0.8, 0.5, 0.9),
+        Group::Special => fg(0.9, 0.6, 0.8),
+        Group::Todo => Highlight::colors(black, Some(V4::rgb(0.9, 0.9, 0.3))),
+        Group::Title => fg(0.9, 0.5, 0.9),
+    }
+}
+
+// #rrggbb or #rrggbbaa
+fn parse_color(value: &str) -> Option<V4> {
+    let hex = value.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |idx: usize| {
+        hex.get(idx..idx + 2)
+            .map(|digits| u8::from_str_radix(digits, 16).map(|c| c as f32 / 255.0))
+    };
+
+    let r = channel(0)?.ok()?;
+    let g = channel(2)?.ok()?;
+    let b = channel(4)?.ok()?;
+    let a = channel(6).unwrap_or(Ok(1.0)).ok()?;
+    Some(V4::rgba(r, g, b, a))
+}
+
+fn format_color(color: V4) -> String {
+    let channel = |c: f32| (c * 255.0).round() as u8;
+    let rgb = format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.x),
+        channel(color.y),
+        channel(color.z)
+    );
+    if color.a < 1.0 {
+        format!("{rgb}{:02x}", channel(color.a))
+    } else {
+        rgb
+    }
+}
+
+fn find_group(name: &str) -> Result<Group, String> {
+    Group::from_name(name).ok_or_else(|| format!("E411: Highlight group not found: {name}"))
+}
+
+pub struct Highlights {
+    groups: [Highlight; GROUP_COUNT],
+}
+
+impl Highlights {
+    pub fn new() -> Highlights {
+        let mut groups = [Highlight::default(); GROUP_COUNT];
+        for (_, group) in GROUPS {
+            groups[group as usize] = default_highlight(group);
+        }
+        Highlights { groups }
+    }
+
+    // the group's own colors or those of the group it links to
+    pub fn get(&self, group: Group) -> Highlight {
+        let mut highlight = self.groups[group as usize];
+        for _ in 0..GROUP_COUNT {
+            match highlight.link {
+                Some(link) => highlight = self.groups[link as usize],
+                None => break,
+            }
+        }
+        highlight
+    }
+
+    pub fn fg(&self, group: Group) -> V4 {
+        self.get(group)
+            .fg
+            .or(self.groups[Group::Normal as usize].fg)
+            .unwrap_or(V4::rgb(1.0, 1.0, 1.0))
+    }
+
+    pub fn bg(&self, group: Group) -> V4 {
+        self.get(group)
+            .bg
+            .or(self.groups[Group::Normal as usize].bg)
+            .unwrap_or_default()
+    }
+
+    // the group as listed by :highlight
+    fn show(&self, group: Group) -> String {
+        let highlight = self.groups[group as usize];
+        let attributes = match highlight.link {
+            Some(link) => format!("links to {}", link.name()),
+            None => {
+                let mut attributes = Vec::new();
+                if let Some(fg) = highlight.fg {
+                    attributes.push(format!("guifg={}", format_color(fg)));
+                }
+                if let Some(bg) = highlight.bg {
+                    attributes.push(format!("guibg={}", format_color(bg)));
+                }
+                if attributes.is_empty() {
+                    "cleared".to_string()
+                } else {
+                    attributes.join(" ")
+                }
+            }
+        };
+        format!("{:<14} {attributes}", group.name())
+    }
+
+    // :highlight lists all groups and :highlight {group} one of them,
+    // :highlight clear [{group}], :highlight link {from} {to} and
+    // :highlight {group} guifg={color} guibg={color} change them;
+    // returns the groups to show
+    pub fn set(&mut self, args: &str) -> Result<String, String> {
+        let args = args.split_whitespace().collect::<Vec<_>>();
+
+        match args.as_slice() {
+            [] => Ok(GROUPS
+                .iter()
+                .map(|(_, group)| self.show(*group))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ["clear"] => {
+                *self = Highlights::new();
+                Ok(String::new())
+            }
+            ["clear", group] => {
+                self.groups[find_group(group)? as usize] = Highlight::default();
+                Ok(String::new())
+            }
+            ["link", from, to] | ["default", "link", from, to] => {
+                let from = find_group(from)?;
+                let link = match *to {
+                    "NONE" => None,
+                    to => Some(find_group(to)?),
+                };
+                self.groups[from as usize].link = link;
+                Ok(String::new())
+            }
+            ["link", ..] | ["default", "link", ..] => Err(format!(
+                "E412: Not enough arguments: \":highlight {}\"",
+                args.join(" ")
+            )),
+            [group] => Ok(self.show(find_group(group)?)),
+            [group, attributes @ ..] => {
+                let group = find_group(group)?;
+                let mut highlight = self.groups[group as usize];
+                highlight.link = None;
+
+                for attribute in attributes {
+                    let (key, value) = attribute
+                        .split_once('=')
+                        .ok_or_else(|| format!("E416: Missing equal sign: {attribute}"))?;
+                    let color = match key {
+                        "guifg" => &mut highlight.fg,
+                        "guibg" => &mut highlight.bg,
+                        _ => return Err(format!("E423: Illegal argument: {key}")),
+                    };
+                    *color = match value {
+                        "NONE" => None,
+                        value => Some(
+                            parse_color(value)
+                                .ok_or_else(|| format!("E254: Cannot allocate color {value}"))?,
+                        ),
+                    };
+                }
+                self.groups[group as usize] = highlight;
+                Ok(String::new())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builtin_scheme, format_color, parse_color, Group, Highlights};
+    use crate::render::V4;
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff0000"), Some(V4::rgb(1.0, 0.0, 0.0)));
+        assert_eq!(parse_color("#00000000"), Some(V4::rgba(0.0, 0.0, 0.0, 0.0)));
+        assert_eq!(parse_color("ff0000"), None);
+        assert_eq!(parse_color("#ff00zz"), None);
+        assert_eq!(format_color(V4::rgba(1.0, 1.0, 1.0, 0.5)), "#ffffff80");
+    }
+
+    #[test]
+    fn set_groups() {
+        let mut h = Highlights::new();
+        assert_eq!(h.fg(Group::Keyword), h.fg(Group::Statement));
+        assert_eq!(h.bg(Group::Comment), h.bg(Group::Normal));
+
+        h.set("Statement guifg=#ff0000").unwrap();
+        assert_eq!(h.fg(Group::Keyword), V4::rgb(1.0, 0.0, 0.0));
+        assert_eq!(
+            h.set("keyword"),
+            Ok("Keyword        links to Statement".to_string())
+        );
+
+        h.set("Keyword guibg=#0000ff").unwrap();
+        assert_eq!(h.fg(Group::Keyword), h.fg(Group::Normal));
+        assert_eq!(h.set("clear Keyword"), Ok(String::new()));
+        assert_eq!(h.set("Keyword"), Ok("Keyword        cleared".to_string()));
+
+        assert_eq!(
+            h.set("Foo guifg=#000000"),
+            Err("E411: Highlight group not found: Foo".to_string())
+        );
+        assert_eq!(
+            h.set("Normal guifg=red"),
+            Err("E254: Cannot allocate color red".to_string())
+        );
+        assert_eq!(
+            h.set("Normal gui=bold"),
+            Err("E423: Illegal argument: gui".to_string())
+        );
+
+        h.set("clear").unwrap();
+        assert_eq!(h.set("").unwrap().lines().count(), Group::names().count());
+    }
+
+    #[test]
+    fn builtin_schemes_apply() {
+        for name in ["default", "desert", "morning"] {
+            let mut h = Highlights::new();
+            for line in builtin_scheme(name).unwrap().lines() {
+                if let Some(args) = line.strip_prefix("hi ") {
+                    assert_eq!(h.set(args), Ok(String::new()), "{name}: {line}");
+                }
+            }
+        }
+    }
+}
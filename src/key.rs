@@ -0,0 +1,137 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Escape,
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    ShiftTab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+const NAMED_KEYS: [(&str, Key); 15] = [
+    ("esc", Key::Escape),
+    ("cr", Key::Enter),
+    ("enter", Key::Enter),
+    ("bs", Key::Backspace),
+    ("del", Key::Delete),
+    ("tab", Key::Tab),
+    ("s-tab", Key::ShiftTab),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("lt", Key::Char('<')),
+    ("space", Key::Char(' ')),
+];
+
+impl Key {
+    // parse a vim-like key sequence, e.g. "dwihello<Esc>:q<CR>"
+    pub fn parse(keys: &str) -> Vec<Key> {
+        let mut result = Vec::new();
+        let mut rest = keys;
+
+        while let Some(c) = rest.chars().next() {
+            if c == '<' {
+                if let Some((key, len)) = rest
+                    .find('>')
+                    .and_then(|end| Key::from_name(&rest[1..end]).map(|key| (key, end + 1)))
+                {
+                    result.push(key);
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            result.push(Key::Char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+
+        result
+    }
+
+    fn from_name(name: &str) -> Option<Key> {
+        let lower = name.to_ascii_lowercase();
+
+        if let Some(c) = lower.strip_prefix("c-") {
+            let mut chars = c.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(Key::Ctrl(c)),
+                _ => None,
+            };
+        }
+
+        NAMED_KEYS
+            .iter()
+            .find(|(key_name, _)| *key_name == lower)
+            .map(|(_, key)| *key)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char('<') => write!(f, "<lt>"),
+            Key::Char(c) => write!(f, "{c}"),
+            Key::Ctrl(c) => write!(f, "<C-{c}>"),
+            Key::Escape => write!(f, "<Esc>"),
+            Key::Enter => write!(f, "<CR>"),
+            Key::Backspace => write!(f, "<BS>"),
+            Key::Delete => write!(f, "<Del>"),
+            Key::Tab => write!(f, "<Tab>"),
+            Key::ShiftTab => write!(f, "<S-Tab>"),
+            Key::Left => write!(f, "<Left>"),
+            Key::Right => write!(f, "<Right>"),
+            Key::Up => write!(f, "<Up>"),
+            Key::Down => write!(f, "<Down>"),
+            Key::Home => write!(f, "<Home>"),
+            Key::End => write!(f, "<End>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Key;
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(
+            Key::parse("d<Esc><c-w>x<CR><lt><foo>"),
+            vec![
+                Key::Char('d'),
+                Key::Escape,
+                Key::Ctrl('w'),
+                Key::Char('x'),
+                Key::Enter,
+                Key::Char('<'),
+                Key::Char('<'),
+                Key::Char('f'),
+                Key::Char('o'),
+                Key::Char('o'),
+                Key::Char('>'),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_notation() {
+        let keys = "3dw<C-r>a<lt><Esc><S-Tab>";
+        let notation = Key::parse(keys)
+            .iter()
+            .map(|key| key.to_string())
+            .collect::<String>();
+
+        assert_eq!(notation, keys);
+    }
+}
@@ -0,0 +1,30 @@
+#![allow(clippy::new_without_default)]
+
+pub mod args;
+pub mod buffer;
+pub mod camera;
+pub mod command;
+pub mod completion;
+pub mod cooldown;
+pub mod cursor;
+pub mod editor;
+pub mod errors;
+pub mod excmd;
+pub mod font;
+pub mod frame;
+#[cfg(feature = "gui")]
+pub mod gl;
+pub mod harness;
+pub mod highlight;
+pub mod key;
+pub mod mapping;
+pub mod message;
+pub mod options;
+pub mod render;
+pub mod statusline;
+pub mod syntax;
+pub mod text;
+#[cfg(feature = "tree-sitter")]
+pub mod tree;
+pub mod undo;
+pub mod window;
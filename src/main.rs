@@ -1,32 +1,25 @@
 use beryllium::error::SdlError;
 use beryllium::init::InitFlags;
 use beryllium::{events, video, Sdl};
+use fermium::keycode::*;
 use fermium::timer::SDL_Delay;
+use fermium::video::{
+    SDL_DisplayMode, SDL_GL_GetCurrentWindow, SDL_GetCurrentDisplayMode, SDL_GetWindowDisplayIndex,
+};
 use gl33::{
-    global_loader::*, GL_BLEND, GL_COLOR_BUFFER_BIT, GL_MULTISAMPLE, GL_ONE_MINUS_SRC_ALPHA,
+    global_loader::*, GL_BLEND, GL_MULTISAMPLE, GL_ONE_MINUS_SRC_ALPHA, GL_SCISSOR_TEST,
     GL_SRC_ALPHA,
 };
 
-use crate::gl::GL;
-
-use self::camera::Camera;
-use self::cursor::{Cursor, CURSOR_OFFSET};
-use self::editor::{Editor, Mode};
-use self::errors::EdiError;
-use self::excmd::ExCmdResult;
-use self::font::{FontAtlas, FONT_SIZE};
-use self::render::{DELTA_TIME, DELTA_TIME_MS, V2, V4};
-
-mod camera;
-mod command;
-mod cooldown;
-mod cursor;
-mod editor;
-mod errors;
-mod excmd;
-mod font;
-mod gl;
-mod render;
+use edi::args::Args;
+use edi::editor::{Editor, Mode};
+use edi::errors::EdiError;
+use edi::excmd::ExCmdResult;
+use edi::font::FontAtlas;
+use edi::frame::{render_frame, text_columns, Views};
+use edi::gl::{GlBackend, GL};
+use edi::key::Key;
+use edi::render::{Renderer, DELTA_TIME_MS};
 
 fn init_sdl() -> Result<Sdl, EdiError> {
     let sdl = Sdl::init(InitFlags::VIDEO | InitFlags::EVENTS);
@@ -48,12 +41,60 @@ fn sdl_error(err: SdlError) -> EdiError {
     EdiError::SdlError(format!("{:?}", err))
 }
 
+fn sdl_key(keycode: SDL_Keycode, modifiers: SDL_Keymod) -> Option<Key> {
+    let ctrl = modifiers.0 & KMOD_CTRL.0 != 0;
+    let shift = modifiers.0 & KMOD_SHIFT.0 != 0;
+
+    match keycode {
+        SDLK_ESCAPE => Some(Key::Escape),
+        SDLK_RETURN => Some(Key::Enter),
+        SDLK_BACKSPACE => Some(Key::Backspace),
+        SDLK_DELETE => Some(Key::Delete),
+        SDLK_TAB if shift => Some(Key::ShiftTab),
+        SDLK_TAB => Some(Key::Tab),
+        SDLK_LEFT => Some(Key::Left),
+        SDLK_RIGHT => Some(Key::Right),
+        SDLK_UP => Some(Key::Up),
+        SDLK_DOWN => Some(Key::Down),
+        SDLK_HOME => Some(Key::Home),
+        SDLK_END => Some(Key::End),
+        SDLK_6 if ctrl => Some(Key::Ctrl('^')),
+        // zooming with or without shift and on the keypad
+        SDLK_EQUALS | SDLK_PLUS | SDLK_KP_PLUS if ctrl => Some(Key::Ctrl('+')),
+        SDLK_KP_MINUS if ctrl => Some(Key::Ctrl('-')),
+        SDLK_KP_0 if ctrl => Some(Key::Ctrl('0')),
+        // printable keys arrive as text input unless combined with ctrl
+        _ if ctrl => char::from_u32(keycode.0 as u32)
+            .filter(|c| c.is_ascii_graphic())
+            .map(Key::Ctrl),
+        _ => None,
+    }
+}
+
+// milliseconds of a frame at the refresh rate of the display showing the
+// window, the default frame rate if it is unknown
+fn frame_time() -> u32 {
+    let mut mode = SDL_DisplayMode {
+        format: 0,
+        w: 0,
+        h: 0,
+        refresh_rate: 0,
+        driverdata: std::ptr::null_mut(),
+    };
+    let found = unsafe {
+        let display = SDL_GetWindowDisplayIndex(SDL_GL_GetCurrentWindow());
+        display >= 0 && SDL_GetCurrentDisplayMode(display, &mut mode) == 0
+    };
+    match mode.refresh_rate {
+        rate if found && rate > 0 => 1000 / rate as u32,
+        _ => DELTA_TIME_MS,
+    }
+}
+
 fn run() -> Result<(), EdiError> {
-    let mut editor = if std::env::args().len() > 1 {
-        Editor::from_file(&std::env::args().collect::<Vec<_>>()[1])
-    } else {
-        Ok(Editor::new())
-    }?;
+    let args = Args::parse(std::env::args().skip(1))?;
+    let mut editor = Editor::from_args(&args)?;
+    editor.source_config();
 
     let mut success = true;
     let sdl = init_sdl()?;
@@ -82,11 +123,11 @@ fn run() -> Result<(), EdiError> {
 
         glEnable(GL_BLEND);
         glEnable(GL_MULTISAMPLE);
+        glEnable(GL_SCISSOR_TEST);
         glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
     }
 
     let mut gl = GL::new();
-    let mut renderer = render::Renderer::new();
 
     let text_shader = gl.create_camera_program(&camera_vert_glsl, &text_frag_glsl)?;
     let color_shader = gl.create_camera_program(&camera_vert_glsl, &color_frag_glsl)?;
@@ -94,42 +135,42 @@ fn run() -> Result<(), EdiError> {
     let ui_text_shader = gl.create_ui_program(&ui_vert_glsl, &text_frag_glsl)?;
     let ui_color_shader = gl.create_ui_program(&ui_vert_glsl, &color_frag_glsl)?;
 
-    let font_atlas = FontAtlas::new("Iosevka")?;
+    let mut renderer = Renderer::new(GlBackend::new(
+        text_shader,
+        color_shader,
+        ui_text_shader,
+        ui_color_shader,
+    ));
 
-    let mut camera = Camera::new();
-    let mut cursor = Cursor::new(V4::rgba(1.0, 1.0, 1.0, 0.5));
+    let mut font_name = editor.options().guifont.clone();
+    let mut font_atlas = FontAtlas::new(&font_name)?;
+    renderer.upload_atlas(&font_atlas);
 
-    let cursor_size = V2 {
-        x: font_atlas.glyph('?').ax,
-        y: -FONT_SIZE,
-    };
+    let mut views = Views::new();
+
+    let mut last_frame = sdl.get_ticks();
+    // the refresh rate is only looked up again once the window may have
+    // moved to another display
+    let mut frame_ms = frame_time();
 
     'main_loop: loop {
+        // animations and key timeouts advance by the time the last frame
+        // actually took
         let start = sdl.get_ticks();
+        let delta = start.wrapping_sub(last_frame) as f32;
+        last_frame = start;
 
         while let Some((event, _ts)) = sdl.poll_events() {
-            match event {
+            let keys = match event {
                 events::Event::Quit => break 'main_loop,
-                events::Event::TextInput {
-                    win_id: _,
-                    text: input,
-                } if editor.mode == Mode::Normal => {
-                    if editor.handle_normal(&input) {
-                        cursor.active();
-                    }
+                events::Event::WindowMoved { .. }
+                | events::Event::DisplayConnected { .. }
+                | events::Event::DisplayDisconnected { .. } => {
+                    frame_ms = frame_time();
+                    Vec::new()
                 }
-                events::Event::TextInput {
-                    win_id: _,
-                    text: input,
-                } if editor.mode == Mode::Insert => {
-                    editor.insert(&input);
-                    cursor.active();
-                }
-                events::Event::TextInput {
-                    win_id: _,
-                    text: input,
-                } if editor.mode == Mode::Command => {
-                    editor.handle_command(&input);
+                events::Event::TextInput { win_id: _, text } => {
+                    text.chars().map(Key::Char).collect()
                 }
                 events::Event::Key {
                     win_id: _,
@@ -137,130 +178,61 @@ fn run() -> Result<(), EdiError> {
                     repeat: _,
                     scancode: _,
                     keycode,
-                    modifiers: _,
-                } => match keycode {
-                    fermium::keycode::SDLK_ESCAPE if editor.mode == Mode::Insert => {
-                        editor.exit_insert();
-                        cursor.active();
-                    }
-                    fermium::keycode::SDLK_ESCAPE if editor.mode == Mode::Command => {
-                        editor.exit_command();
-                    }
-                    fermium::keycode::SDLK_BACKSPACE if editor.mode == Mode::Insert => {
-                        editor.delete();
-                        cursor.active();
-                    }
-                    fermium::keycode::SDLK_BACKSPACE if editor.mode == Mode::Command => {
-                        editor.command_delete_char();
-                    }
-                    fermium::keycode::SDLK_RETURN if editor.mode == Mode::Insert => {
-                        editor.new_line();
-                        cursor.active();
-                    }
-                    fermium::keycode::SDLK_RETURN if editor.mode == Mode::Command => {
-                        match editor.command_execute() {
-                            ExCmdResult::Quit(is_success) => {
-                                success = is_success;
-                                break 'main_loop;
-                            }
-                            _ => {}
-                        }
-                    }
-                    _ => (),
-                },
-                _ => (),
-            }
-        }
+                    modifiers,
+                } => sdl_key(keycode, modifiers).into_iter().collect(),
+                _ => Vec::new(),
+            };
 
-        cursor.update(DELTA_TIME);
-        camera.update(DELTA_TIME);
-        editor.update(DELTA_TIME);
+            for key in keys {
+                let result = editor.handle_key(key);
 
-        let (win_width, win_height) = win.get_window_size().into();
-        let resolution = (win_width, win_height).into();
-
-        unsafe {
-            // TODO: necessary all the time?
-            glViewport(0, 0, win_width, win_width);
-            glClearColor(0.1, 0.1, 0.1, 1.0);
-            glClear(GL_COLOR_BUFFER_BIT);
-        }
-
-        let mut max_line_length = 0.0f32;
-
-        // render text
-        {
-            text_shader.activate(&resolution, &camera);
-
-            let text_color = V4::rgb(1.0, 1.0, 0.1);
-
-            let mut y_offset = 0.0;
-
-            for line in editor.iter() {
-                let mut x_offset = 0.0;
-
-                for word in line {
-                    x_offset += renderer.render_text(
-                        &font_atlas,
-                        word,
-                        (x_offset, y_offset).into(),
-                        text_color,
-                        1.0,
-                    );
+                if editor.mode != Mode::Command {
+                    views.get(editor.current_window()).cursor.active();
                 }
 
-                y_offset -= FONT_SIZE;
-                max_line_length = max_line_length.max(x_offset);
+                if let Some(ExCmdResult::Quit(is_success)) = result {
+                    success = is_success;
+                    break 'main_loop;
+                }
             }
-            renderer.flush();
         }
 
-        // render cursor
-        {
-            let cursor_target = (editor.cursor() + (0.0, CURSOR_OFFSET).into()) * cursor_size;
-            cursor.move_to(cursor_target);
-
-            camera.target(cursor.pos, max_line_length, win_width as f32);
-
-            if cursor.visible() {
-                color_shader.activate(&resolution, &camera);
-                cursor.render(&mut renderer, editor.mode != Mode::Insert);
-                renderer.flush();
+        // 'guifont' is applied as soon as it changes, keeping the
+        // current font if the new one cannot be loaded
+        if editor.options().guifont != font_name {
+            font_name = editor.options().guifont.clone();
+            match FontAtlas::new(&font_name) {
+                Ok(atlas) => {
+                    font_atlas = atlas;
+                    renderer.upload_atlas(&font_atlas);
+                }
+                Err(err) => editor.error(format!("E596: Invalid font {font_name}: {err}")),
             }
         }
 
-        // render UI
-        {
-            ui_color_shader.activate(&resolution);
-
-            renderer.render_solid_rect(
-                (-resolution.x / 2.0, -resolution.y / 2.0).into(),
-                (resolution.x, 25.0).into(),
-                V4::rgb(0.5, 0.8, 0.5),
-            );
-            renderer.flush();
-
-            ui_text_shader.activate(&resolution);
+        views.update(delta);
+        if let Some(ExCmdResult::Quit(is_success)) = editor.update(delta) {
+            success = is_success;
+            break 'main_loop;
+        }
 
-            renderer.render_text(
-                &font_atlas,
-                editor.status_line(),
-                (-resolution.x / 2.0, -resolution.y / 2.0).into(),
-                V4::rgb(0.0, 0.0, 0.0),
-                0.1,
-            );
+        let (win_width, win_height) = win.get_window_size();
+        let resolution = (win_width, win_height).into();
 
-            renderer.flush();
+        for (id, align) in editor.take_aligns() {
+            views.get(id).camera.align(align);
         }
+        // gj and gk move by the rows the lines are wrapped in
+        editor.set_text_columns(text_columns(&editor, &font_atlas, resolution));
+        render_frame(&mut renderer, &font_atlas, &editor, &mut views, resolution);
 
         win.swap_window();
 
-        let finished = sdl.get_ticks();
-        let duration = finished - start;
+        let duration = sdl.get_ticks().wrapping_sub(start);
 
-        if duration < DELTA_TIME_MS {
+        if duration < frame_ms {
             unsafe {
-                SDL_Delay(DELTA_TIME_MS - duration);
+                SDL_Delay(frame_ms - duration);
             }
         }
     }
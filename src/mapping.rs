@@ -0,0 +1,159 @@
+use crate::command::{match_input, InputMatch};
+use crate::key::Key;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapMode {
+    Normal,
+    Insert,
+    Command,
+}
+
+impl MapMode {
+    fn prefix(&self) -> char {
+        match self {
+            MapMode::Normal => 'n',
+            MapMode::Insert => 'i',
+            MapMode::Command => 'c',
+        }
+    }
+}
+
+// both sides are kept in normalized key notation, e.g. "<C-w>j"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub mode: MapMode,
+    pub lhs: String,
+    pub rhs: String,
+    // the keys of `rhs` are not mapped again
+    pub noremap: bool,
+}
+
+pub struct Mappings {
+    mappings: Vec<Mapping>,
+}
+
+// key notation with <leader> replaced by the given keys
+pub fn normalize(keys: &str, leader: &str) -> String {
+    let mut expanded = String::with_capacity(keys.len());
+    let mut rest = keys;
+
+    while let Some(idx) = rest.find('<') {
+        expanded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        match rest.get(..8) {
+            Some(name) if name.eq_ignore_ascii_case("<leader>") => {
+                expanded.push_str(leader);
+                rest = &rest[8..];
+            }
+            _ => {
+                expanded.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+
+    Key::parse(&expanded)
+        .iter()
+        .map(|key| key.to_string())
+        .collect()
+}
+
+impl Mappings {
+    pub fn new() -> Mappings {
+        Mappings {
+            mappings: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, mapping: Mapping) {
+        self.remove(mapping.mode, &mapping.lhs);
+        self.mappings.push(mapping);
+    }
+
+    pub fn remove(&mut self, mode: MapMode, lhs: &str) -> bool {
+        let len = self.mappings.len();
+        self.mappings
+            .retain(|mapping| mapping.mode != mode || mapping.lhs != lhs);
+        self.mappings.len() != len
+    }
+
+    pub fn lookup(&self, mode: MapMode, keys: &str) -> InputMatch<&Mapping> {
+        let mappings = self
+            .mappings
+            .iter()
+            .filter(|mapping| mapping.mode == mode)
+            .map(|mapping| (mapping.lhs.as_str(), mapping));
+        match_input(mappings, keys)
+    }
+
+    // mappings of the given modes starting with `prefix`, one per line
+    pub fn list(&self, modes: &[MapMode], prefix: &str) -> Vec<String> {
+        let mut mappings = self
+            .mappings
+            .iter()
+            .filter(|mapping| modes.contains(&mapping.mode) && mapping.lhs.starts_with(prefix))
+            .collect::<Vec<_>>();
+        mappings.sort_by(|a, b| a.lhs.cmp(&b.lhs));
+
+        mappings
+            .iter()
+            .map(|mapping| {
+                let noremap = if mapping.noremap { '*' } else { ' ' };
+                format!(
+                    "{}  {:<12} {noremap} {}",
+                    mapping.mode.prefix(),
+                    mapping.lhs,
+                    mapping.rhs
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, MapMode, Mapping, Mappings};
+    use crate::command::InputMatch;
+
+    fn mapping(lhs: &str, rhs: &str) -> Mapping {
+        Mapping {
+            mode: MapMode::Normal,
+            lhs: lhs.to_string(),
+            rhs: rhs.to_string(),
+            noremap: true,
+        }
+    }
+
+    #[test]
+    fn normalize_keys() {
+        assert_eq!(normalize("<Leader>w<c-W>j", ","), ",w<C-w>j");
+        assert_eq!(normalize("<leader><lt>", "<Space>"), " <lt>");
+        assert_eq!(normalize("<lea", "\\"), "<lt>lea");
+    }
+
+    #[test]
+    fn lookup_prefixes() {
+        let mut m = Mappings::new();
+        m.add(mapping("g", "x"));
+        m.add(mapping("gx", "dd"));
+        m.add(mapping("gx", "dw"));
+
+        assert!(matches!(
+            m.lookup(MapMode::Normal, "g"),
+            InputMatch::Partial(Some(_))
+        ));
+        assert!(matches!(m.lookup(MapMode::Normal, "gx"), InputMatch::Full(m) if m.rhs == "dw"));
+        assert!(matches!(m.lookup(MapMode::Insert, "g"), InputMatch::None));
+        assert_eq!(
+            m.list(&[MapMode::Normal], "gx"),
+            vec!["n  gx           * dw"]
+        );
+
+        assert!(m.remove(MapMode::Normal, "g"));
+        assert!(matches!(
+            m.lookup(MapMode::Normal, "g"),
+            InputMatch::Partial(None)
+        ));
+    }
+}
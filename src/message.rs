@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+// number of messages kept for :messages
+const MAX_HISTORY: usize = 200;
+
+pub const PRESS_ENTER: &str = "Press ENTER or type command to continue";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub kind: MessageKind,
+    pub text: String,
+}
+
+impl Message {
+    // multi-line messages stay until dismissed by the "Press ENTER" prompt
+    pub fn is_multiline(&self) -> bool {
+        self.text.contains('\n')
+    }
+}
+
+// the message shown in the command line area until the next key press
+// and the history of past messages
+pub struct Messages {
+    current: Option<Message>,
+    history: VecDeque<Message>,
+}
+
+impl Messages {
+    pub fn new() -> Messages {
+        Messages {
+            current: None,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<&Message> {
+        self.current.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    pub fn info(&mut self, text: String) {
+        self.record(MessageKind::Info, text);
+    }
+
+    pub fn error(&mut self, text: String) {
+        self.record(MessageKind::Error, text);
+    }
+
+    // shows command output like :ls without adding it to the history
+    pub fn print(&mut self, text: String) {
+        self.current = Some(Message {
+            kind: MessageKind::Info,
+            text,
+        });
+    }
+
+    fn record(&mut self, kind: MessageKind, text: String) {
+        let message = Message { kind, text };
+        if self.history.len() >= MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(message.clone());
+        self.current = Some(message);
+    }
+
+    pub fn history(&self) -> String {
+        self.history
+            .iter()
+            .map(|message| message.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageKind, Messages, MAX_HISTORY};
+
+    #[test]
+    fn history() {
+        let mut messages = Messages::new();
+        messages.error("E1: first".to_string());
+        assert_eq!(messages.current().unwrap().kind, MessageKind::Error);
+
+        messages.print("not recorded".to_string());
+        messages.info("second".to_string());
+        messages.clear();
+        assert!(messages.current().is_none());
+        assert_eq!(messages.history(), "E1: first\nsecond");
+
+        for idx in 0..MAX_HISTORY {
+            messages.info(idx.to_string());
+        }
+        assert!(messages.history().starts_with("0\n1\n"));
+    }
+}
@@ -0,0 +1,490 @@
+use std::path::PathBuf;
+
+use crate::buffer::Wrap;
+use crate::command::Timeout;
+
+pub struct Options {
+    pub number: bool,
+    pub relativenumber: bool,
+    pub cursorline: bool,
+    // comma separated screen columns to highlight
+    pub colorcolumn: String,
+    // whether to show blanks and line ends as given by 'listchars'
+    pub list: bool,
+    pub listchars: String,
+    // whether long lines continue in the following rows, breaking them
+    // before words with 'linebreak' and showing 'showbreak' in front of
+    // the continued rows
+    pub wrap: bool,
+    pub linebreak: bool,
+    pub showbreak: String,
+    pub statusline: String,
+    pub guifont: String,
+    // whether and how many milliseconds to wait for the next key of a
+    // command or mapping, and of a key code
+    pub timeout: bool,
+    pub timeoutlen: usize,
+    pub ttimeout: bool,
+    pub ttimeoutlen: usize,
+    // milliseconds the cursor is shown and hidden while blinking
+    pub blinkon: usize,
+    pub blinkoff: usize,
+    pub cameraspeed: usize,
+    // whether the camera and the cursor move smoothly or jump
+    pub animate: bool,
+    // lines and columns kept visible around the cursor
+    pub scrolloff: usize,
+    pub sidescrolloff: usize,
+    // fixed text size in percent of the largest automatic size, 0 fits
+    // the longest line into the window
+    pub zoom: usize,
+    // keys <leader> stands for in mappings
+    pub mapleader: String,
+}
+
+// (name, short name) of all options by type
+const BOOL_OPTIONS: [(&str, &str); 9] = [
+    ("animate", "anim"),
+    ("cursorline", "cul"),
+    ("linebreak", "lbr"),
+    ("list", "list"),
+    ("number", "nu"),
+    ("relativenumber", "rnu"),
+    ("timeout", "to"),
+    ("ttimeout", "ttimeout"),
+    ("wrap", "wrap"),
+];
+
+const NUMBER_OPTIONS: [(&str, &str); 8] = [
+    ("blinkoff", "bof"),
+    ("blinkon", "bon"),
+    ("cameraspeed", "cs"),
+    ("scrolloff", "so"),
+    ("sidescrolloff", "siso"),
+    ("timeoutlen", "tm"),
+    ("ttimeoutlen", "ttm"),
+    ("zoom", "zoom"),
+];
+
+const STRING_OPTIONS: [(&str, &str); 6] = [
+    ("colorcolumn", "cc"),
+    ("guifont", "gfn"),
+    ("listchars", "lcs"),
+    ("mapleader", "mapleader"),
+    ("showbreak", "sbr"),
+    ("statusline", "stl"),
+];
+
+pub const DEFAULT_STATUSLINE: &str =
+    " %{mode}  %f%m%r%=%S  %y %{encoding} [%{fileformat}]  %l:%c  %p%% ";
+
+// characters shown for blanks and line ends with 'list' set
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ListChars {
+    pub eol: Option<char>,
+    pub tab: Option<char>,
+    pub space: Option<char>,
+    // spaces and tabs at the end of a line
+    pub trail: Option<char>,
+}
+
+impl ListChars {
+    // "eol:$,tab:>-,space:.,trail:-", only the first character of a tab
+    // is used as a tab is a single column wide
+    fn parse(value: &str) -> Option<ListChars> {
+        let mut chars = ListChars::default();
+        for item in value.split(',').filter(|item| !item.is_empty()) {
+            let (name, value) = item.split_once(':')?;
+            let mut values = value.chars();
+            let first = values.next()?;
+            let count = values.count() + 1;
+            let (field, counts) = match name {
+                "eol" => (&mut chars.eol, 1..=1),
+                "tab" => (&mut chars.tab, 2..=3),
+                "space" => (&mut chars.space, 1..=1),
+                "trail" => (&mut chars.trail, 1..=1),
+                _ => return None,
+            };
+            if !counts.contains(&count) {
+                return None;
+            }
+            *field = Some(first);
+        }
+        Some(chars)
+    }
+}
+
+// 1-based columns of 'colorcolumn' like "80,100"
+fn parse_columns(value: &str) -> Option<Vec<usize>> {
+    value
+        .split(',')
+        .filter(|column| !column.is_empty())
+        .map(|column| {
+            let digits = column.bytes().all(|byte| byte.is_ascii_digit());
+            column.parse().ok().filter(|column| digits && *column > 0)
+        })
+        .collect()
+}
+
+fn long_name(name: &str) -> Option<&'static str> {
+    BOOL_OPTIONS
+        .iter()
+        .chain(NUMBER_OPTIONS.iter())
+        .chain(STRING_OPTIONS.iter())
+        .find(|(long, short)| *long == name || *short == name)
+        .map(|(long, _)| *long)
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            number: false,
+            relativenumber: false,
+            cursorline: false,
+            colorcolumn: String::new(),
+            list: false,
+            listchars: "eol:$".to_string(),
+            wrap: false,
+            linebreak: false,
+            showbreak: String::new(),
+            statusline: DEFAULT_STATUSLINE.to_string(),
+            guifont: "Iosevka".to_string(),
+            timeout: true,
+            timeoutlen: 500,
+            ttimeout: true,
+            ttimeoutlen: 100,
+            blinkon: 600,
+            blinkoff: 400,
+            cameraspeed: 5,
+            animate: true,
+            scrolloff: 5,
+            sidescrolloff: 0,
+            zoom: 0,
+            mapleader: "\\".to_string(),
+        }
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        BOOL_OPTIONS
+            .iter()
+            .chain(NUMBER_OPTIONS.iter())
+            .chain(STRING_OPTIONS.iter())
+            .map(|(name, _)| *name)
+    }
+
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match long_name(name)? {
+            "number" => Some(&mut self.number),
+            "relativenumber" => Some(&mut self.relativenumber),
+            "cursorline" => Some(&mut self.cursorline),
+            "list" => Some(&mut self.list),
+            "wrap" => Some(&mut self.wrap),
+            "linebreak" => Some(&mut self.linebreak),
+            "animate" => Some(&mut self.animate),
+            "timeout" => Some(&mut self.timeout),
+            "ttimeout" => Some(&mut self.ttimeout),
+            _ => None,
+        }
+    }
+
+    fn number(&mut self, name: &str) -> Option<&mut usize> {
+        match long_name(name)? {
+            "timeoutlen" => Some(&mut self.timeoutlen),
+            "ttimeoutlen" => Some(&mut self.ttimeoutlen),
+            "blinkon" => Some(&mut self.blinkon),
+            "blinkoff" => Some(&mut self.blinkoff),
+            "cameraspeed" => Some(&mut self.cameraspeed),
+            "scrolloff" => Some(&mut self.scrolloff),
+            "sidescrolloff" => Some(&mut self.sidescrolloff),
+            "zoom" => Some(&mut self.zoom),
+            _ => None,
+        }
+    }
+
+    fn string(&mut self, name: &str) -> Option<&mut String> {
+        match long_name(name)? {
+            "statusline" => Some(&mut self.statusline),
+            "guifont" => Some(&mut self.guifont),
+            "mapleader" => Some(&mut self.mapleader),
+            "colorcolumn" => Some(&mut self.colorcolumn),
+            "listchars" => Some(&mut self.listchars),
+            "showbreak" => Some(&mut self.showbreak),
+            _ => None,
+        }
+    }
+
+    // like vim key codes time out with 'timeout' set even if 'ttimeout' is
+    // not
+    pub fn key_timeout(&self) -> Timeout {
+        Timeout {
+            keys: self.timeout.then_some(self.timeoutlen as f32),
+            key_codes: (self.timeout || self.ttimeout).then_some(self.ttimeoutlen as f32),
+        }
+    }
+
+    // the option as shown by :set {option}?
+    fn show(&mut self, name: &str) -> Result<String, String> {
+        let name = long_name(name).ok_or_else(|| format!("E518: Unknown option: {name}"))?;
+
+        if let Some(flag) = self.flag(name) {
+            let prefix = if *flag { "  " } else { "no" };
+            return Ok(format!("{prefix}{name}"));
+        }
+        if let Some(number) = self.number(name) {
+            return Ok(format!("  {name}={number}"));
+        }
+        let value = self.string(name).map(|value| value.clone());
+        Ok(format!("  {name}={}", value.unwrap_or_default()))
+    }
+
+    fn reset(&mut self, name: &str) -> Result<(), String> {
+        let mut default = Options::new();
+
+        if let (Some(flag), Some(value)) = (self.flag(name), default.flag(name)) {
+            *flag = *value;
+        } else if let (Some(number), Some(value)) = (self.number(name), default.number(name)) {
+            *number = *value;
+        } else if let (Some(string), Some(value)) = (self.string(name), default.string(name)) {
+            *string = std::mem::take(value);
+        } else {
+            return Err(format!("E518: Unknown option: {name}"));
+        }
+        Ok(())
+    }
+
+    // :set {option}, :set no{option}, :set inv{option}, :set {option}?,
+    // :set {option}& and :set {option}={value} with spaces in values
+    // escaped by a backslash; returns the options to show, all of them
+    // without arguments
+    pub fn set(&mut self, args: &str) -> Result<String, String> {
+        let mut shown = Vec::new();
+        if args.trim().is_empty() {
+            for name in Options::names() {
+                shown.push(self.show(name)?);
+            }
+        }
+
+        for arg in split_args(args) {
+            if let Some((name, value)) = arg.split_once('=') {
+                if let Some(number) = self.number(name) {
+                    *number = value
+                        .parse()
+                        .map_err(|_| format!("E521: Number required after =: {arg}"))?;
+                } else if let Some(string) = self.string(name) {
+                    let valid = match long_name(name) {
+                        Some("colorcolumn") => parse_columns(value).is_some(),
+                        Some("listchars") => ListChars::parse(value).is_some(),
+                        _ => true,
+                    };
+                    if !valid {
+                        return Err(format!("E474: Invalid argument: {arg}"));
+                    }
+                    *string = value.to_string();
+                } else if self.flag(name).is_some() {
+                    return Err(format!("E474: Invalid argument: {arg}"));
+                } else {
+                    return Err(format!("E518: Unknown option: {name}"));
+                }
+                continue;
+            }
+
+            if let Some(name) = arg.strip_suffix('?') {
+                shown.push(self.show(name)?);
+                continue;
+            }
+            if let Some(name) = arg.strip_suffix('&') {
+                self.reset(name)?;
+                continue;
+            }
+
+            let arg = arg.as_str();
+            if self.flag(arg).is_none() && long_name(arg).is_some() {
+                shown.push(self.show(arg)?);
+                continue;
+            }
+
+            let (name, value) = if self.flag(arg).is_some() {
+                (arg, Some(true))
+            } else if let Some(name) = arg.strip_prefix("no") {
+                (name, Some(false))
+            } else if let Some(name) = arg.strip_prefix("inv") {
+                (name, None)
+            } else {
+                (arg, Some(true))
+            };
+
+            let flag = self
+                .flag(name)
+                .ok_or_else(|| format!("E518: Unknown option: {name}"))?;
+            *flag = value.unwrap_or(!*flag);
+        }
+        Ok(shown.join("\n"))
+    }
+
+    // 0-based columns to highlight
+    pub fn color_columns(&self) -> Vec<usize> {
+        parse_columns(&self.colorcolumn)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|column| column - 1)
+            .collect()
+    }
+
+    pub fn list_chars(&self) -> ListChars {
+        ListChars::parse(&self.listchars).unwrap_or_default()
+    }
+
+    // layout of long lines in a text area `width` columns wide
+    pub fn wrap(&self, width: usize) -> Option<Wrap> {
+        self.wrap.then(|| Wrap {
+            width,
+            linebreak: self.linebreak,
+            prefix: self.showbreak.chars().count(),
+        })
+    }
+
+    // columns of the line number gutter including the trailing space
+    pub fn gutter_width(&self, line_count: usize) -> usize {
+        if !self.number && !self.relativenumber {
+            return 0;
+        }
+        line_count.max(1).to_string().len().max(3) + 1
+    }
+}
+
+// $XDG_CONFIG_HOME/edi/edirc, defaulting to ~/.config/edi/edirc
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("edirc"))
+}
+
+// $XDG_CONFIG_HOME/edi, defaulting to ~/.config/edi
+pub fn config_dir() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("edi"))
+}
+
+fn split_args(args: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut chars = args.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    result.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        result.push(current);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListChars, Options};
+
+    #[test]
+    fn set_flags() {
+        let mut options = Options::new();
+        assert_eq!(options.gutter_width(100), 0);
+
+        assert!(options.set("nu invrnu").is_ok());
+        assert!(options.number && options.relativenumber);
+        assert!(options.set("norelativenumber").is_ok());
+        assert!(!options.relativenumber);
+
+        assert_eq!(options.gutter_width(12), 4);
+        assert_eq!(options.gutter_width(12345), 6);
+        assert!(options.set(r"stl=%f\ %m").is_ok());
+        assert_eq!(options.statusline, "%f %m");
+        assert!(options.set("nu=1").is_err());
+        assert_eq!(
+            options.set("nofoo"),
+            Err("E518: Unknown option: foo".to_string())
+        );
+    }
+
+    #[test]
+    fn set_values() {
+        let mut options = Options::new();
+
+        assert_eq!(options.set("tm=200 gfn=Hack"), Ok(String::new()));
+        assert_eq!(
+            options.set("timeoutlen? nu? guifont"),
+            Ok("  timeoutlen=200\nnonumber\n  guifont=Hack".to_string())
+        );
+        assert_eq!(
+            options.set("tm=x"),
+            Err("E521: Number required after =: tm=x".to_string())
+        );
+
+        assert!(options.set("nu tm& gfn&").is_ok());
+        assert!(options.number);
+        assert_eq!(options.timeoutlen, 500);
+        assert_eq!(options.guifont, "Iosevka");
+        assert_eq!(options.set("").unwrap().lines().count(), 23);
+
+        assert!(options.set("noanim").is_ok());
+        assert!(!options.animate);
+        assert_eq!(
+            options.set("nosms"),
+            Err("E518: Unknown option: sms".to_string())
+        );
+    }
+
+    #[test]
+    fn key_timeouts() {
+        let mut options = Options::new();
+        assert_eq!(options.key_timeout().wait_for("d"), Some(500.0));
+        assert_eq!(options.key_timeout().wait_for("<Esc>j"), Some(100.0));
+
+        options.set("noto").unwrap();
+        assert_eq!(options.key_timeout().wait_for("d"), None);
+        assert_eq!(options.key_timeout().wait_for("<Esc>j"), Some(100.0));
+
+        options.set("nottimeout").unwrap();
+        assert_eq!(options.key_timeout().wait_for("<Esc>j"), None);
+    }
+
+    #[test]
+    fn columns_and_list_chars() {
+        let mut options = Options::new();
+        assert_eq!(options.color_columns(), vec![]);
+        assert_eq!(
+            options.list_chars(),
+            ListChars {
+                eol: Some('$'),
+                ..ListChars::default()
+            }
+        );
+
+        assert!(options.set("cc=80,100 lcs=tab:>-,trail:~,space:.").is_ok());
+        assert_eq!(options.color_columns(), vec![79, 99]);
+        assert_eq!(
+            options.list_chars(),
+            ListChars {
+                eol: None,
+                tab: Some('>'),
+                space: Some('.'),
+                trail: Some('~'),
+            }
+        );
+
+        for arg in ["cc=0", "cc=+1", "lcs=eol:", "lcs=tab:>", "lcs=foo:x"] {
+            assert_eq!(
+                options.set(arg),
+                Err(format!("E474: Invalid argument: {arg}"))
+            );
+        }
+        assert_eq!(options.colorcolumn, "80,100");
+    }
+}
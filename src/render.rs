@@ -1,16 +1,16 @@
-use std::mem::size_of;
 use std::ops::{Add, Div, Mul, Sub};
 
-use bytemuck::offset_of;
-use gl33::{global_loader::*, GL_ARRAY_BUFFER, GL_DYNAMIC_DRAW, GL_FALSE, GL_FLOAT, GL_TRIANGLES};
-
+use crate::camera::Camera;
 use crate::font::FontAtlas;
 
+// frame rate if the refresh rate of the display is unknown, the speed of
+// animations is given per frame at this rate
 pub const FPS: u32 = 60;
 pub const DELTA_TIME_MS: u32 = 1000 / FPS;
 pub const DELTA_TIME: f32 = 1000.0 / (FPS as f32);
 
-const MAX_VERTICES: usize = 10 * 640 * 1000;
+// multiple of 3 so that an automatic flush never splits a triangle
+pub const MAX_VERTICES: usize = 6 * 64 * 1024;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct V2 {
@@ -91,7 +91,30 @@ impl Div<f32> for V2 {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub pos: V2,
+    pub size: V2,
+}
+
+impl Rect {
+    pub fn new<P: Into<V2>, S: Into<V2>>(pos: P, size: S) -> Rect {
+        Rect {
+            pos: pos.into(),
+            size: size.into(),
+        }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.pos.x + self.size.x
+    }
+
+    pub fn top(&self) -> f32 {
+        self.pos.y + self.size.y
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct V4 {
     pub x: f32,
     pub y: f32,
@@ -114,7 +137,36 @@ impl V4 {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+// color of the character at a byte offset of a rendered text
+pub trait TextColor {
+    fn at(&self, idx: usize) -> V4;
+}
+
+impl TextColor for V4 {
+    fn at(&self, _idx: usize) -> V4 {
+        *self
+    }
+}
+
+// colors of a line, each used from its byte offset on, for the part of
+// the line starting at `offset`
+pub struct LineColors<'a> {
+    pub colors: &'a [(usize, V4)],
+    pub offset: usize,
+}
+
+impl TextColor for LineColors<'_> {
+    fn at(&self, idx: usize) -> V4 {
+        let count = self
+            .colors
+            .partition_point(|(start, _)| *start <= self.offset + idx);
+        self.colors[..count]
+            .last()
+            .map_or(V4::default(), |(_, color)| *color)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Vertex {
     pub pos: V2,
     pub color: V4,
@@ -127,84 +179,137 @@ impl Vertex {
     }
 }
 
-pub struct Renderer {
-    vao: u32,
-    vbo: u32,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shader {
+    Text,
+    Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Camera { pos: V2, scale: f32 },
+    Screen,
+}
+
+impl From<&Camera> for Projection {
+    fn from(camera: &Camera) -> Self {
+        Projection::Camera {
+            pos: camera.pos,
+            scale: camera.scale,
+        }
+    }
+}
+
+pub trait Backend {
+    fn upload_atlas(&mut self, atlas: &FontAtlas);
+    fn clear(&mut self, resolution: V2, color: V4);
+    // restricts drawing to the given area of the window, in pixels
+    fn viewport(&mut self, area: Rect);
+    fn activate(&mut self, shader: Shader, projection: Projection, resolution: V2);
+    fn draw(&mut self, vertices: &[Vertex]);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub shader: Shader,
+    pub projection: Projection,
+    pub viewport: Rect,
     pub vertices: Vec<Vertex>,
 }
 
-impl Renderer {
-    pub fn new() -> Renderer {
-        let mut renderer = Renderer {
-            vao: 0,
-            vbo: 0,
-            vertices: Vec::with_capacity(MAX_VERTICES),
-        };
-
-        unsafe {
-            glGenVertexArrays(1, &mut renderer.vao);
-            glBindVertexArray(renderer.vao);
-
-            glGenBuffers(1, &mut renderer.vbo);
-            glBindBuffer(GL_ARRAY_BUFFER, renderer.vbo);
-            glBufferData(
-                GL_ARRAY_BUFFER,
-                (size_of::<Vertex>() * MAX_VERTICES) as isize,
-                renderer.vertices.as_ptr().cast(),
-                GL_DYNAMIC_DRAW,
-            );
+// records every draw call instead of talking to the GPU
+pub struct HeadlessBackend {
+    pub batches: Vec<Batch>,
+    shader: Shader,
+    projection: Projection,
+    viewport: Rect,
+}
 
-            // position
-            glEnableVertexAttribArray(0);
-            glVertexAttribPointer(
-                0, // location 0
-                2, // 2 values (V2)
-                GL_FLOAT,
-                GL_FALSE.0 as u8,
-                size_of::<Vertex>() as i32,
-                offset_of!(Vertex, pos) as *const _,
-            );
+impl HeadlessBackend {
+    pub fn new() -> HeadlessBackend {
+        HeadlessBackend {
+            batches: Vec::new(),
+            shader: Shader::Color,
+            projection: Projection::Screen,
+            viewport: Rect::default(),
+        }
+    }
+}
 
-            // color
-            glEnableVertexAttribArray(1);
-            glVertexAttribPointer(
-                1, // location 1
-                4, // 4 values (V4)
-                GL_FLOAT,
-                GL_FALSE.0 as u8,
-                size_of::<Vertex>() as i32,
-                offset_of!(Vertex, color) as *const _,
-            );
+impl Backend for HeadlessBackend {
+    fn upload_atlas(&mut self, _atlas: &FontAtlas) {}
 
-            // uv
-            glEnableVertexAttribArray(2);
-            glVertexAttribPointer(
-                2, // location 2
-                2, // 2 values (V2)
-                GL_FLOAT,
-                GL_FALSE.0 as u8,
-                size_of::<Vertex>() as i32,
-                offset_of!(Vertex, uv) as *const _,
-            );
+    fn clear(&mut self, resolution: V2, _color: V4) {
+        self.batches.clear();
+        self.viewport = Rect::new((0.0, 0.0), resolution);
+    }
+
+    fn viewport(&mut self, area: Rect) {
+        self.viewport = area;
+    }
+
+    fn activate(&mut self, shader: Shader, projection: Projection, _resolution: V2) {
+        self.shader = shader;
+        self.projection = projection;
+    }
+
+    fn draw(&mut self, vertices: &[Vertex]) {
+        if !vertices.is_empty() {
+            self.batches.push(Batch {
+                shader: self.shader,
+                projection: self.projection,
+                viewport: self.viewport,
+                vertices: vertices.to_vec(),
+            });
         }
+    }
+}
 
-        renderer
+pub struct Renderer<B: Backend> {
+    backend: B,
+    pub vertices: Vec<Vertex>,
+}
+
+impl<B: Backend> Renderer<B> {
+    pub fn new(backend: B) -> Renderer<B> {
+        Renderer {
+            backend,
+            vertices: Vec::with_capacity(MAX_VERTICES),
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn upload_atlas(&mut self, atlas: &FontAtlas) {
+        self.backend.upload_atlas(atlas);
+    }
+
+    pub fn clear(&mut self, resolution: V2, color: V4) {
+        self.vertices.clear();
+        self.backend.clear(resolution, color);
+    }
+
+    pub fn viewport(&mut self, area: Rect) {
+        self.flush();
+        self.backend.viewport(area);
+    }
+
+    pub fn activate<P: Into<Projection>>(&mut self, shader: Shader, projection: P, resolution: V2) {
+        self.flush();
+        self.backend.activate(shader, projection.into(), resolution);
     }
 
     pub fn flush(&mut self) {
-        unsafe {
-            glBufferSubData(
-                GL_ARRAY_BUFFER,
-                0,
-                (size_of::<Vertex>() * self.vertices.len()) as isize,
-                self.vertices.as_mut_ptr().cast(),
-            );
-            glDrawArrays(GL_TRIANGLES, 0, self.vertices.len() as i32);
-        }
+        self.backend.draw(&self.vertices);
         self.vertices.clear();
     }
 
     pub fn render_vertex(&mut self, v: Vertex) {
+        if self.vertices.len() >= MAX_VERTICES {
+            self.flush();
+        }
         self.vertices.push(v);
     }
 
@@ -249,17 +354,17 @@ impl Renderer {
         )
     }
 
-    pub fn render_text(
+    pub fn render_text<C: TextColor>(
         &mut self,
         atlas: &FontAtlas,
         text: &str,
         mut pos: V2,
-        color: V4,
+        color: C,
         scale: f32,
     ) -> f32 {
         let mut width = 0.0;
 
-        for c in text.chars() {
+        for (idx, c) in text.char_indices() {
             let glyph = atlas.glyph(c);
 
             let x = pos.x + (glyph.bl as f32 * scale);
@@ -282,7 +387,7 @@ impl Renderer {
                     x: (glyph.bw as f32) / (atlas.atlas_width as f32),
                     y: (glyph.bh as f32) / (atlas.atlas_height as f32),
                 },
-                color,
+                color.at(idx),
             );
 
             width += glyph.ax;
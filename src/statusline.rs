@@ -0,0 +1,60 @@
+// expands a vim-like 'statusline' format into its left and right aligned
+// parts, split at %=; `item` resolves single letter items like %f as well
+// as named ones like %{mode}, unknown items are kept as they are
+pub fn expand<F: Fn(&str) -> Option<String>>(format: &str, item: F) -> (String, String) {
+    let mut parts = [String::new(), String::new()];
+    let mut side = 0;
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        let out = &mut parts[side];
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some('=') => side = 1,
+            Some('{') => {
+                let name = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                match item(&name) {
+                    Some(value) => out.push_str(&value),
+                    None => out.push_str(&format!("%{{{name}}}")),
+                }
+            }
+            Some(c) => match item(&c.to_string()) {
+                Some(value) => out.push_str(&value),
+                None => {
+                    out.push('%');
+                    out.push(c);
+                }
+            },
+            None => out.push('%'),
+        }
+    }
+
+    let [left, right] = parts;
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+
+    #[test]
+    fn expand_items() {
+        let item = |name: &str| match name {
+            "f" => Some("main.rs".to_string()),
+            "m" => Some(String::new()),
+            "mode" => Some("NORMAL".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            expand("%{mode} %f%m%=%x %{foo} 100%%", item),
+            ("NORMAL main.rs".to_string(), "%x %{foo} 100%".to_string())
+        );
+        assert_eq!(expand("%f", item), ("main.rs".to_string(), String::new()));
+    }
+}
@@ -0,0 +1,960 @@
+use crate::highlight::Group;
+
+// what a line continues from the end of the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Normal,
+    // inside a block comment of the given nesting depth
+    Comment(usize),
+    // inside a string spanning lines, indexing the language's strings
+    String(usize),
+    // inside a fenced markdown code block
+    CodeBlock,
+}
+
+// byte range of a line shown in the colors of a highlight group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub group: Group,
+}
+
+struct StringRule {
+    start: &'static str,
+    end: &'static str,
+    // a backslash escapes the following character
+    escape: bool,
+    multiline: bool,
+}
+
+const fn string(
+    start: &'static str,
+    end: &'static str,
+    escape: bool,
+    multiline: bool,
+) -> StringRule {
+    StringRule {
+        start,
+        end,
+        escape,
+        multiline,
+    }
+}
+
+enum Lexer {
+    Code,
+    Markdown,
+}
+
+pub struct Language {
+    pub name: &'static str,
+    lexer: Lexer,
+    line_comments: &'static [&'static str],
+    // a line comment has to start a word, like # in shell scripts
+    comment_after_space: bool,
+    block_comment: Option<(&'static str, &'static str)>,
+    nested_comments: bool,
+    // tried in order, so longer delimiters go first
+    strings: &'static [StringRule],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    constants: &'static [&'static str],
+    // characters besides alphanumerics and underscores in identifiers
+    ident_chars: &'static str,
+    // characters besides alphanumerics and underscores in numbers
+    number_chars: &'static str,
+    // a minus sign directly before a digit starts a number
+    signed_numbers: bool,
+    // identifiers with this prefix are built-ins
+    builtin_prefix: Option<&'static str>,
+    // identifiers or strings followed by this are keys, as in JSON
+    key_separator: Option<char>,
+    // lines starting with '#' are preprocessor directives
+    preproc_lines: bool,
+    // [table] headers of TOML
+    headers: bool,
+    // Rust's char literals and lifetimes, macros and attributes
+    rust: bool,
+    // $name, ${name} and $1 in shell scripts
+    variables: bool,
+}
+
+const CODE: Language = Language {
+    name: "",
+    lexer: Lexer::Code,
+    line_comments: &[],
+    comment_after_space: false,
+    block_comment: None,
+    nested_comments: false,
+    strings: &[],
+    keywords: &[],
+    types: &[],
+    constants: &[],
+    ident_chars: "",
+    number_chars: "",
+    signed_numbers: false,
+    builtin_prefix: None,
+    key_separator: None,
+    preproc_lines: false,
+    headers: false,
+    rust: false,
+    variables: false,
+};
+
+const RUST: Language = Language {
+    name: "rust",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: true,
+    strings: &[
+        string("r#\"", "\"#", false, true),
+        string("r\"", "\"", false, true),
+        string("b\"", "\"", true, true),
+        string("\"", "\"", true, true),
+    ],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "static", "struct", "super", "trait", "type", "unsafe",
+        "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str", "u8",
+        "u16", "u32", "u64", "u128", "usize", "Self",
+    ],
+    constants: &["true", "false"],
+    rust: true,
+    ..CODE
+};
+
+const TOML: Language = Language {
+    name: "toml",
+    line_comments: &["#"],
+    strings: &[
+        string("\"\"\"", "\"\"\"", true, true),
+        string("'''", "'''", false, true),
+        string("\"", "\"", true, false),
+        string("'", "'", false, false),
+    ],
+    constants: &["true", "false", "inf", "nan"],
+    ident_chars: "-",
+    number_chars: "-:+",
+    signed_numbers: true,
+    key_separator: Some('='),
+    headers: true,
+    ..CODE
+};
+
+const JSON: Language = Language {
+    name: "json",
+    strings: &[string("\"", "\"", true, false)],
+    constants: &["true", "false", "null"],
+    number_chars: "+-",
+    signed_numbers: true,
+    key_separator: Some(':'),
+    ..CODE
+};
+
+const GLSL: Language = Language {
+    name: "glsl",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    keywords: &[
+        "attribute",
+        "break",
+        "centroid",
+        "const",
+        "continue",
+        "discard",
+        "do",
+        "else",
+        "flat",
+        "for",
+        "highp",
+        "if",
+        "in",
+        "inout",
+        "invariant",
+        "layout",
+        "lowp",
+        "mediump",
+        "out",
+        "precision",
+        "return",
+        "smooth",
+        "struct",
+        "switch",
+        "case",
+        "default",
+        "uniform",
+        "varying",
+        "while",
+    ],
+    types: &[
+        "void",
+        "bool",
+        "int",
+        "uint",
+        "float",
+        "double",
+        "vec2",
+        "vec3",
+        "vec4",
+        "ivec2",
+        "ivec3",
+        "ivec4",
+        "uvec2",
+        "uvec3",
+        "uvec4",
+        "bvec2",
+        "bvec3",
+        "bvec4",
+        "dvec2",
+        "dvec3",
+        "dvec4",
+        "mat2",
+        "mat3",
+        "mat4",
+        "mat2x2",
+        "mat2x3",
+        "mat2x4",
+        "mat3x2",
+        "mat3x3",
+        "mat3x4",
+        "mat4x2",
+        "mat4x3",
+        "mat4x4",
+        "sampler1D",
+        "sampler2D",
+        "sampler3D",
+        "samplerCube",
+        "sampler2DArray",
+        "sampler2DShadow",
+    ],
+    constants: &["true", "false"],
+    builtin_prefix: Some("gl_"),
+    preproc_lines: true,
+    ..CODE
+};
+
+const SHELL: Language = Language {
+    name: "sh",
+    line_comments: &["#"],
+    comment_after_space: true,
+    strings: &[
+        string("\"", "\"", true, true),
+        string("'", "'", false, true),
+    ],
+    keywords: &[
+        "break", "case", "continue", "declare", "do", "done", "elif", "else", "esac", "eval",
+        "exec", "exit", "export", "fi", "for", "function", "if", "in", "local", "readonly",
+        "return", "select", "set", "shift", "source", "then", "trap", "unset", "until", "while",
+    ],
+    constants: &["true", "false"],
+    variables: true,
+    ..CODE
+};
+
+const MARKDOWN: Language = Language {
+    name: "markdown",
+    lexer: Lexer::Markdown,
+    block_comment: Some(("<!--", "-->")),
+    ..CODE
+};
+
+const LANGUAGES: [&Language; 6] = [&RUST, &TOML, &MARKDOWN, &JSON, &GLSL, &SHELL];
+
+pub fn language(filetype: &str) -> Option<&'static Language> {
+    LANGUAGES
+        .iter()
+        .find(|language| language.name == filetype)
+        .copied()
+}
+
+// the spans of one line and the state the next line starts in
+pub fn highlight_line(language: &Language, line: &str, state: State) -> (Vec<Span>, State) {
+    let mut spans = Spans(Vec::new());
+    let state = match language.lexer {
+        Lexer::Code => code(language, line, state, &mut spans),
+        Lexer::Markdown => markdown(language, line, state, &mut spans),
+    };
+    (spans.0, state)
+}
+
+struct Spans(Vec<Span>);
+
+impl Spans {
+    fn push(&mut self, start: usize, end: usize, group: Group) {
+        if start < end {
+            self.0.push(Span { start, end, group });
+        }
+    }
+
+    // comments with TODO, FIXME and XXX marked in them
+    fn comment(&mut self, line: &str, start: usize, end: usize) {
+        let mut from = start;
+        let mut idx = start;
+        while idx < end {
+            let word_end = idx + ident_len(&line[idx..end], "");
+            if matches!(&line[idx..word_end], "TODO" | "FIXME" | "XXX") {
+                self.push(from, idx, Group::Comment);
+                self.push(idx, word_end, Group::Todo);
+                from = word_end;
+            }
+            idx = word_end.max(idx + next_char_len(&line[idx..]));
+        }
+        self.push(from, end, Group::Comment);
+    }
+}
+
+fn next_char_len(text: &str) -> usize {
+    text.chars().next().map_or(1, char::len_utf8)
+}
+
+// bytes of the identifier `text` starts with
+fn ident_len(text: &str, extra: &str) -> usize {
+    text.char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || extra.contains(*c)))
+        .map_or(text.len(), |(idx, _)| idx)
+}
+
+fn number_len(text: &str, extra: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut idx = 1;
+    while idx < bytes.len() {
+        let c = bytes[idx] as char;
+        let fraction = c == '.' && bytes.get(idx + 1).is_some_and(u8::is_ascii_digit);
+        if !(c.is_ascii_alphanumeric() || c == '_' || fraction || extra.contains(c)) {
+            break;
+        }
+        idx += 1;
+    }
+    idx
+}
+
+// end of a string whose contents start at `from`
+fn string_end(rule: &StringRule, line: &str, from: usize) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut idx = from;
+    while idx < bytes.len() {
+        if rule.escape && bytes[idx] == b'\\' {
+            idx += 2;
+        } else if bytes[idx..].starts_with(rule.end.as_bytes()) {
+            return Some(idx + rule.end.len());
+        } else {
+            idx += 1;
+        }
+    }
+    None
+}
+
+// end of a block comment whose contents start at `from` and its nesting
+// depth at that point, which is zero once it is closed
+fn comment_end(language: &Language, line: &str, from: usize, mut depth: usize) -> (usize, usize) {
+    let Some((open, close)) = language.block_comment else {
+        return (line.len(), 0);
+    };
+    let bytes = line.as_bytes();
+    let mut idx = from;
+    while idx < bytes.len() {
+        if bytes[idx..].starts_with(close.as_bytes()) {
+            idx += close.len();
+            depth -= 1;
+            if depth == 0 {
+                break;
+            }
+        } else if language.nested_comments && bytes[idx..].starts_with(open.as_bytes()) {
+            idx += open.len();
+            depth += 1;
+        } else {
+            idx += 1;
+        }
+    }
+    (idx.min(line.len()), depth)
+}
+
+// 'a', '\n' and '\u{1F600}' but not the lifetime in &'a str
+fn char_literal_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\\') => text[2..].find('\'').map(|end| end + 3),
+        (_, '\'') => None,
+        (_, c) => text[1 + c.len_utf8()..]
+            .starts_with('\'')
+            .then_some(c.len_utf8() + 2),
+    }
+}
+
+fn classify(language: &Language, word: &str, rest: &str) -> Option<Group> {
+    let follows = |c: char| rest.trim_start().starts_with(c);
+
+    if language.keywords.contains(&word) {
+        Some(Group::Keyword)
+    } else if language.types.contains(&word) {
+        Some(Group::Type)
+    } else if language.constants.contains(&word) {
+        Some(Group::Boolean)
+    } else if language.key_separator.is_some_and(follows) && !rest.trim_start().starts_with("==") {
+        Some(Group::Identifier)
+    } else if language
+        .builtin_prefix
+        .is_some_and(|prefix| word.starts_with(prefix))
+    {
+        Some(Group::Special)
+    } else if language.rust && rest.starts_with('!') && !rest.starts_with("!=") {
+        Some(Group::PreProc)
+    } else if rest.starts_with('(') {
+        Some(Group::Function)
+    } else if language.rust && word.starts_with(char::is_uppercase) {
+        let constant = word.len() > 1 && !word.contains(char::is_lowercase);
+        Some(if constant {
+            Group::Constant
+        } else {
+            Group::Type
+        })
+    } else {
+        None
+    }
+}
+
+fn code(language: &Language, line: &str, state: State, spans: &mut Spans) -> State {
+    let len = line.len();
+    let mut idx = 0;
+
+    // constructs continued from the previous line
+    match state {
+        State::Comment(depth) => {
+            let (end, depth) = comment_end(language, line, 0, depth);
+            spans.comment(line, 0, end);
+            if depth > 0 {
+                return State::Comment(depth);
+            }
+            idx = end;
+        }
+        State::String(rule) => match language.strings.get(rule) {
+            Some(rule) => match string_end(rule, line, 0) {
+                Some(end) => {
+                    spans.push(0, end, Group::String);
+                    idx = end;
+                }
+                None => {
+                    spans.push(0, len, Group::String);
+                    return state;
+                }
+            },
+            None => return State::Normal,
+        },
+        State::Normal | State::CodeBlock => (),
+    }
+
+    let trimmed = line.trim_start();
+    if idx == 0 && language.preproc_lines && trimmed.starts_with('#') {
+        spans.push(len - trimmed.len(), len, Group::PreProc);
+        return State::Normal;
+    }
+    if idx == 0 && language.headers && trimmed.starts_with('[') {
+        let start = len - trimmed.len();
+        idx = line.rfind(']').map_or(len, |end| end + 1);
+        spans.push(start, idx, Group::Title);
+    }
+
+    while idx < len {
+        let rest = &line[idx..];
+        let c = rest.chars().next().unwrap_or_default();
+        let word_start = idx == 0 || line[..idx].ends_with(char::is_whitespace);
+
+        if language
+            .line_comments
+            .iter()
+            .any(|comment| rest.starts_with(comment))
+            && (word_start || !language.comment_after_space)
+        {
+            spans.comment(line, idx, len);
+            break;
+        }
+
+        if let Some((open, _)) = language
+            .block_comment
+            .filter(|(open, _)| rest.starts_with(open))
+        {
+            let (end, depth) = comment_end(language, line, idx + open.len(), 1);
+            spans.comment(line, idx, end);
+            if depth > 0 {
+                return State::Comment(depth);
+            }
+            idx = end;
+            continue;
+        }
+
+        if let Some((rule_idx, rule)) = language
+            .strings
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rest.starts_with(rule.start))
+        {
+            match string_end(rule, line, idx + rule.start.len()) {
+                Some(end) => {
+                    let key = language
+                        .key_separator
+                        .is_some_and(|sep| line[end..].trim_start().starts_with(sep));
+                    let group = if key {
+                        Group::Identifier
+                    } else {
+                        Group::String
+                    };
+                    spans.push(idx, end, group);
+                    idx = end;
+                    continue;
+                }
+                None => {
+                    spans.push(idx, len, Group::String);
+                    return if rule.multiline {
+                        State::String(rule_idx)
+                    } else {
+                        State::Normal
+                    };
+                }
+            }
+        }
+
+        if language.rust && c == '\'' {
+            match char_literal_len(rest) {
+                Some(char_len) => {
+                    spans.push(idx, idx + char_len, Group::String);
+                    idx += char_len;
+                }
+                None => {
+                    let end = idx + 1 + ident_len(&rest[1..], "");
+                    spans.push(idx, end, Group::Special);
+                    idx = end;
+                }
+            }
+            continue;
+        }
+
+        if language.rust && (rest.starts_with("#[") || rest.starts_with("#![")) {
+            let end = rest.rfind(']').map_or(len, |end| idx + end + 1);
+            spans.push(idx, end, Group::PreProc);
+            idx = end;
+            continue;
+        }
+
+        if language.variables && c == '$' {
+            let end = match rest[1..].chars().next() {
+                Some('{') => rest.find('}').map_or(len, |end| idx + end + 1),
+                Some(c) if c.is_alphabetic() || c == '_' => idx + 1 + ident_len(&rest[1..], ""),
+                Some(c) if c.is_ascii_digit() || "@*#?$!-".contains(c) => idx + 2,
+                _ => idx + 1,
+            };
+            spans.push(idx, end, Group::Identifier);
+            idx = end;
+            continue;
+        }
+
+        let signed = language.signed_numbers
+            && c == '-'
+            && rest[1..].starts_with(|c: char| c.is_ascii_digit());
+        if c.is_ascii_digit() || signed {
+            let end = idx + number_len(rest, language.number_chars);
+            spans.push(idx, end, Group::Number);
+            idx = end;
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let end = idx + ident_len(rest, language.ident_chars);
+            if let Some(group) = classify(language, &line[idx..end], &line[end..]) {
+                spans.push(idx, end, group);
+            }
+            idx = end;
+            continue;
+        }
+
+        idx += c.len_utf8();
+    }
+    State::Normal
+}
+
+fn markdown(language: &Language, line: &str, state: State, spans: &mut Spans) -> State {
+    let len = line.len();
+    let trimmed = line.trim_start();
+    let indent = len - trimmed.len();
+    let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+
+    match state {
+        State::CodeBlock if fence => {
+            spans.push(indent, len, Group::Special);
+            return State::Normal;
+        }
+        State::CodeBlock => {
+            spans.push(0, len, Group::String);
+            return state;
+        }
+        State::Comment(depth) => {
+            let (end, depth) = comment_end(language, line, 0, depth);
+            spans.comment(line, 0, end);
+            if depth > 0 {
+                return State::Comment(depth);
+            }
+            return inline(language, line, end, spans);
+        }
+        State::Normal | State::String(_) => (),
+    }
+
+    if fence {
+        spans.push(indent, len, Group::Special);
+        return State::CodeBlock;
+    }
+    let level = trimmed.bytes().take_while(|c| *c == b'#').count();
+    if (1..=6).contains(&level) && !trimmed[level..].starts_with(|c: char| !c.is_whitespace()) {
+        spans.push(indent, len, Group::Title);
+        return State::Normal;
+    }
+    if trimmed.starts_with('>') {
+        spans.push(indent, len, Group::Comment);
+        return State::Normal;
+    }
+
+    // list items
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+    let marker =
+        if trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ") {
+            1
+        } else if digits > 0 && trimmed[digits..].starts_with(". ") {
+            digits + 1
+        } else {
+            0
+        };
+    spans.push(indent, indent + marker, Group::Statement);
+
+    inline(language, line, indent + marker, spans)
+}
+
+// code spans, emphasis, links and comments inside a markdown line
+fn inline(language: &Language, line: &str, from: usize, spans: &mut Spans) -> State {
+    let len = line.len();
+    let mut idx = from;
+
+    while idx < len {
+        let rest = &line[idx..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        if rest.starts_with("<!--") {
+            let (end, depth) = comment_end(language, line, idx + 4, 1);
+            spans.comment(line, idx, end);
+            if depth > 0 {
+                return State::Comment(depth);
+            }
+            idx = end;
+            continue;
+        }
+
+        let closing = match c {
+            '`' => rest[1..].find('`').map(|end| (end + 2, Group::String)),
+            '*' | '_' => {
+                let delimiter = if rest[1..].starts_with(c) {
+                    &rest[..2]
+                } else {
+                    &rest[..1]
+                };
+                let inner = &rest[delimiter.len()..];
+                let in_word = c == '_' && line[..idx].ends_with(char::is_alphanumeric);
+                (!in_word && inner.starts_with(|c: char| !c.is_whitespace()))
+                    .then(|| inner.find(delimiter))
+                    .flatten()
+                    .map(|end| (2 * delimiter.len() + end, Group::Special))
+            }
+            '[' => rest.find("](").and_then(|text_end| {
+                let url_end = rest[text_end..].find(')')?;
+                spans.push(idx, idx + text_end + 1, Group::Identifier);
+                spans.push(
+                    idx + text_end + 1,
+                    idx + text_end + url_end + 1,
+                    Group::Constant,
+                );
+                Some((text_end + url_end + 1, Group::Constant))
+            }),
+            _ => None,
+        };
+
+        match closing {
+            Some((span_len, group)) => {
+                if c != '[' {
+                    spans.push(idx, idx + span_len, group);
+                }
+                idx += span_len;
+            }
+            None => idx += c.len_utf8(),
+        }
+    }
+    State::Normal
+}
+
+// highlighting of all lines of a buffer, kept up to date on edits by
+// highlighting the changed lines and the following ones only as long as
+// the state they start in differs from before
+pub struct Syntax {
+    language: Option<&'static Language>,
+    // spans of each line and the state at its end
+    lines: Vec<(Vec<Span>, State)>,
+}
+
+impl Syntax {
+    pub fn new(language: Option<&'static Language>) -> Syntax {
+        Syntax {
+            language,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn language(&self) -> Option<&'static Language> {
+        self.language
+    }
+
+    pub fn spans(&self, line: usize) -> &[Span] {
+        self.lines
+            .get(line)
+            .map_or(&[], |(spans, _)| spans.as_slice())
+    }
+
+    pub fn highlight<'a, I>(&mut self, lines: I)
+    where
+        I: IntoIterator<Item = std::borrow::Cow<'a, str>>,
+    {
+        let Some(language) = self.language else {
+            self.lines.clear();
+            return;
+        };
+        let mut state = State::Normal;
+        self.lines = lines
+            .into_iter()
+            .map(|line| {
+                let (spans, end) = highlight_line(language, &line, state);
+                state = end;
+                (spans, end)
+            })
+            .collect();
+    }
+
+    // replaces `removed` lines starting at `first` by `added` ones without
+    // spans, for highlighting set from outside like by a syntax tree
+    pub fn splice(&mut self, first: usize, removed: usize, added: usize) {
+        let end = (first + removed).min(self.lines.len());
+        let first = first.min(end);
+        self.lines
+            .splice(first..end, (0..added).map(|_| (Vec::new(), State::Normal)));
+    }
+
+    pub fn set_spans(&mut self, line: usize, spans: Vec<Span>) {
+        if let Some((current, _)) = self.lines.get_mut(line) {
+            *current = spans;
+        }
+    }
+
+    // `removed` lines starting at `first` were replaced by `added` ones,
+    // `line` returns the text of a line after the edit
+    pub fn edit<'a, F>(&mut self, first: usize, removed: usize, added: usize, line: F)
+    where
+        F: Fn(usize) -> Option<std::borrow::Cow<'a, str>>,
+    {
+        let Some(language) = self.language else {
+            return;
+        };
+        self.splice(first, removed, added);
+        let first = first.min(self.lines.len() - added);
+
+        let mut state = match first {
+            0 => State::Normal,
+            first => self.lines[first - 1].1,
+        };
+        let mut idx = first;
+        while let Some(text) = line(idx) {
+            let highlighted = highlight_line(language, &text, state);
+            let unchanged = idx >= first + added && self.lines.get(idx) == Some(&highlighted);
+            match self.lines.get_mut(idx) {
+                Some(current) => *current = highlighted,
+                None => self.lines.push(highlighted),
+            }
+            if unchanged {
+                break;
+            }
+            state = self.lines[idx].1;
+            idx += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight_line, language, Span, State, Syntax};
+    use crate::highlight::Group;
+
+    fn groups<'a>(filetype: &str, line: &'a str, state: State) -> (Vec<(&'a str, Group)>, State) {
+        let (spans, state) = highlight_line(language(filetype).unwrap(), line, state);
+        let groups = spans
+            .iter()
+            .map(|span| (&line[span.start..span.end], span.group))
+            .collect();
+        (groups, state)
+    }
+
+    #[test]
+    fn rust() {
+        let (spans, state) = groups(
+            "rust",
+            "pub fn f<'a>(s: &'a str) -> Option<char> { println!(\"{s}\"); 'x' } // TODO",
+            State::Normal,
+        );
+        assert_eq!(
+            spans,
+            vec![
+                ("pub", Group::Keyword),
+                ("fn", Group::Keyword),
+                ("'a", Group::Special),
+                ("'a", Group::Special),
+                ("str", Group::Type),
+                ("Option", Group::Type),
+                ("char", Group::Type),
+                ("println", Group::PreProc),
+                ("\"{s}\"", Group::String),
+                ("'x'", Group::String),
+                ("// ", Group::Comment),
+                ("TODO", Group::Todo),
+            ]
+        );
+        assert_eq!(state, State::Normal);
+
+        // nested block comments and strings continue on the next lines
+        let (_, state) = groups("rust", "let x = 1; /* a /* b */", State::Normal);
+        assert_eq!(state, State::Comment(1));
+        let (spans, state) = groups("rust", "*/ let s = r#\"raw", state);
+        assert_eq!(spans[0], ("*/", Group::Comment));
+        assert_eq!(spans[2], ("r#\"raw", Group::String));
+        assert_eq!(state, State::String(0));
+        let (spans, _) = groups("rust", "\"# MAX_LEN", state);
+        assert_eq!(
+            spans,
+            vec![("\"#", Group::String), ("MAX_LEN", Group::Constant)]
+        );
+    }
+
+    #[test]
+    fn toml_and_json() {
+        let (spans, _) = groups("toml", "[dependencies] # deps", State::Normal);
+        assert_eq!(
+            spans,
+            vec![("[dependencies]", Group::Title), ("# deps", Group::Comment)]
+        );
+        let (spans, _) = groups(
+            "toml",
+            "ropey = { version = \"1.6\", default-features = false }",
+            State::Normal,
+        );
+        assert_eq!(
+            spans,
+            vec![
+                ("ropey", Group::Identifier),
+                ("version", Group::Identifier),
+                ("\"1.6\"", Group::String),
+                ("default-features", Group::Identifier),
+                ("false", Group::Boolean),
+            ]
+        );
+
+        let (spans, _) = groups("json", "{\"a\": [-1.5e3, \"b\", null]}", State::Normal);
+        assert_eq!(
+            spans,
+            vec![
+                ("\"a\"", Group::Identifier),
+                ("-1.5e3", Group::Number),
+                ("\"b\"", Group::String),
+                ("null", Group::Boolean),
+            ]
+        );
+    }
+
+    #[test]
+    fn glsl_and_shell() {
+        let (spans, _) = groups("glsl", "  #version 330 core", State::Normal);
+        assert_eq!(spans, vec![("#version 330 core", Group::PreProc)]);
+        let (spans, _) = groups("glsl", "gl_Position = vec4(pos, 0.0, 1.0);", State::Normal);
+        assert_eq!(
+            spans,
+            vec![
+                ("gl_Position", Group::Special),
+                ("vec4", Group::Type),
+                ("0.0", Group::Number),
+                ("1.0", Group::Number),
+            ]
+        );
+
+        let (spans, _) = groups(
+            "sh",
+            "if [ $# -gt 0 ]; then echo \"${1}\" # done",
+            State::Normal,
+        );
+        assert_eq!(
+            spans,
+            vec![
+                ("if", Group::Keyword),
+                ("$#", Group::Identifier),
+                ("0", Group::Number),
+                ("then", Group::Keyword),
+                ("\"${1}\"", Group::String),
+                ("# done", Group::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let (spans, _) = groups("markdown", "## Title", State::Normal);
+        assert_eq!(spans, vec![("## Title", Group::Title)]);
+        let (spans, _) = groups(
+            "markdown",
+            "- use `:set nu` in **edi**, see [docs](http://x)",
+            State::Normal,
+        );
+        assert_eq!(
+            spans,
+            vec![
+                ("-", Group::Statement),
+                ("`:set nu`", Group::String),
+                ("**edi**", Group::Special),
+                ("[docs]", Group::Identifier),
+                ("(http://x)", Group::Constant),
+            ]
+        );
+
+        let (_, state) = groups("markdown", "```rust", State::Normal);
+        assert_eq!(state, State::CodeBlock);
+        let (spans, state) = groups("markdown", "# not a title", state);
+        assert_eq!(spans, vec![("# not a title", Group::String)]);
+        let (_, state) = groups("markdown", "```", state);
+        assert_eq!(state, State::Normal);
+    }
+
+    #[test]
+    fn incremental_edits() {
+        let mut lines = vec!["let a = 1;", "let b = 2;", "let c = 3;"];
+        let mut syntax = Syntax::new(language("rust"));
+        syntax.highlight(lines.iter().map(|line| (*line).into()));
+        let keyword = Span {
+            start: 0,
+            end: 3,
+            group: Group::Keyword,
+        };
+        assert_eq!(syntax.spans(2)[0], keyword);
+
+        // opening a block comment changes all following lines
+        lines[0] = "/* let a = 1;";
+        syntax.edit(0, 1, 1, |idx| lines.get(idx).map(|line| (*line).into()));
+        assert_eq!(syntax.spans(2)[0].group, Group::Comment);
+
+        lines.insert(2, "*/");
+        syntax.edit(2, 0, 1, |idx| lines.get(idx).map(|line| (*line).into()));
+        assert_eq!(syntax.spans(1)[0].group, Group::Comment);
+        assert_eq!(syntax.spans(3)[0], keyword);
+    }
+}
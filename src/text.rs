@@ -0,0 +1,189 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use ropey::Rope;
+
+pub struct Text {
+    rope: Rope,
+}
+
+impl Text {
+    pub fn new() -> Text {
+        Text { rope: Rope::new() }
+    }
+
+    pub fn from_reader<R: std::io::Read>(reader: R) -> std::io::Result<Text> {
+        Ok(Text {
+            rope: Rope::from_reader(reader)?,
+        })
+    }
+
+    pub fn write_to<W: std::io::Write>(&self, writer: W) -> std::io::Result<()> {
+        self.rope.write_to(writer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rope.len_bytes() == 0
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    pub fn insert(&mut self, idx: usize, text: &str) {
+        let char_idx = self.rope.byte_to_char(idx);
+        self.rope.insert(char_idx, text);
+    }
+
+    pub fn remove(&mut self, range: Range<usize>) {
+        let start = self.rope.byte_to_char(range.start);
+        let end = self.rope.byte_to_char(range.end);
+        self.rope.remove(start..end);
+    }
+
+    // number of characters in front of a byte offset
+    pub fn byte_to_char(&self, idx: usize) -> usize {
+        self.rope.byte_to_char(idx)
+    }
+
+    // byte offset of the start of the character at `idx`
+    pub fn char_start(&self, idx: usize) -> usize {
+        self.rope.char_to_byte(self.rope.byte_to_char(idx))
+    }
+
+    // byte offset of the character following the one at `idx`
+    pub fn next_char(&self, idx: usize) -> usize {
+        let char_idx = self.rope.byte_to_char(idx) + 1;
+        self.rope.char_to_byte(char_idx.min(self.rope.len_chars()))
+    }
+
+    // byte offset of the character in front of `idx`
+    pub fn prev_char(&self, idx: usize) -> usize {
+        match idx {
+            0 => 0,
+            _ => self.rope.char_to_byte(self.rope.byte_to_char(idx - 1)),
+        }
+    }
+
+    pub fn line_to_byte(&self, line: usize) -> usize {
+        self.rope.line_to_byte(line)
+    }
+
+    pub fn byte_to_line(&self, idx: usize) -> usize {
+        self.rope.byte_to_line(idx)
+    }
+
+    // (line, column) of a byte offset, the column being counted in bytes
+    pub fn byte_to_pos(&self, idx: usize) -> (usize, usize) {
+        let line = self.rope.byte_to_line(idx);
+        (line, idx - self.rope.line_to_byte(line))
+    }
+
+    pub fn pos_to_byte(&self, line: usize, col: usize) -> usize {
+        self.rope.line_to_byte(line) + col
+    }
+
+    // byte length of the given line without its line break
+    pub fn line_len(&self, line: usize) -> usize {
+        let slice = self.rope.line(line);
+        let len = slice.len_bytes();
+        if len > 0 && slice.byte(len - 1) == b'\n' {
+            len - 1
+        } else {
+            len
+        }
+    }
+
+    // text of the given line without its line break
+    pub fn line(&self, line: usize) -> Cow<'_, str> {
+        let start = self.rope.line_to_byte(line);
+        self.slice(start..start + self.line_len(line))
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        self.rope.byte_slice(range).into()
+    }
+
+    pub fn bytes_at(&self, idx: usize) -> ropey::iter::Bytes<'_> {
+        self.rope.bytes_at(idx)
+    }
+
+    // rest of the chunk of the rope holding the given byte, empty at the
+    // end of the text
+    pub fn chunk_at(&self, idx: usize) -> &str {
+        if idx >= self.len() {
+            return "";
+        }
+        let (chunk, start, _, _) = self.rope.chunk_at_byte(idx);
+        &chunk[idx - start..]
+    }
+}
+
+impl std::fmt::Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.rope.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Text;
+
+    fn text(s: &str) -> Text {
+        Text::from_reader(s.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut t = text("foo bar");
+        t.insert(3, "\nham");
+        assert_eq!(t.to_string(), "foo\nham bar");
+
+        t.remove(3..7);
+        assert_eq!(t.to_string(), "foo bar");
+    }
+
+    #[test]
+    fn line_conversions() {
+        let t = text("foo\n\nbar baz\n");
+
+        assert_eq!(t.line_count(), 4);
+        assert_eq!(t.line_to_byte(2), 5);
+        assert_eq!(t.byte_to_pos(9), (2, 4));
+        assert_eq!(t.pos_to_byte(2, 4), 9);
+        assert_eq!(t.line_len(0), 3);
+        assert_eq!(t.line_len(1), 0);
+        assert_eq!(t.line_len(2), 7);
+        assert_eq!(t.line_len(3), 0);
+    }
+
+    #[test]
+    fn multibyte_offsets() {
+        let mut t = text("äb");
+        t.insert(2, "c");
+        assert_eq!(t.to_string(), "äcb");
+
+        t.remove(0..2);
+        assert_eq!(t.to_string(), "cb");
+
+        let t = text("aä日b");
+        assert_eq!(t.next_char(0), 1);
+        assert_eq!(t.next_char(1), 3);
+        assert_eq!(t.next_char(3), 6);
+        assert_eq!(t.next_char(7), 7);
+        assert_eq!(t.prev_char(6), 3);
+        assert_eq!(t.prev_char(3), 1);
+        assert_eq!(t.prev_char(0), 0);
+        assert_eq!(t.char_start(2), 1);
+        assert_eq!(t.char_start(5), 3);
+        assert_eq!(t.char_start(7), 7);
+        assert_eq!(t.byte_to_char(6), 3);
+    }
+}
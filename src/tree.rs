@@ -0,0 +1,508 @@
+use std::ops::Range;
+
+use tree_sitter::{
+    InputEdit, Language, Node, Parser, Point, Query, QueryCursor, StreamingIterator, Tree,
+};
+
+use crate::buffer::TextObject;
+use crate::highlight::Group;
+use crate::syntax::Span;
+use crate::text::Text;
+
+pub struct Grammar {
+    pub filetype: &'static str,
+    language: fn() -> Language,
+    highlights: &'static str,
+    functions: &'static [&'static str],
+    classes: &'static [&'static str],
+    // nodes whose named children are arguments or parameters
+    argument_lists: &'static [&'static str],
+}
+
+const GRAMMARS: [Grammar; 4] = [
+    Grammar {
+        filetype: "rust",
+        language: || tree_sitter_rust::LANGUAGE.into(),
+        highlights: tree_sitter_rust::HIGHLIGHTS_QUERY,
+        functions: &[
+            "function_item",
+            "function_signature_item",
+            "closure_expression",
+        ],
+        classes: &[
+            "struct_item",
+            "enum_item",
+            "union_item",
+            "trait_item",
+            "impl_item",
+        ],
+        argument_lists: &[
+            "parameters",
+            "arguments",
+            "closure_parameters",
+            "type_parameters",
+            "type_arguments",
+        ],
+    },
+    Grammar {
+        filetype: "toml",
+        language: || tree_sitter_toml_ng::LANGUAGE.into(),
+        highlights: tree_sitter_toml_ng::HIGHLIGHTS_QUERY,
+        functions: &[],
+        classes: &[],
+        argument_lists: &["array"],
+    },
+    Grammar {
+        filetype: "json",
+        language: || tree_sitter_json::LANGUAGE.into(),
+        highlights: tree_sitter_json::HIGHLIGHTS_QUERY,
+        functions: &[],
+        classes: &[],
+        argument_lists: &["array"],
+    },
+    Grammar {
+        filetype: "sh",
+        language: || tree_sitter_bash::LANGUAGE.into(),
+        highlights: tree_sitter_bash::HIGHLIGHT_QUERY,
+        functions: &["function_definition"],
+        classes: &[],
+        argument_lists: &[],
+    },
+];
+
+// highlight groups of the capture names used by the queries, names
+// without an entry fall back to their parent like function.method to
+// function
+const CAPTURES: [(&str, Group); 19] = [
+    ("attribute", Group::PreProc),
+    ("boolean", Group::Boolean),
+    ("comment", Group::Comment),
+    ("constant", Group::Constant),
+    ("constructor", Group::Type),
+    ("escape", Group::Special),
+    ("function", Group::Function),
+    ("function.macro", Group::PreProc),
+    ("keyword", Group::Keyword),
+    ("label", Group::Special),
+    ("number", Group::Number),
+    ("operator", Group::Operator),
+    ("property", Group::Identifier),
+    ("string", Group::String),
+    ("string.special", Group::Special),
+    ("string.special.key", Group::Identifier),
+    ("type", Group::Type),
+    ("variable.builtin", Group::Keyword),
+    ("variable.parameter", Group::Normal),
+];
+
+fn capture_group(name: &str) -> Option<Group> {
+    let mut name = name;
+    loop {
+        if let Some((_, group)) = CAPTURES.iter().find(|(capture, _)| *capture == name) {
+            return Some(*group);
+        }
+        name = name.rsplit_once('.')?.0;
+    }
+}
+
+pub fn grammar(filetype: &str) -> Option<&'static Grammar> {
+    GRAMMARS.iter().find(|grammar| grammar.filetype == filetype)
+}
+
+// the edit replacing the byte range `range` of the text by `inserted`,
+// to be called before the text is changed
+pub fn input_edit(text: &Text, range: Range<usize>, inserted: &str) -> InputEdit {
+    let point = |(row, column)| Point { row, column };
+    let start = text.byte_to_pos(range.start);
+    let new_end = match inserted.rsplit_once('\n') {
+        Some((before, after)) => (start.0 + before.matches('\n').count() + 1, after.len()),
+        None => (start.0, start.1 + inserted.len()),
+    };
+
+    InputEdit {
+        start_byte: range.start,
+        old_end_byte: range.end,
+        new_end_byte: range.start + inserted.len(),
+        start_position: point(start),
+        old_end_position: point(text.byte_to_pos(range.end)),
+        new_end_position: point(new_end),
+    }
+}
+
+// syntax tree of a buffer, parsed again on every edit while reusing the
+// unchanged parts of the previous tree
+pub struct SyntaxTree {
+    grammar: &'static Grammar,
+    parser: Parser,
+    tree: Tree,
+    query: Query,
+    // highlight group of each capture of the query
+    groups: Vec<Option<Group>>,
+}
+
+impl SyntaxTree {
+    pub fn new(grammar: &'static Grammar, text: &Text) -> SyntaxTree {
+        let language = (grammar.language)();
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language)
+            .expect("grammar is compatible with the parser");
+        let tree = Self::parse(&mut parser, text, None);
+        let query = Query::new(&language, grammar.highlights).expect("highlight query is valid");
+        let groups = query
+            .capture_names()
+            .iter()
+            .map(|name| capture_group(name))
+            .collect();
+
+        SyntaxTree {
+            grammar,
+            parser,
+            tree,
+            query,
+            groups,
+        }
+    }
+
+    fn parse(parser: &mut Parser, text: &Text, old: Option<&Tree>) -> Tree {
+        parser
+            .parse_with_options(&mut |idx, _| text.chunk_at(idx), old, None)
+            .expect("parser has a language")
+    }
+
+    pub fn filetype(&self) -> &'static str {
+        self.grammar.filetype
+    }
+
+    // applies an edit of the text, returning the lines whose syntax
+    // changed besides the edited ones
+    pub fn edit(&mut self, text: &Text, edit: &InputEdit) -> Vec<usize> {
+        self.tree.edit(edit);
+        let tree = Self::parse(&mut self.parser, text, Some(&self.tree));
+        let mut lines = self
+            .tree
+            .changed_ranges(&tree)
+            .flat_map(|range| range.start_point.row..=range.end_point.row)
+            .filter(|line| *line < text.line_count())
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+
+        self.tree = tree;
+        lines
+    }
+
+    // highlighted parts of a line, inner nodes overriding the ones they
+    // are part of
+    pub fn spans(&self, text: &Text, line: usize) -> Vec<Span> {
+        let start = text.line_to_byte(line);
+        let len = text.line_len(line);
+        let mut groups = vec![None; len];
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(start..start + len);
+        let source = |node: Node| std::iter::once(text.slice(node.byte_range()).into_owned());
+        let mut captures = cursor.captures(&self.query, self.tree.root_node(), source);
+        let mut last = None;
+
+        while let Some((found, idx)) = captures.next() {
+            let capture = found.captures[*idx];
+            // the first pattern matching a node wins
+            if last == Some(capture.node.id()) {
+                continue;
+            }
+            let Some(group) = self.groups[capture.index as usize] else {
+                continue;
+            };
+            last = Some(capture.node.id());
+            let range = capture.node.byte_range();
+            let from = range.start.max(start) - start;
+            let to = range.end.min(start + len).max(start) - start;
+            groups[from.min(to)..to].fill(Some(group));
+        }
+
+        let mut spans: Vec<Span> = Vec::new();
+        for (idx, group) in groups.into_iter().enumerate() {
+            match (spans.last_mut(), group) {
+                (Some(span), Some(group)) if span.end == idx && span.group == group => {
+                    span.end += 1
+                }
+                (_, Some(group)) if group != Group::Normal => spans.push(Span {
+                    start: idx,
+                    end: idx + 1,
+                    group,
+                }),
+                _ => (),
+            }
+        }
+        spans
+    }
+
+    // byte range of the text object around the given byte, `around`
+    // including the surrounding whitespace or separator
+    pub fn object(
+        &self,
+        text: &Text,
+        object: TextObject,
+        around: bool,
+        idx: usize,
+    ) -> Option<Range<usize>> {
+        let root = self.tree.root_node();
+        let mut node = root.descendant_for_byte_range(idx, idx)?;
+        let kinds = match object {
+            TextObject::Function => self.grammar.functions,
+            TextObject::Class => self.grammar.classes,
+            TextObject::Argument => &[],
+        };
+        while !match object {
+            TextObject::Argument => {
+                node.is_named()
+                    && node
+                        .parent()
+                        .is_some_and(|parent| self.grammar.argument_lists.contains(&parent.kind()))
+            }
+            _ => kinds.contains(&node.kind()),
+        } {
+            node = node.parent()?;
+        }
+
+        let range = node.byte_range();
+        Some(match (object, around) {
+            (TextObject::Argument, false) => range,
+            (TextObject::Argument, true) => {
+                if let Some(next) = node.next_named_sibling() {
+                    range.start..next.start_byte()
+                } else if let Some(prev) = node.prev_named_sibling() {
+                    prev.end_byte()..range.end
+                } else {
+                    range
+                }
+            }
+            (_, false) => {
+                let body = node.child_by_field_name("body").unwrap_or(node);
+                let count = body.named_child_count();
+                match (
+                    body.named_child(0),
+                    count.checked_sub(1).and_then(|last| body.named_child(last)),
+                ) {
+                    (Some(first), Some(last)) => first.start_byte()..last.end_byte(),
+                    // an empty block
+                    _ => {
+                        let inner = body.start_byte() + 1;
+                        inner.min(body.end_byte())..inner.min(body.end_byte())
+                    }
+                }
+            }
+            (_, true) => whole_lines(text, range),
+        })
+    }
+
+    // start of the next or previous function from the given byte
+    pub fn function(&self, idx: usize, forward: bool) -> Option<usize> {
+        let mut starts = Vec::new();
+        self.walk(|node, _, _| {
+            if self.grammar.functions.contains(&node.kind()) {
+                starts.push(node.start_byte());
+            }
+        });
+
+        if forward {
+            starts.into_iter().find(|start| *start > idx)
+        } else {
+            starts.into_iter().rev().find(|start| *start < idx)
+        }
+    }
+
+    // calls `f` with each node in order, its depth and its field name
+    fn walk<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(Node<'a>, usize, Option<&'static str>),
+    {
+        let mut cursor = self.tree.walk();
+        loop {
+            f(cursor.node(), cursor.depth() as usize, cursor.field_name());
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return;
+                }
+            }
+        }
+    }
+
+    // outline of the named nodes with their positions, as shown by
+    // :InspectTree
+    pub fn inspect(&self) -> String {
+        let mut lines = Vec::new();
+        self.walk(|node, depth, field| {
+            if !node.is_named() {
+                return;
+            }
+            let (start, end) = (node.start_position(), node.end_position());
+            lines.push(format!(
+                "{:indent$}{}({}) ; [{}, {}] - [{}, {}]",
+                "",
+                field.map(|field| format!("{field}: ")).unwrap_or_default(),
+                node.kind(),
+                start.row,
+                start.column,
+                end.row,
+                end.column,
+                indent = depth * 2,
+            ));
+        });
+        lines.join("\n")
+    }
+}
+
+// extends a range to whole lines if nothing but whitespace is before or
+// after it on its first and last line
+fn whole_lines(text: &Text, range: Range<usize>) -> Range<usize> {
+    let first = text.byte_to_line(range.start);
+    let last = text.byte_to_line(range.end);
+    let line_start = text.line_to_byte(first);
+    let line_end = text.line_to_byte(last) + text.line_len(last);
+
+    let before = text.slice(line_start..range.start);
+    let after = text.slice(range.end..line_end);
+    if !before.trim().is_empty() || !after.trim().is_empty() {
+        return range;
+    }
+    let end = if last + 1 < text.line_count() {
+        line_end + 1
+    } else {
+        line_end
+    };
+    line_start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grammar, input_edit, SyntaxTree};
+    use crate::buffer::TextObject;
+    use crate::highlight::Group;
+    use crate::text::Text;
+
+    const SOURCE: &str = "struct Point { x: i32 }\n\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nfn main() {\n    // sum\n    println!(\"{}\", add(1, 2));\n}\n";
+
+    fn parse(filetype: &str, source: &str) -> (Text, SyntaxTree) {
+        let text = Text::from_reader(source.as_bytes()).unwrap();
+        let tree = SyntaxTree::new(grammar(filetype).unwrap(), &text);
+        (text, tree)
+    }
+
+    fn groups(text: &Text, tree: &SyntaxTree, line: usize) -> Vec<(String, Group)> {
+        let source = text.line(line);
+        tree.spans(text, line)
+            .iter()
+            .map(|span| (source[span.start..span.end].to_string(), span.group))
+            .collect()
+    }
+
+    #[test]
+    fn highlights() {
+        let (text, tree) = parse("rust", SOURCE);
+        assert_eq!(
+            groups(&text, &tree, 2),
+            vec![
+                ("fn".to_string(), Group::Keyword),
+                ("add".to_string(), Group::Function),
+                ("i32".to_string(), Group::Type),
+                ("i32".to_string(), Group::Type),
+                ("i32".to_string(), Group::Type),
+            ]
+        );
+        assert_eq!(
+            groups(&text, &tree, 7),
+            vec![("// sum".to_string(), Group::Comment)]
+        );
+
+        let (text, tree) = parse("json", "{\"key\": [1, true]}");
+        assert_eq!(
+            groups(&text, &tree, 0),
+            vec![
+                ("\"key\"".to_string(), Group::Identifier),
+                ("1".to_string(), Group::Number),
+                ("true".to_string(), Group::Constant),
+            ]
+        );
+    }
+
+    #[test]
+    fn incremental_edits() {
+        let (mut text, mut tree) = parse("rust", SOURCE);
+
+        // opening a block comment changes the following lines as well
+        let edit = input_edit(&text, 0..0, "/*\n");
+        text.insert(0, "/*\n");
+        let lines = tree.edit(&text, &edit);
+        assert!(lines.contains(&3));
+        assert_eq!(groups(&text, &tree, 3)[0].1, Group::Comment);
+
+        let edit = input_edit(&text, 0..3, "");
+        text.remove(0..3);
+        tree.edit(&text, &edit);
+        assert_eq!(
+            groups(&text, &tree, 2)[0],
+            ("fn".to_string(), Group::Keyword)
+        );
+    }
+
+    #[test]
+    fn objects_and_functions() {
+        let (text, tree) = parse("rust", SOURCE);
+        let at = |line, col| text.pos_to_byte(line, col);
+        let object = |object, around, idx| {
+            tree.object(&text, object, around, idx)
+                .map(|range| text.slice(range).into_owned())
+        };
+
+        assert_eq!(
+            object(TextObject::Function, false, at(3, 4)).unwrap(),
+            "a + b"
+        );
+        assert_eq!(
+            object(TextObject::Function, true, at(3, 4)).unwrap(),
+            "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n"
+        );
+        assert_eq!(
+            object(TextObject::Class, false, at(0, 0)).unwrap(),
+            "x: i32"
+        );
+        assert_eq!(
+            object(TextObject::Argument, false, at(2, 8)).unwrap(),
+            "a: i32"
+        );
+        assert_eq!(
+            object(TextObject::Argument, true, at(2, 8)).unwrap(),
+            "a: i32, "
+        );
+        assert_eq!(
+            object(TextObject::Argument, true, at(2, 15)).unwrap(),
+            ", b: i32"
+        );
+        assert_eq!(object(TextObject::Class, false, at(3, 4)), None);
+
+        assert_eq!(tree.function(0, true), Some(at(2, 0)));
+        assert_eq!(tree.function(at(2, 0), true), Some(at(6, 0)));
+        assert_eq!(tree.function(at(6, 0), true), None);
+        assert_eq!(tree.function(at(7, 0), false), Some(at(6, 0)));
+    }
+
+    #[test]
+    fn inspect() {
+        let (_, tree) = parse("sh", "f() {\n  echo hi\n}\n");
+        let outline = tree.inspect();
+        let lines = outline.lines().take(3).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "(program) ; [0, 0] - [3, 0]",
+                "  (function_definition) ; [0, 0] - [2, 1]",
+                "    name: (word) ; [0, 0] - [0, 1]",
+            ]
+        );
+    }
+}
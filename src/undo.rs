@@ -0,0 +1,124 @@
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub idx: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+struct Group {
+    seq: usize,
+    changes: Vec<Change>,
+}
+
+// changes are collected until `commit` closes the current undo step,
+// e.g. after a normal mode command or when leaving insert mode
+pub struct History {
+    undo: Vec<Group>,
+    redo: Vec<Group>,
+    pending: Vec<Change>,
+    seq: usize,
+    saved: usize,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            pending: Vec::new(),
+            seq: 0,
+            saved: 0,
+        }
+    }
+
+    pub fn record(&mut self, change: Change) {
+        self.redo.clear();
+        self.pending.push(change);
+    }
+
+    pub fn commit(&mut self) {
+        if !self.pending.is_empty() {
+            self.seq += 1;
+            self.undo.push(Group {
+                seq: self.seq,
+                changes: std::mem::take(&mut self.pending),
+            });
+        }
+    }
+
+    // changes of the step to revert, in the order they were made
+    pub fn undo(&mut self) -> Option<Vec<Change>> {
+        self.commit();
+        let group = self.undo.pop()?;
+        let changes = group.changes.clone();
+        self.redo.push(group);
+        Some(changes)
+    }
+
+    pub fn redo(&mut self) -> Option<Vec<Change>> {
+        let group = self.redo.pop()?;
+        let changes = group.changes.clone();
+        self.undo.push(group);
+        Some(changes)
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.commit();
+        self.saved = self.current();
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !self.pending.is_empty() || self.current() != self.saved
+    }
+
+    fn current(&self) -> usize {
+        self.undo.last().map(|group| group.seq).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, History};
+
+    fn change(idx: usize, inserted: &str) -> Change {
+        Change {
+            idx,
+            removed: String::new(),
+            inserted: inserted.to_string(),
+        }
+    }
+
+    #[test]
+    fn undo_redo_steps() {
+        let mut h = History::new();
+        assert!(!h.is_modified());
+
+        h.record(change(0, "a"));
+        h.record(change(1, "b"));
+        h.commit();
+        h.record(change(2, "c"));
+
+        assert!(h.is_modified());
+        assert_eq!(h.undo(), Some(vec![change(2, "c")]));
+        assert_eq!(h.undo(), Some(vec![change(0, "a"), change(1, "b")]));
+        assert_eq!(h.undo(), None);
+        assert!(!h.is_modified());
+
+        assert_eq!(h.redo(), Some(vec![change(0, "a"), change(1, "b")]));
+        h.record(change(2, "d"));
+        assert_eq!(h.redo(), None);
+    }
+
+    #[test]
+    fn saved_state() {
+        let mut h = History::new();
+        h.record(change(0, "a"));
+        h.mark_saved();
+        assert!(!h.is_modified());
+
+        h.undo();
+        assert!(h.is_modified());
+        h.redo();
+        assert!(!h.is_modified());
+    }
+}
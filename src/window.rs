@@ -0,0 +1,338 @@
+use crate::render::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Split {
+    // windows stacked on top of each other (:split)
+    Horizontal,
+    // windows side by side (:vsplit)
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+pub struct Window {
+    pub id: usize,
+    // id of the displayed buffer
+    pub buffer: usize,
+    // (line, column) of the cursor while the window is not active
+    pub cursor: (usize, usize),
+}
+
+// split children carry a weight that determines their share of the area
+enum Layout {
+    Window(usize),
+    Split(Split, Vec<(f32, Layout)>),
+}
+
+impl Layout {
+    fn split(&mut self, target: usize, split: Split, new: usize) -> bool {
+        match self {
+            Layout::Window(id) if *id == target => {
+                *self = Layout::Split(
+                    split,
+                    vec![(1.0, Layout::Window(new)), (1.0, Layout::Window(target))],
+                );
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split(dir, children) => {
+                if *dir == split {
+                    if let Some(idx) = children
+                        .iter()
+                        .position(|(_, child)| matches!(child, Layout::Window(id) if *id == target))
+                    {
+                        let weight = children[idx].0 / 2.0;
+                        children[idx].0 = weight;
+                        children.insert(idx, (weight, Layout::Window(new)));
+                        return true;
+                    }
+                }
+                children
+                    .iter_mut()
+                    .any(|(_, child)| child.split(target, split, new))
+            }
+        }
+    }
+
+    fn remove(&mut self, target: usize) -> bool {
+        let Layout::Split(_, children) = self else {
+            return false;
+        };
+
+        let removed = match children
+            .iter()
+            .position(|(_, child)| matches!(child, Layout::Window(id) if *id == target))
+        {
+            Some(idx) => {
+                let (weight, _) = children.remove(idx);
+                children[idx.saturating_sub(1)].0 += weight;
+                true
+            }
+            None => children.iter_mut().any(|(_, child)| child.remove(target)),
+        };
+
+        if children.len() == 1 {
+            let (_, child) = children.pop().unwrap();
+            *self = child;
+        }
+        removed
+    }
+
+    fn equalize(&mut self) {
+        if let Layout::Split(_, children) = self {
+            for (weight, child) in children {
+                *weight = 1.0;
+                child.equalize();
+            }
+        }
+    }
+
+    fn rects(&self, area: Rect, out: &mut Vec<(usize, Rect)>) {
+        match self {
+            Layout::Window(id) => out.push((*id, area)),
+            Layout::Split(split, children) => {
+                let total = children.iter().map(|(weight, _)| weight).sum::<f32>();
+                let mut offset = 0.0;
+
+                for (weight, child) in children {
+                    let part = weight / total;
+                    let rect = match split {
+                        // the first window is at the top
+                        Split::Horizontal => Rect::new(
+                            (area.pos.x, area.pos.y + area.size.y * (1.0 - offset - part)),
+                            (area.size.x, area.size.y * part),
+                        ),
+                        Split::Vertical => Rect::new(
+                            (area.pos.x + area.size.x * offset, area.pos.y),
+                            (area.size.x * part, area.size.y),
+                        ),
+                    };
+                    offset += part;
+                    child.rects(rect, out);
+                }
+            }
+        }
+    }
+}
+
+// the window layout of a tab page, window ids are handed out by the
+// editor so that they are unique across tab pages
+pub struct Windows {
+    layout: Layout,
+    windows: Vec<Window>,
+    current: usize,
+}
+
+impl Windows {
+    pub fn new(id: usize, buffer: usize) -> Windows {
+        Windows {
+            layout: Layout::Window(id),
+            windows: vec![Window {
+                id,
+                buffer,
+                cursor: (0, 0),
+            }],
+            current: id,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Window> {
+        self.windows.iter().find(|window| window.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Window> {
+        self.windows.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Window> {
+        self.windows.iter_mut()
+    }
+
+    pub fn set_current(&mut self, id: usize) {
+        if self.get(id).is_some() {
+            self.current = id;
+        }
+    }
+
+    // splits the current window, the new window is placed above or
+    // to the left of it
+    pub fn split(&mut self, id: usize, split: Split, buffer: usize, cursor: (usize, usize)) {
+        self.layout.split(self.current, split, id);
+        self.windows.push(Window { id, buffer, cursor });
+    }
+
+    pub fn close(&mut self, id: usize) -> bool {
+        if self.windows.len() < 2 || !self.layout.remove(id) {
+            return false;
+        }
+
+        self.windows.retain(|window| window.id != id);
+        if self.current == id {
+            self.current = self.ids()[0];
+        }
+        true
+    }
+
+    pub fn only(&mut self) {
+        self.layout = Layout::Window(self.current);
+        self.windows.retain(|window| window.id == self.current);
+    }
+
+    pub fn equalize(&mut self) {
+        self.layout.equalize();
+    }
+
+    // window ids in layout order, from the top left
+    pub fn ids(&self) -> Vec<usize> {
+        self.rects(Rect::default())
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    pub fn rects(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut rects = Vec::new();
+        self.layout.rects(area, &mut rects);
+        rects
+    }
+
+    pub fn next(&self) -> usize {
+        let ids = self.ids();
+        let idx = ids.iter().position(|id| *id == self.current).unwrap_or(0);
+        ids[(idx + 1) % ids.len()]
+    }
+
+    // the window adjacent to the current one in the given direction
+    pub fn neighbor(&self, direction: Direction) -> Option<usize> {
+        const EPS: f32 = 1e-4;
+
+        let rects = self.rects(Rect::new((0.0, 0.0), (1.0, 1.0)));
+        let (_, current) = rects.iter().find(|(id, _)| *id == self.current)?;
+
+        let candidates = rects.iter().filter(|(_, rect)| match direction {
+            Direction::Left => (rect.right() - current.pos.x).abs() < EPS,
+            Direction::Right => (rect.pos.x - current.right()).abs() < EPS,
+            Direction::Up => (rect.pos.y - current.top()).abs() < EPS,
+            Direction::Down => (rect.top() - current.pos.y).abs() < EPS,
+        });
+
+        // prefer the window next to the top left corner of the current one
+        let (id, _) = candidates
+            .filter(|(_, rect)| match direction {
+                Direction::Left | Direction::Right => {
+                    rect.pos.y < current.top() && rect.top() > current.pos.y
+                }
+                Direction::Up | Direction::Down => {
+                    rect.pos.x < current.right() && rect.right() > current.pos.x
+                }
+            })
+            .min_by(|(_, a), (_, b)| match direction {
+                Direction::Left | Direction::Right => (current.top() - a.top())
+                    .abs()
+                    .total_cmp(&(current.top() - b.top()).abs()),
+                Direction::Up | Direction::Down => (current.pos.x - a.pos.x)
+                    .abs()
+                    .total_cmp(&(current.pos.x - b.pos.x).abs()),
+            })?;
+        Some(*id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Split, Windows};
+    use crate::render::Rect;
+
+    #[test]
+    fn split_layout() {
+        let mut w = Windows::new(1, 1);
+        let (top, left) = (2, 3);
+        w.split(top, Split::Horizontal, 1, (0, 0));
+        w.set_current(top);
+        w.split(left, Split::Vertical, 1, (0, 0));
+
+        assert_eq!(w.ids(), vec![left, top, 1]);
+        assert_eq!(
+            w.rects(Rect::new((0.0, 0.0), (800.0, 600.0))),
+            vec![
+                (left, Rect::new((0.0, 300.0), (400.0, 300.0))),
+                (top, Rect::new((400.0, 300.0), (400.0, 300.0))),
+                (1, Rect::new((0.0, 0.0), (800.0, 300.0))),
+            ]
+        );
+
+        assert!(w.close(top));
+        assert_eq!(
+            w.rects(Rect::new((0.0, 0.0), (800.0, 600.0))),
+            vec![
+                (left, Rect::new((0.0, 300.0), (800.0, 300.0))),
+                (1, Rect::new((0.0, 0.0), (800.0, 300.0))),
+            ]
+        );
+
+        w.only();
+        assert_eq!(w.ids(), vec![left]);
+        assert!(!w.close(left));
+    }
+
+    #[test]
+    fn split_weights() {
+        let mut w = Windows::new(1, 1);
+        w.split(2, Split::Vertical, 1, (0, 0));
+        w.split(3, Split::Vertical, 1, (0, 0));
+
+        let widths = |w: &Windows| {
+            w.rects(Rect::new((0.0, 0.0), (300.0, 100.0)))
+                .iter()
+                .map(|(_, rect)| rect.size.x)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(widths(&w), vec![150.0, 75.0, 75.0]);
+
+        w.equalize();
+        assert_eq!(widths(&w), vec![100.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn neighbors() {
+        let mut w = Windows::new(1, 1);
+        let (right, left, top_right) = (1, 2, 3);
+        w.split(left, Split::Vertical, 1, (0, 0));
+        w.split(top_right, Split::Horizontal, 1, (0, 0));
+
+        // current window is still the bottom right one
+        assert_eq!(w.neighbor(Direction::Up), Some(top_right));
+        assert_eq!(w.neighbor(Direction::Left), Some(left));
+        assert_eq!(w.neighbor(Direction::Right), None);
+
+        w.set_current(left);
+        assert_eq!(w.neighbor(Direction::Right), Some(top_right));
+        assert_eq!(w.neighbor(Direction::Down), None);
+        assert_eq!(w.next(), top_right);
+
+        w.set_current(top_right);
+        assert_eq!(w.neighbor(Direction::Down), Some(right));
+    }
+}